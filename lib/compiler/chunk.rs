@@ -0,0 +1,263 @@
+use crate::interpreter::Value;
+use crate::lexer::Token;
+use crate::source::Span;
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[repr(u8)]
+pub enum OpCode {
+    Constant,
+    True,
+    False,
+    Pop,
+    Slide,
+    GetLocal,
+    GetCapture,
+    GetCurrent,
+    GetGlobal,
+    DefineGlobal,
+    GetImport,
+    Construct,
+    Record,
+    Tuple,
+    GetField,
+    Index,
+    IsVariant,
+    IsEqual,
+    Closure,
+    Call,
+    Return,
+    Negate,
+    Not,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Jump,
+    JumpIfFalse,
+    NoMatch,
+    Print,
+    SetResult,
+    ClearResult,
+    Halt,
+}
+
+pub(crate) const OPCODES: [OpCode; 40] = [
+    OpCode::Constant,
+    OpCode::True,
+    OpCode::False,
+    OpCode::Pop,
+    OpCode::Slide,
+    OpCode::GetLocal,
+    OpCode::GetCapture,
+    OpCode::GetCurrent,
+    OpCode::GetGlobal,
+    OpCode::DefineGlobal,
+    OpCode::GetImport,
+    OpCode::Construct,
+    OpCode::Record,
+    OpCode::Tuple,
+    OpCode::GetField,
+    OpCode::Index,
+    OpCode::IsVariant,
+    OpCode::IsEqual,
+    OpCode::Closure,
+    OpCode::Call,
+    OpCode::Return,
+    OpCode::Negate,
+    OpCode::Not,
+    OpCode::Add,
+    OpCode::Subtract,
+    OpCode::Multiply,
+    OpCode::Divide,
+    OpCode::Equal,
+    OpCode::NotEqual,
+    OpCode::Greater,
+    OpCode::GreaterEqual,
+    OpCode::Less,
+    OpCode::LessEqual,
+    OpCode::Jump,
+    OpCode::JumpIfFalse,
+    OpCode::NoMatch,
+    OpCode::Print,
+    OpCode::SetResult,
+    OpCode::ClearResult,
+    OpCode::Halt,
+];
+
+impl OpCode {
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        OPCODES.get(byte as usize).copied()
+    }
+
+    /// The width in bytes of each operand following the opcode.
+    pub fn operands(self) -> &'static [usize] {
+        match self {
+            OpCode::Slide | OpCode::Record | OpCode::Tuple | OpCode::Index | OpCode::Call => &[1],
+            OpCode::Constant
+            | OpCode::GetLocal
+            | OpCode::GetCapture
+            | OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::GetField
+            | OpCode::IsEqual
+            | OpCode::Closure
+            | OpCode::Jump
+            | OpCode::JumpIfFalse => &[2],
+            OpCode::Construct | OpCode::IsVariant => &[2, 1],
+            OpCode::GetImport => &[2, 2],
+            _ => &[],
+        }
+    }
+
+    /// How many values the instruction leaves on the stack, minus how many
+    /// it takes off.
+    pub fn stack_effect(self, operands: &[usize]) -> isize {
+        let count = || operands[operands.len() - 1] as isize;
+        match self {
+            OpCode::Constant
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetLocal
+            | OpCode::GetCapture
+            | OpCode::GetCurrent
+            | OpCode::GetGlobal
+            | OpCode::GetImport
+            | OpCode::Closure => 1,
+            OpCode::Pop
+            | OpCode::DefineGlobal
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide
+            | OpCode::Equal
+            | OpCode::NotEqual
+            | OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::JumpIfFalse
+            | OpCode::Print
+            | OpCode::SetResult
+            | OpCode::Return => -1,
+            OpCode::Slide | OpCode::Call => -count(),
+            OpCode::Construct | OpCode::Tuple => 1 - count(),
+            OpCode::Record => 1 - 2 * count(),
+            _ => 0,
+        }
+    }
+}
+
+/// Where a function finds a value it closes over, or where a name resolves
+/// to relative to the function being compiled.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum Capture {
+    /// A slot in the enclosing function's frame.
+    Local(usize),
+    /// One of the enclosing function's own captures.
+    Capture(usize),
+    /// The enclosing function itself, for recursive `let` bindings.
+    Current,
+}
+
+/// Where in the source an instruction came from.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub struct Position {
+    pub line: u32,
+    pub col: u32,
+    pub span: Span,
+}
+
+impl From<&Token> for Position {
+    fn from(token: &Token) -> Self {
+        Position {
+            line: token.line,
+            col: token.col,
+            span: token.span,
+        }
+    }
+}
+
+/// Bytecode with the constants it refers to and a table mapping code
+/// offsets back to source positions.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    pub functions: Vec<Rc<Function>>,
+    positions: Vec<(usize, Position)>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, position: Position) {
+        let offset = self.code.len();
+        match self.positions.last() {
+            Some((_, last)) if *last == position => {}
+            _ => self.positions.push((offset, position)),
+        }
+        self.code.push(byte);
+    }
+
+    /// Returns the index of `value` in the constant pool, adding it if needed.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        match self
+            .constants
+            .iter()
+            .position(|constant| *constant == value)
+        {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        }
+    }
+
+    pub fn add_function(&mut self, function: Function) -> usize {
+        self.functions.push(Rc::new(function));
+        self.functions.len() - 1
+    }
+
+    /// The source that produced the code at `offset`.
+    pub fn position(&self, offset: usize) -> Position {
+        let index = self
+            .positions
+            .partition_point(|(start, _)| *start <= offset);
+        match index.checked_sub(1) {
+            Some(index) => self.positions[index].1,
+            None => Position::default(),
+        }
+    }
+
+    pub fn read_u8(&self, offset: usize) -> usize {
+        self.code[offset] as usize
+    }
+
+    pub fn read_u16(&self, offset: usize) -> usize {
+        u16::from_le_bytes([self.code[offset], self.code[offset + 1]]) as usize
+    }
+}
+
+/// A compiled function body together with what it captures when a closure
+/// is created from it.
+#[derive(Debug, Default)]
+pub struct Function {
+    pub arity: usize,
+    pub chunk: Chunk,
+    pub captures: Vec<Capture>,
+    /// The source of the function, shown when it is printed.
+    pub source: String,
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
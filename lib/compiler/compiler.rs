@@ -0,0 +1,602 @@
+use super::{Capture, CompileError, Function, OpCode, Position};
+use crate::diagnostics::ErrorCode;
+use crate::interpreter::{literal, Value};
+use crate::lexer::{Token, TokenKind};
+use crate::parser::{Expression, MatchArm, Pattern, Program, Statement};
+use std::rc::Rc;
+
+struct Local {
+    name: String,
+    slot: usize,
+    depth: usize,
+}
+
+// The state of one function while its body is being compiled.
+#[derive(Default)]
+struct Scope {
+    function: Function,
+    locals: Vec<Local>,
+    // Names of the function's captures, in the order of `function.captures`.
+    captured: Vec<String>,
+    // The name a recursive local `let` binds the function to.
+    self_name: Option<String>,
+    depth: usize,
+    // How many values are on the stack above the frame's base.
+    height: usize,
+}
+
+// A step from a match subject to one of its parts.
+#[derive(Clone)]
+enum Step {
+    Index(usize),
+    Field(String),
+}
+
+/// Lowers a checked program to bytecode. Top level `let` bindings become
+/// globals; everything else lives on the stack.
+#[derive(Default)]
+pub struct Compiler {
+    scopes: Vec<Scope>,
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler::default()
+    }
+
+    /// Compiles `program` into the function that runs a module's top level.
+    pub fn compile(&mut self, program: &Program) -> Result<Function, CompileError> {
+        self.scopes = vec![Scope::default()];
+        self.scope_mut().function.source = String::from("<script>");
+
+        for statement in &program.statements {
+            self.statement(statement)?;
+        }
+
+        let chunk = &mut self.scope_mut().function.chunk;
+        let position = chunk.position(chunk.code.len());
+        chunk.write(OpCode::Halt as u8, position);
+
+        Ok(self.scopes.pop().unwrap_or_default().function)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Result<(), CompileError> {
+        match statement {
+            Statement::Let {
+                name, initializer, ..
+            } => {
+                match initializer.as_ref() {
+                    // Globals are looked up by name, so only locals need to
+                    // know they are recursive.
+                    Expression::Lambda {
+                        arrow,
+                        params,
+                        body,
+                        ..
+                    } if !self.is_global() => {
+                        self.lambda(initializer, arrow, params, body, Some(name.to_string()))?
+                    }
+                    _ => self.expression(initializer)?,
+                }
+                self.define(name)?;
+                self.emit(OpCode::ClearResult, &[], name)?;
+            }
+            Statement::Expression { expr } => {
+                self.expression(expr)?;
+                self.emit(OpCode::SetResult, &[], expr.token())?;
+            }
+            Statement::Print { expr } => {
+                self.expression(expr)?;
+                self.emit(OpCode::Print, &[], expr.token())?;
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let token = condition.token();
+                self.expression(condition)?;
+                let then_jump = self.emit(OpCode::JumpIfFalse, &[0], token)?;
+                self.block(then_branch, token)?;
+                let else_jump = self.emit(OpCode::Jump, &[0], token)?;
+                self.patch_jump(then_jump, token)?;
+                match else_branch {
+                    Some(else_branch) => self.block(else_branch, token)?,
+                    None => {
+                        self.emit(OpCode::ClearResult, &[], token)?;
+                    }
+                }
+                self.patch_jump(else_jump, token)?;
+            }
+            Statement::Type { name, .. } => {
+                self.emit(OpCode::ClearResult, &[], name)?;
+            }
+            Statement::Import { names, path } => {
+                let module = self.name(path);
+                // Types and constructors have no runtime value to import.
+                for name in names.iter().filter(|name| !is_constructor_name(name)) {
+                    let index = self.name(name);
+                    self.emit(OpCode::GetImport, &[module, index], name)?;
+                    self.define(name)?;
+                }
+                self.emit(OpCode::ClearResult, &[], path)?;
+            }
+            Statement::Export { statement } => self.statement(statement)?,
+        }
+
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement], token: &Token) -> Result<(), CompileError> {
+        self.scope_mut().depth += 1;
+        if statements.is_empty() {
+            self.emit(OpCode::ClearResult, &[], token)?;
+        }
+        for statement in statements {
+            self.statement(statement)?;
+        }
+
+        let scope = self.scope_mut();
+        scope.depth -= 1;
+        let depth = scope.depth;
+        while self
+            .scope_mut()
+            .locals
+            .last()
+            .is_some_and(|local| local.depth > depth)
+        {
+            self.scope_mut().locals.pop();
+            self.emit(OpCode::Pop, &[], token)?;
+        }
+
+        Ok(())
+    }
+
+    fn expression(&mut self, expr: &Expression) -> Result<(), CompileError> {
+        match expr {
+            Expression::Literal { lit, .. } => match lit.kind {
+                TokenKind::True => self.emit(OpCode::True, &[], lit).map(|_| ()),
+                TokenKind::False => self.emit(OpCode::False, &[], lit).map(|_| ()),
+                _ => {
+                    let index = self.literal(lit)?;
+                    self.emit(OpCode::Constant, &[index], lit).map(|_| ())
+                }
+            },
+            Expression::Variable { name, .. } => {
+                let level = self.scopes.len() - 1;
+                match self.resolve(level, &name.to_string()) {
+                    Some(Capture::Local(slot)) => self.emit(OpCode::GetLocal, &[slot], name),
+                    Some(Capture::Capture(index)) => self.emit(OpCode::GetCapture, &[index], name),
+                    Some(Capture::Current) => self.emit(OpCode::GetCurrent, &[], name),
+                    None => {
+                        let index = self.name(name);
+                        self.emit(OpCode::GetGlobal, &[index], name)
+                    }
+                }
+                .map(|_| ())
+            }
+            Expression::Grouping { expr, .. } => self.expression(expr),
+            Expression::Construct { name, args, .. } => {
+                for arg in args {
+                    self.expression(arg)?;
+                }
+                let index = self.name(name);
+                self.emit(OpCode::Construct, &[index, args.len()], name)
+                    .map(|_| ())
+            }
+            Expression::Record { brace, fields, .. } => {
+                for (name, value) in fields {
+                    let index = self.name(name);
+                    self.emit(OpCode::Constant, &[index], name)?;
+                    self.expression(value)?;
+                }
+                self.emit(OpCode::Record, &[fields.len()], brace)
+                    .map(|_| ())
+            }
+            Expression::Get { object, name, .. } => {
+                self.expression(object)?;
+                let index = self.name(name);
+                self.emit(OpCode::GetField, &[index], name).map(|_| ())
+            }
+            Expression::Tuple {
+                paren, elements, ..
+            } => {
+                for element in elements {
+                    self.expression(element)?;
+                }
+                self.emit(OpCode::Tuple, &[elements.len()], paren)
+                    .map(|_| ())
+            }
+            Expression::Match {
+                keyword,
+                subject,
+                arms,
+                ..
+            } => self.match_expression(keyword, subject, arms),
+            Expression::Lambda {
+                arrow,
+                params,
+                body,
+                ..
+            } => self.lambda(expr, arrow, params, body, None),
+            Expression::Call {
+                callee,
+                paren,
+                args,
+                ..
+            } => {
+                self.expression(callee)?;
+                for arg in args {
+                    self.expression(arg)?;
+                }
+                self.emit(OpCode::Call, &[args.len()], paren).map(|_| ())
+            }
+            Expression::Unary { op, right, .. } => {
+                self.expression(right)?;
+                let code = match op.kind {
+                    TokenKind::Bang => OpCode::Not,
+                    _ => OpCode::Negate,
+                };
+                self.emit(code, &[], op).map(|_| ())
+            }
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                self.expression(left)?;
+                self.expression(right)?;
+                let code = match op.kind {
+                    TokenKind::Plus => OpCode::Add,
+                    TokenKind::Minus => OpCode::Subtract,
+                    TokenKind::Star => OpCode::Multiply,
+                    TokenKind::Slash => OpCode::Divide,
+                    TokenKind::EqualEqual => OpCode::Equal,
+                    TokenKind::BangEqual => OpCode::NotEqual,
+                    TokenKind::Greater => OpCode::Greater,
+                    TokenKind::GreaterEqual => OpCode::GreaterEqual,
+                    TokenKind::Less => OpCode::Less,
+                    TokenKind::LessEqual => OpCode::LessEqual,
+                    _ => {
+                        return Err(CompileError::new(
+                            ErrorCode::InvalidProgram,
+                            op,
+                            format!("Unknown operator \"{}\"", op),
+                        ))
+                    }
+                };
+                self.emit(code, &[], op).map(|_| ())
+            }
+        }
+    }
+
+    fn lambda(
+        &mut self,
+        expr: &Expression,
+        arrow: &Token,
+        params: &[Token],
+        body: &Rc<Expression>,
+        self_name: Option<String>,
+    ) -> Result<(), CompileError> {
+        let mut scope = Scope {
+            self_name,
+            height: params.len(),
+            ..Scope::default()
+        };
+        scope.function.arity = params.len();
+        scope.function.source = expr.to_string();
+        for (slot, param) in params.iter().enumerate() {
+            scope.locals.push(Local {
+                name: param.to_string(),
+                slot,
+                depth: 0,
+            });
+        }
+
+        self.scopes.push(scope);
+        self.expression(body)?;
+        self.emit(OpCode::Return, &[], arrow)?;
+        let function = self.scopes.pop().unwrap_or_default().function;
+
+        let index = self.scope_mut().function.chunk.add_function(function);
+        self.emit(OpCode::Closure, &[index], arrow).map(|_| ())
+    }
+
+    // The subject stays in a hidden local while the arms are tried in order.
+    // Each arm first tests the whole pattern, then binds its variables.
+    fn match_expression(
+        &mut self,
+        keyword: &Token,
+        subject: &Expression,
+        arms: &[MatchArm],
+    ) -> Result<(), CompileError> {
+        self.expression(subject)?;
+        let slot = self.scope_mut().height - 1;
+        let depth = self.scope_mut().depth;
+        self.scope_mut().locals.push(Local {
+            name: String::new(),
+            slot,
+            depth,
+        });
+        let locals = self.scope_mut().locals.len();
+
+        let mut ends = vec![];
+        for arm in arms {
+            let mut failures = vec![];
+            let mut bindings = vec![];
+            self.pattern(
+                &arm.pattern,
+                slot,
+                &mut vec![],
+                &mut failures,
+                &mut bindings,
+            )?;
+
+            for (name, path) in &bindings {
+                self.load(slot, path, name)?;
+                self.define_local(name);
+            }
+            self.expression(&arm.body)?;
+            self.emit(OpCode::Slide, &[bindings.len() + 1], keyword)?;
+            self.scope_mut().locals.truncate(locals);
+            ends.push(self.emit(OpCode::Jump, &[0], keyword)?);
+
+            // A failed arm leaves only the subject on the stack, just as the
+            // slide after a successful one leaves only the result.
+            for failure in failures {
+                self.patch_jump(failure, keyword)?;
+            }
+        }
+        self.emit(OpCode::NoMatch, &[], keyword)?;
+        for end in ends {
+            self.patch_jump(end, keyword)?;
+        }
+
+        self.scope_mut().locals.pop();
+        Ok(())
+    }
+
+    fn pattern(
+        &mut self,
+        pattern: &Pattern,
+        slot: usize,
+        path: &mut Vec<Step>,
+        failures: &mut Vec<usize>,
+        bindings: &mut Vec<(Token, Vec<Step>)>,
+    ) -> Result<(), CompileError> {
+        match pattern {
+            Pattern::Wildcard { .. } => {}
+            Pattern::Binding { name } => bindings.push((name.to_owned(), path.to_owned())),
+            Pattern::Literal { lit } => {
+                self.load(slot, path, lit)?;
+                let index = self.literal(lit)?;
+                self.emit(OpCode::IsEqual, &[index], lit)?;
+                failures.push(self.emit(OpCode::JumpIfFalse, &[0], lit)?);
+            }
+            Pattern::Constructor { name, args } => {
+                self.load(slot, path, name)?;
+                let index = self.name(name);
+                self.emit(OpCode::IsVariant, &[index, args.len()], name)?;
+                failures.push(self.emit(OpCode::JumpIfFalse, &[0], name)?);
+                for (i, arg) in args.iter().enumerate() {
+                    path.push(Step::Index(i));
+                    self.pattern(arg, slot, path, failures, bindings)?;
+                    path.pop();
+                }
+            }
+            Pattern::Record { fields, .. } => {
+                for (name, field) in fields {
+                    path.push(Step::Field(name.to_string()));
+                    self.pattern(field, slot, path, failures, bindings)?;
+                    path.pop();
+                }
+            }
+            Pattern::Tuple { elements, .. } => {
+                for (i, element) in elements.iter().enumerate() {
+                    path.push(Step::Index(i));
+                    self.pattern(element, slot, path, failures, bindings)?;
+                    path.pop();
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Pushes the part of the subject in `slot` that `path` leads to.
+    fn load(&mut self, slot: usize, path: &[Step], token: &Token) -> Result<(), CompileError> {
+        self.emit(OpCode::GetLocal, &[slot], token)?;
+        for step in path {
+            match step {
+                Step::Index(i) => self.emit(OpCode::Index, &[*i], token)?,
+                Step::Field(name) => {
+                    let index = self
+                        .scope_mut()
+                        .function
+                        .chunk
+                        .add_constant(Value::Str(name.to_owned()));
+                    self.emit(OpCode::GetField, &[index], token)?
+                }
+            };
+        }
+        Ok(())
+    }
+
+    // Finds where `name` lives as seen from the function at `level`, adding
+    // captures to every function in between.
+    fn resolve(&mut self, level: usize, name: &str) -> Option<Capture> {
+        let scope = &self.scopes[level];
+        if let Some(local) = scope.locals.iter().rev().find(|local| local.name == name) {
+            return Some(Capture::Local(local.slot));
+        }
+        if scope.self_name.as_deref() == Some(name) {
+            return Some(Capture::Current);
+        }
+        if let Some(index) = scope.captured.iter().position(|captured| captured == name) {
+            return Some(Capture::Capture(index));
+        }
+        if level == 0 {
+            return None;
+        }
+
+        let capture = self.resolve(level - 1, name)?;
+        let scope = &mut self.scopes[level];
+        scope.function.captures.push(capture);
+        scope.captured.push(name.to_owned());
+        Some(Capture::Capture(scope.captured.len() - 1))
+    }
+
+    // Binds the value on top of the stack to `name`.
+    fn define(&mut self, name: &Token) -> Result<(), CompileError> {
+        if self.is_global() {
+            let index = self.name(name);
+            self.emit(OpCode::DefineGlobal, &[index], name)?;
+        } else {
+            self.define_local(name);
+        }
+        Ok(())
+    }
+
+    fn define_local(&mut self, name: &Token) {
+        let scope = self.scope_mut();
+        scope.locals.push(Local {
+            name: name.to_string(),
+            slot: scope.height - 1,
+            depth: scope.depth,
+        });
+    }
+
+    fn is_global(&self) -> bool {
+        self.scopes.len() == 1 && self.scopes[0].depth == 0
+    }
+
+    fn literal(&mut self, lit: &Token) -> Result<usize, CompileError> {
+        let value = literal(lit).map_err(|err| CompileError::new(err.code, lit, err.text))?;
+        Ok(self.scope_mut().function.chunk.add_constant(value))
+    }
+
+    fn name(&mut self, token: &Token) -> usize {
+        self.scope_mut()
+            .function
+            .chunk
+            .add_constant(Value::Str(token.to_string()))
+    }
+
+    // Writes an instruction, returning its offset.
+    fn emit(
+        &mut self,
+        code: OpCode,
+        operands: &[usize],
+        token: &Token,
+    ) -> Result<usize, CompileError> {
+        let scope = self.scope_mut();
+        let chunk = &mut scope.function.chunk;
+        let offset = chunk.code.len();
+
+        chunk.write(code as u8, Position::from(token));
+        for (operand, width) in operands.iter().zip(code.operands()) {
+            if *operand >> (8 * width) != 0 {
+                return Err(CompileError::new(
+                    ErrorCode::TooLarge,
+                    token,
+                    String::from("Expression is too large to compile"),
+                ));
+            }
+            for byte in &operand.to_le_bytes()[..*width] {
+                chunk.write(*byte, Position::from(token));
+            }
+        }
+
+        scope.height = (scope.height as isize + code.stack_effect(operands)) as usize;
+        Ok(offset)
+    }
+
+    // Points the jump at `offset` to the next instruction.
+    fn patch_jump(&mut self, offset: usize, token: &Token) -> Result<(), CompileError> {
+        let code = &mut self.scope_mut().function.chunk.code;
+        let distance = code.len() - offset - 3;
+        if distance > u16::MAX as usize {
+            return Err(CompileError::new(
+                ErrorCode::TooLarge,
+                token,
+                String::from("Too much code to jump over"),
+            ));
+        }
+        code[offset + 1..offset + 3].copy_from_slice(&(distance as u16).to_le_bytes());
+        Ok(())
+    }
+
+    fn scope_mut(&mut self) -> &mut Scope {
+        self.scopes
+            .last_mut()
+            .expect("The compiler always has a scope")
+    }
+}
+
+fn is_constructor_name(token: &Token) -> bool {
+    token
+        .literal
+        .as_ref()
+        .and_then(|name| name.chars().next())
+        .is_some_and(|c| c.is_ascii_uppercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compiler::chunk::OPCODES;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn compile(source: &str) -> Function {
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        Compiler::new().compile(&program).unwrap()
+    }
+
+    #[test]
+    fn emits_compact_code_with_a_constant_pool() {
+        let function = compile("let x = 1;\n1 + x");
+        let chunk = &function.chunk;
+
+        assert_eq!(
+            chunk.code,
+            vec![
+                OpCode::Constant as u8,
+                0,
+                0,
+                OpCode::DefineGlobal as u8,
+                1,
+                0,
+                OpCode::ClearResult as u8,
+                OpCode::Constant as u8,
+                0,
+                0,
+                OpCode::GetGlobal as u8,
+                1,
+                0,
+                OpCode::Add as u8,
+                OpCode::SetResult as u8,
+                OpCode::Halt as u8,
+            ]
+        );
+        assert_eq!(
+            chunk.constants,
+            vec![Value::Num(1.0), Value::Str("x".to_string())]
+        );
+        assert_eq!(chunk.position(0).line, chunk.position(6).line);
+        assert!(chunk.position(7).line > chunk.position(6).line);
+    }
+
+    #[test]
+    fn captures_enclosing_locals_and_itself() {
+        let function =
+            compile("if true { let n = 1; let f = x => match x { 0 => n, _ => f(x - 1) }; f(2) }");
+        let closure = &function.chunk.functions[0];
+
+        assert_eq!(closure.arity, 1);
+        assert_eq!(closure.captures, vec![Capture::Local(0)]);
+        assert!(closure.chunk.code.contains(&(OpCode::GetCurrent as u8)));
+        assert!(OPCODES
+            .iter()
+            .all(|code| OpCode::from_byte(*code as u8) == Some(*code)));
+    }
+}
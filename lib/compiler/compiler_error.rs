@@ -0,0 +1,48 @@
+use crate::diagnostics::{Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::lexer::Token;
+use crate::source::Span;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub struct CompileError {
+    pub code: ErrorCode,
+    pub line: u32,
+    pub col: u32,
+    pub span: Span,
+    pub text: String,
+}
+
+impl CompileError {
+    pub fn new(code: ErrorCode, token: &Token, text: String) -> Self {
+        CompileError {
+            code,
+            line: token.line,
+            col: token.col,
+            span: token.span,
+            text,
+        }
+    }
+}
+
+impl Display for CompileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Compile error \"{0}\" at line {1}, column {2}.",
+            self.text, self.line, self.col
+        )
+    }
+}
+
+impl Error for CompileError {}
+
+impl ToDiagnostics for CompileError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        vec![Diagnostic::error(&self.text)
+            .with_code(self.code)
+            .with_label(Label::primary(self.span, ""))]
+    }
+}
@@ -0,0 +1,7 @@
+mod chunk;
+mod compiler;
+mod compiler_error;
+
+pub use chunk::{Capture, Chunk, Function, OpCode, Position};
+pub use compiler::Compiler;
+pub use compiler_error::CompileError;
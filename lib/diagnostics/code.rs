@@ -0,0 +1,154 @@
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! error_codes {
+    ($($(#[$doc:meta])* $name:ident = $code:literal,)*) => {
+        /// A stable identifier for each kind of error, so documentation and
+        /// tools can refer to it. `--explain` prints what each one means.
+        #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+        pub enum ErrorCode {
+            $($(#[$doc])* $name,)*
+        }
+
+        impl ErrorCode {
+            pub const ALL: &'static [ErrorCode] = &[$(ErrorCode::$name,)*];
+
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$name => $code,)*
+                }
+            }
+
+            /// A longer description of the error with examples, in Markdown.
+            pub fn explanation(self) -> &'static str {
+                match self {
+                    $(ErrorCode::$name => include_str!(concat!("explanations/", $code, ".md")),)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// A character that starts no token.
+    UnknownCharacter = "L0001",
+    /// A string or template literal missing its closing quote.
+    UnterminatedString = "L0002",
+    /// Source text that is not valid UTF-8.
+    InvalidUtf8 = "L0003",
+    /// A backslash escape a string does not understand.
+    InvalidEscape = "L0004",
+    /// A number literal with a missing or invalid digit.
+    InvalidNumber = "L0005",
+    /// A block comment missing its closing `*/`.
+    UnterminatedComment = "L0006",
+
+    /// A specific token such as `)` or `=` is missing.
+    ExpectedToken = "P0001",
+    /// Something other than an expression where one must start.
+    ExpectedExpression = "P0002",
+    /// Something other than a pattern in a match arm.
+    ExpectedPattern = "P0003",
+    /// A name is missing where a variable, field, type or parameter is declared.
+    ExpectedName = "P0004",
+    /// A constructor declared with a lowercase name.
+    InvalidConstructorName = "P0005",
+    /// `export` not followed by a declaration.
+    ExpectedDeclaration = "P0006",
+    /// An `import` or `export` inside a block.
+    NotTopLevel = "P0007",
+
+    /// Two types that should be the same are not.
+    Mismatch = "T0001",
+    /// A type that would have to contain itself.
+    InfiniteType = "T0002",
+    /// A variable used without being defined.
+    UndefinedVariable = "T0003",
+    /// A type name that is not declared.
+    UnknownType = "T0004",
+    /// A constructor that no type declares.
+    UndefinedConstructor = "T0005",
+    /// A function, constructor or type given the wrong number of arguments.
+    ArgumentCount = "T0006",
+    /// A call of something that is not a function.
+    NotAFunction = "T0007",
+    /// A field that no record, or not this record, declares.
+    UnknownField = "T0008",
+    /// A record built without one of its fields.
+    MissingField = "T0009",
+    /// An empty record whose type cannot be inferred.
+    EmptyRecord = "T0010",
+    /// A match that does not handle every value.
+    NonExhaustiveMatch = "T0011",
+    /// A match arm that can never be reached.
+    UnreachablePattern = "T0012",
+    /// A pattern that binds the same name twice.
+    DuplicateBinding = "T0013",
+    /// An import of a module or name that does not exist.
+    UnresolvedImport = "T0014",
+    /// A literal the checker does not understand.
+    InvalidLiteral = "T0015",
+    /// A type declared twice, or a builtin type declared again.
+    DuplicateType = "T0016",
+    /// A constructor declared twice.
+    DuplicateConstructor = "T0017",
+    /// A record field declared or given twice.
+    DuplicateField = "T0018",
+    /// A record field that another record type already declares.
+    AmbiguousField = "T0019",
+
+    /// Too many nested calls.
+    StackOverflow = "R0001",
+    /// An operation applied to a value it does not support.
+    InvalidOperand = "R0002",
+    /// A match that found no arm for its value.
+    NoMatch = "R0003",
+    /// A program that was not type checked refers to something missing.
+    InvalidProgram = "R0004",
+
+    /// Code too large for the bytecode format.
+    TooLarge = "C0001",
+
+    /// An imported file that does not exist.
+    ModuleNotFound = "M0001",
+    /// Modules that import each other.
+    ImportCycle = "M0002",
+    /// A module file that cannot be read.
+    UnreadableModule = "M0003",
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ErrorCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ErrorCode::ALL
+            .iter()
+            .find(|code| code.as_str().eq_ignore_ascii_case(s.trim()))
+            .copied()
+            .ok_or_else(|| format!("Unknown error code {}", s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_are_unique_and_explained() {
+        for (i, code) in ErrorCode::ALL.iter().enumerate() {
+            assert_eq!(code.as_str().parse::<ErrorCode>(), Ok(*code));
+            assert!(ErrorCode::ALL[i + 1..]
+                .iter()
+                .all(|other| other.as_str() != code.as_str()));
+            assert!(code.explanation().starts_with(&format!("# {}", code)));
+        }
+        assert_eq!("t0004".parse::<ErrorCode>(), Ok(ErrorCode::UnknownType));
+        assert!("X0001".parse::<ErrorCode>().is_err());
+    }
+}
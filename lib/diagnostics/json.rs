@@ -0,0 +1,96 @@
+use super::{Diagnostic, Label, LabelStyle};
+use crate::emit_js::quote;
+use crate::source::{SourceMap, Span};
+
+/// Serializes a diagnostic as a single line of JSON for tools to consume.
+///
+/// Positions are one based and `end_line`, `end_column` point just past the
+/// span. The file and span are `null` for diagnostics without a location.
+pub fn to_json(diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+    let primary = diagnostic.primary();
+    let related: Vec<String> = diagnostic
+        .labels
+        .iter()
+        .filter(|label| Some(*label) != primary)
+        .map(|label| {
+            format!(
+                "{{\"file\":{0},\"span\":{1},\"message\":{2}}}",
+                file(label.span, sources),
+                span(label.span, sources),
+                quote(&label.message)
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"severity\":{0},\"code\":{8},\"message\":{1},\"file\":{2},\"span\":{3},\"label\":{4},\"related\":[{5}],\"notes\":[{6}],\"help\":[{7}]}}",
+        quote(&diagnostic.severity.to_string()),
+        quote(&diagnostic.message),
+        primary.map_or("null".to_owned(), |label| file(label.span, sources)),
+        primary.map_or("null".to_owned(), |label| span(label.span, sources)),
+        primary
+            .filter(|label| label.style == LabelStyle::Primary && !label.message.is_empty())
+            .map_or("null".to_owned(), |label: &Label| quote(&label.message)),
+        related.join(","),
+        list(&diagnostic.notes),
+        list(&diagnostic.help),
+        diagnostic
+            .code
+            .map_or("null".to_owned(), |code| quote(code.as_str()))
+    )
+}
+
+fn file(span: Span, sources: &SourceMap) -> String {
+    sources
+        .file(span.file_id)
+        .map_or("null".to_owned(), |file| quote(&file.name))
+}
+
+fn span(span: Span, sources: &SourceMap) -> String {
+    match (sources.start(span), sources.end(span)) {
+        (Some((line, column)), Some((end_line, end_column))) => format!(
+            "{{\"start\":{0},\"end\":{1},\"line\":{2},\"column\":{3},\"end_line\":{4},\"end_column\":{5}}}",
+            span.start, span.end, line, column, end_line, end_column
+        ),
+        _ => format!(
+            "{{\"start\":{0},\"end\":{1}}}",
+            span.start, span.end
+        ),
+    }
+}
+
+fn list(texts: &[String]) -> String {
+    let texts: Vec<String> = texts.iter().map(|text| quote(text)).collect();
+    texts.join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ErrorCode;
+
+    #[test]
+    fn writes_one_object_per_diagnostic() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("a \"b\".ls", "let x = 1 +\n  \"s\";");
+        let diagnostic = Diagnostic::error("Expected Num, found Str")
+            .with_code(ErrorCode::Mismatch)
+            .with_label(Label::secondary(Span::new(id, 8, 9), "this is Num"))
+            .with_label(Label::primary(Span::new(id, 14, 17), ""))
+            .with_help("use two numbers");
+
+        assert_eq!(
+            to_json(&diagnostic, &sources),
+            "{\"severity\":\"error\",\"code\":\"T0001\",\"message\":\"Expected Num, found Str\",\"file\":\"a \\\"b\\\".ls\",\
+             \"span\":{\"start\":14,\"end\":17,\"line\":2,\"column\":3,\"end_line\":2,\"end_column\":6},\
+             \"label\":null,\"related\":[{\"file\":\"a \\\"b\\\".ls\",\
+             \"span\":{\"start\":8,\"end\":9,\"line\":1,\"column\":9,\"end_line\":1,\"end_column\":10},\
+             \"message\":\"this is Num\"}],\"notes\":[],\"help\":[\"use two numbers\"]}"
+        );
+        assert_eq!(
+            to_json(&Diagnostic::error("Import cycle"), &sources),
+            "{\"severity\":\"error\",\"code\":null,\"message\":\"Import cycle\",\"file\":null,\"span\":null,\
+             \"label\":null,\"related\":[],\"notes\":[],\"help\":[]}"
+        );
+    }
+}
@@ -0,0 +1,11 @@
+mod code;
+mod diagnostic;
+mod json;
+mod renderer;
+mod report;
+
+pub use code::ErrorCode;
+pub use diagnostic::{Diagnostic, Label, LabelStyle, Severity};
+pub use json::to_json;
+pub use renderer::Renderer;
+pub use report::{diagnostics, ToDiagnostics};
@@ -0,0 +1,270 @@
+use super::{Diagnostic, Label, LabelStyle, Severity};
+use crate::source::{FileId, SourceFile, SourceMap};
+use std::collections::BTreeMap;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+
+/// Renders diagnostics the way rustc does, quoting the lines they point at.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct Renderer {
+    color: bool,
+}
+
+impl Renderer {
+    pub fn new() -> Self {
+        Renderer::default()
+    }
+
+    /// A renderer that highlights its output with ANSI escape codes.
+    pub fn with_color(color: bool) -> Self {
+        Renderer { color }
+    }
+
+    /// Renders every diagnostic, separated by blank lines.
+    pub fn render_all(&self, diagnostics: &[Diagnostic], sources: &SourceMap) -> String {
+        let reports: Vec<String> = diagnostics
+            .iter()
+            .map(|diagnostic| self.render(diagnostic, sources))
+            .collect();
+        reports.join("\n")
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic, sources: &SourceMap) -> String {
+        let severity = self.severity_style(diagnostic.severity);
+        let heading = match diagnostic.code {
+            Some(code) => format!("{0}[{1}]", diagnostic.severity, code),
+            None => diagnostic.severity.to_string(),
+        };
+        let mut out = format!(
+            "{0}{1}\n",
+            self.paint(severity, &heading),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+
+        // Labels are grouped by file, starting with the file of the primary label.
+        let mut files: Vec<FileId> = vec![];
+        for label in diagnostic.primary().into_iter().chain(&diagnostic.labels) {
+            if !files.contains(&label.span.file_id) && sources.file(label.span.file_id).is_some() {
+                files.push(label.span.file_id);
+            }
+        }
+
+        let width = files
+            .iter()
+            .flat_map(|file_id| {
+                let file = sources.file(*file_id).unwrap();
+                diagnostic
+                    .labels
+                    .iter()
+                    .filter(move |label| label.span.file_id == *file_id)
+                    .map(move |label| file.line_col(label.span.start).0)
+            })
+            .max()
+            .map_or(0, |line| line.to_string().len());
+        let blank = " ".repeat(width);
+
+        for file_id in &files {
+            let file = sources.file(*file_id).unwrap();
+            let labels: Vec<&Label> = diagnostic
+                .labels
+                .iter()
+                .filter(|label| label.span.file_id == *file_id)
+                .collect();
+            let located = labels
+                .iter()
+                .find(|label| label.style == LabelStyle::Primary)
+                .unwrap_or(&labels[0]);
+            let (line, col) = file.line_col(located.span.start);
+            out.push_str(&format!(
+                "{0}{1} {2}:{3}:{4}\n",
+                blank,
+                self.paint(BLUE, "-->"),
+                file.name,
+                line,
+                col
+            ));
+            out.push_str(&format!("{0} {1}\n", blank, self.paint(BLUE, "|")));
+
+            let mut lines: BTreeMap<u32, Vec<&Label>> = BTreeMap::new();
+            for label in labels {
+                let line = file.line_col(label.span.start).0;
+                lines.entry(line).or_default().push(label);
+            }
+
+            let mut previous = None;
+            for (line, mut labels) in lines {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    out.push_str(&format!("{}\n", self.paint(BLUE, "...")));
+                }
+                previous = Some(line);
+
+                let text = file.line(line).unwrap_or("");
+                out.push_str(&format!(
+                    "{0} {1}\n",
+                    self.paint(BLUE, &format!("{0:>1$} |", line, width)),
+                    text
+                ));
+                labels.sort_by_key(|label| label.span.start);
+                for label in labels {
+                    out.push_str(&format!(
+                        "{0} {1} {2}\n",
+                        blank,
+                        self.paint(BLUE, "|"),
+                        self.underline(file, text, label, diagnostic.severity)
+                    ));
+                }
+            }
+        }
+
+        if !files.is_empty() && (!diagnostic.notes.is_empty() || !diagnostic.help.is_empty()) {
+            out.push_str(&format!("{0} {1}\n", blank, self.paint(BLUE, "|")));
+        }
+        for (kind, texts) in [("note", &diagnostic.notes), ("help", &diagnostic.help)] {
+            for text in texts {
+                out.push_str(&format!(
+                    "{0} {1} {2}\n",
+                    blank,
+                    self.paint(BLUE, "="),
+                    self.paint_rest(BOLD, &format!("{}:", kind), text)
+                ));
+            }
+        }
+
+        out
+    }
+
+    // Marks the part of `text` the label covers, leaving spans that continue
+    // past the end of the line underlined up to its end.
+    fn underline(
+        &self,
+        file: &SourceFile,
+        text: &str,
+        label: &Label,
+        severity: Severity,
+    ) -> String {
+        let line = file.line_col(label.span.start).0;
+        let line_start = file.line_start(line).unwrap_or(0);
+        let start = (label.span.start - line_start).min(text.len());
+        let end = (start + label.span.len()).min(text.len());
+        let padding: String = text
+            .get(..start)
+            .unwrap_or("")
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let count = text
+            .get(start..end)
+            .map_or(0, |marked| marked.chars().count());
+
+        let (mark, style) = match label.style {
+            LabelStyle::Primary => ('^', self.severity_style(severity)),
+            LabelStyle::Secondary => ('-', BLUE),
+        };
+        let mut marks: String = std::iter::repeat_n(mark, count.max(1)).collect();
+        if !label.message.is_empty() {
+            marks = format!("{0} {1}", marks, label.message);
+        }
+        format!("{0}{1}", padding, self.paint(style, &marks))
+    }
+
+    fn severity_style(&self, severity: Severity) -> &'static str {
+        match severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        }
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{0}{1}{2}", style, text, RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+
+    // Paints `head` and leaves the `text` after it plain.
+    fn paint_rest(&self, style: &str, head: &str, text: &str) -> String {
+        format!("{0} {1}", self.paint(style, head), text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ErrorCode;
+    use crate::source::Span;
+
+    #[test]
+    fn renders_snippets_with_labels_notes_and_help() {
+        let mut sources = SourceMap::new();
+        let id = sources.add(
+            "main.ls",
+            "let a = 1;\nlet b = \"x\";\n\n\nlet c = a +\n  b;",
+        );
+        let diagnostic = Diagnostic::error("Expected Num, found Str")
+            .with_code(ErrorCode::Mismatch)
+            .with_label(Label::primary(Span::new(id, 40, 41), "this is Str"))
+            .with_label(Label::secondary(Span::new(id, 34, 35), "this is Num"))
+            .with_label(Label::secondary(Span::new(id, 19, 22), ""))
+            .with_note("`+` needs two numbers or two strings")
+            .with_help("convert one side first");
+
+        assert_eq!(
+            Renderer::new().render(&diagnostic, &sources),
+            "error[T0001]: Expected Num, found Str
+ --> main.ls:6:3
+  |
+2 | let b = \"x\";
+  |         ---
+...
+5 | let c = a +
+  |         - this is Num
+6 |   b;
+  |   ^ this is Str
+  |
+  = note: `+` needs two numbers or two strings
+  = help: convert one side first
+"
+        );
+    }
+
+    #[test]
+    fn colors_and_sourceless_reports() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("a.ls", "x");
+        let diagnostic = Diagnostic::error("Undefined variable x")
+            .with_label(Label::primary(Span::new(id, 0, 1), ""));
+        let colored = Renderer::with_color(true).render(&diagnostic, &sources);
+        assert!(colored.starts_with("\x1b[1;31merror\x1b[0m\x1b[1m: Undefined variable x\x1b[0m\n"));
+        assert!(colored.contains("\x1b[1;31m^\x1b[0m"));
+
+        let missing = Diagnostic::error("Import cycle detected")
+            .with_label(Label::primary(Span::new(FileId(4), 0, 1), ""))
+            .with_note("a.ls -> a.ls");
+        assert_eq!(
+            Renderer::new().render(&missing, &sources),
+            "error: Import cycle detected\n = note: a.ls -> a.ls\n"
+        );
+    }
+
+    #[test]
+    fn aligns_underlines_under_multibyte_text() {
+        let mut sources = SourceMap::new();
+        let id = sources.add("u.ls", "let π = \"日本\" + ü;");
+        let diagnostic = Diagnostic::error("Undefined variable ü")
+            .with_label(Label::primary(Span::new(id, 20, 22), "not found"));
+        assert_eq!(
+            Renderer::new().render(&diagnostic, &sources),
+            "error: Undefined variable ü
+ --> u.ls:1:16
+  |
+1 | let π = \"日本\" + ü;
+  |                ^ not found
+"
+        );
+    }
+}
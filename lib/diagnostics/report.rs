@@ -0,0 +1,42 @@
+use super::Diagnostic;
+use crate::compiler::CompileError;
+use crate::formatter::FormatError;
+use crate::interpreter::InterpreterError;
+use crate::lexer::{LexerError, ParseTokenError};
+use crate::loader::ModuleError;
+use crate::parser::{ParseError, ParserError};
+use crate::typechecker::{TypeCheckerError, TypeError};
+use std::error::Error;
+
+/// Errors that can describe themselves as diagnostics.
+pub trait ToDiagnostics {
+    fn to_diagnostics(&self) -> Vec<Diagnostic>;
+}
+
+/// Turns any error into diagnostics, falling back to its message for errors
+/// that know nothing about the source.
+pub fn diagnostics(err: &(dyn Error + 'static)) -> Vec<Diagnostic> {
+    macro_rules! downcast {
+        ($($error:ty),*) => {
+            $(
+                if let Some(err) = err.downcast_ref::<$error>() {
+                    return err.to_diagnostics();
+                }
+            )*
+        };
+    }
+    downcast!(
+        LexerError,
+        ParseTokenError,
+        ParserError,
+        ParseError,
+        TypeCheckerError,
+        TypeError,
+        InterpreterError,
+        CompileError,
+        ModuleError,
+        FormatError
+    );
+
+    vec![Diagnostic::error(err.to_string())]
+}
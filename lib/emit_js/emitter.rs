@@ -0,0 +1,887 @@
+use super::source_map::{quote, JsSourceMap};
+use crate::lexer::{Token, TokenKind};
+use crate::parser::{Expression, MatchArm, Pattern, Program, Statement};
+use std::collections::{HashMap, HashSet};
+
+const INDENT: &str = "  ";
+
+// Names LightScript allows that JavaScript does not, and the globals the
+// emitted code uses.
+const RESERVED: &[&str] = &[
+    "Error",
+    "Object",
+    "arguments",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "console",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "enum",
+    "eval",
+    "extends",
+    "finally",
+    "for",
+    "function",
+    "implements",
+    "in",
+    "instanceof",
+    "interface",
+    "new",
+    "null",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+const EQUAL: &str = "function $equal(a, b) {
+  if (a === b) return true;
+  if (typeof a !== \"object\" || typeof b !== \"object\") return false;
+  const keys = Object.keys(a);
+  return keys.length === Object.keys(b).length && keys.every((key) => $equal(a[key], b[key]));
+}
+";
+
+// Writes values the way the interpreter displays them, except functions,
+// which show their JavaScript source.
+const SHOW: &str = r#"function $show(value) {
+  if (typeof value === "string") return `"${value.replace(/["\\\x00-\x1f\x7f-\x9f]/g, $escape)}"`;
+  if (typeof value === "number") {
+    if (!Number.isFinite(value)) return Number.isNaN(value) ? "NaN" : value > 0 ? "inf" : "-inf";
+    if (Object.is(value, -0)) return "-0";
+    const [mantissa, exponent] = String(value).split("e");
+    if (exponent === undefined) return mantissa;
+    const sign = value < 0 ? "-" : "";
+    const [whole, fraction = ""] = mantissa.replace("-", "").split(".");
+    const point = whole.length + Number(exponent);
+    const digits = whole + fraction;
+    return sign + (point > 0 ? digits.padEnd(point, "0") : `0.${"0".repeat(-point)}${digits}`);
+  }
+  if (typeof value !== "object") return String(value);
+  if (Array.isArray(value)) return `(${value.map($show).join(", ")})`;
+  if (typeof value.tag === "string" && Array.isArray(value.args)) {
+    return value.args.length === 0 ? value.tag : `${value.tag}(${value.args.map($show).join(", ")})`;
+  }
+  return `{ ${Object.entries(value).map(([name, field]) => `${name}: ${$show(field)}`).join(", ")} }`;
+}
+function $escape(c) {
+  const escapes = { "\"": "\\\"", "\\": "\\\\", "\n": "\\n", "\t": "\\t", "\r": "\\r", "\b": "\\b", "\f": "\\f", "\v": "\\v", "\0": "\\0" };
+  return escapes[c] ?? `\\x${c.charCodeAt(0).toString(16).padStart(2, "0")}`;
+}
+"#;
+
+#[derive(Default)]
+struct Scope {
+    // The JavaScript name of every LightScript binding in the scope.
+    bindings: HashMap<String, String>,
+    declared: HashSet<String>,
+}
+
+/// JavaScript source and the map pointing back to the LightScript it came from.
+#[derive(Debug)]
+pub struct JsOutput {
+    pub code: String,
+    pub map: JsSourceMap,
+}
+
+/// Compiles a type-checked program to an ES module.
+pub fn emit_js(program: &Program) -> JsOutput {
+    JsEmitter::new().emit(program)
+}
+
+/// Writes readable JavaScript, erasing types. Values of sum types become
+/// `{ tag, args }` objects, records become objects and tuples arrays.
+#[derive(Default)]
+pub struct JsEmitter {
+    code: String,
+    line: u32,
+    col: u32,
+    indent: usize,
+    map: JsSourceMap,
+    scopes: Vec<Scope>,
+    // Each exported name and the binding it ends up exporting.
+    exports: Vec<(String, String)>,
+    uses_equal: bool,
+    uses_show: bool,
+}
+
+impl JsEmitter {
+    pub fn new() -> Self {
+        JsEmitter::default()
+    }
+
+    pub fn emit(mut self, program: &Program) -> JsOutput {
+        self.scopes = vec![Scope::default()];
+        for statement in &program.statements {
+            self.statement(statement);
+        }
+
+        // Exporting at the end lets a later binding replace an earlier one
+        // under the same name.
+        if !self.exports.is_empty() {
+            let exports: Vec<String> = self
+                .exports
+                .iter()
+                .map(|(name, js_name)| match name == js_name {
+                    true => name.to_owned(),
+                    false => format!("{0} as {1}", js_name, name),
+                })
+                .collect();
+            self.write(&format!("export {{ {} }};\n", exports.join(", ")));
+        }
+
+        // Function declarations are hoisted, so helpers can go last without
+        // shifting any mapped lines.
+        if self.uses_equal {
+            self.write(EQUAL);
+        }
+        if self.uses_show {
+            self.write(SHOW);
+        }
+
+        JsOutput {
+            code: self.code,
+            map: self.map,
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let {
+                name, initializer, ..
+            } => {
+                self.let_declaration(name, initializer);
+            }
+            Statement::Expression { expr } => {
+                self.start_line();
+                self.expression_statement(expr);
+                self.write(";\n");
+            }
+            Statement::Print { expr } => {
+                self.start_line();
+                self.uses_show = true;
+                self.write("console.log($show(");
+                self.expression(expr);
+                self.write("));\n");
+            }
+            Statement::If { .. } => {
+                self.start_line();
+                self.if_statement(statement);
+                self.write("\n");
+            }
+            Statement::Type { .. } => {}
+            Statement::Import { names, path } => {
+                let names: Vec<&Token> = names
+                    .iter()
+                    .filter(|name| !is_constructor_name(name))
+                    .collect();
+                self.start_line();
+                self.mark(path);
+                if names.is_empty() {
+                    self.write("import ");
+                } else {
+                    self.write("import { ");
+                    for (i, name) in names.iter().enumerate() {
+                        if i > 0 {
+                            self.write(", ");
+                        }
+                        self.mark(name);
+                        let js_name = self.declare(&name.to_string(), false);
+                        if js_name == name.to_string() {
+                            self.write(&js_name);
+                        } else {
+                            self.write(&format!("{0} as {1}", name, js_name));
+                        }
+                    }
+                    self.write(" } from ");
+                }
+                self.write(&quote(&module_path(&path.to_string())));
+                self.write(";\n");
+            }
+            Statement::Export { statement } => match statement.as_ref() {
+                Statement::Let {
+                    name, initializer, ..
+                } => {
+                    let js_name = self.let_declaration(name, initializer);
+                    let name = name.to_string();
+                    match self
+                        .exports
+                        .iter_mut()
+                        .find(|(exported, _)| *exported == name)
+                    {
+                        Some((_, exported)) => *exported = js_name,
+                        None => self.exports.push((name, js_name)),
+                    }
+                }
+                other => self.statement(other),
+            },
+        }
+    }
+
+    // Returns the JavaScript name of the binding.
+    fn let_declaration(&mut self, name: &Token, initializer: &Expression) -> String {
+        // A function may refer to itself, anything else sees the previous
+        // binding. Names visible from outer scopes are avoided, since a
+        // `const` shadows them in the whole block, even before it.
+        let js_name = self.fresh_name(&name.to_string(), true);
+
+        self.start_line();
+        self.mark(name);
+        self.write("const ");
+        if let Expression::Lambda { .. } = initializer {
+            self.bind(&name.to_string(), &js_name);
+        }
+        self.write(&js_name);
+        self.write(" = ");
+        self.expression(initializer);
+        self.bind(&name.to_string(), &js_name);
+
+        self.write(";\n");
+        js_name
+    }
+
+    fn if_statement(&mut self, statement: &Statement) {
+        if let Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } = statement
+        {
+            self.mark(condition.token());
+            self.write("if (");
+            self.expression(condition);
+            self.write(") ");
+            self.block(then_branch);
+
+            if let Some(else_branch) = else_branch {
+                self.write(" else ");
+                match else_branch.as_slice() {
+                    [nested @ Statement::If { .. }] => self.if_statement(nested),
+                    statements => self.block(statements),
+                }
+            }
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        self.write("{\n");
+        self.indent += 1;
+        self.scopes.push(Scope::default());
+        for statement in statements {
+            self.statement(statement);
+        }
+        self.scopes.pop();
+        self.indent -= 1;
+        self.start_line();
+        self.write("}");
+    }
+
+    // An expression statement starting with `{` would be read as a block.
+    fn expression_statement(&mut self, expr: &Expression) {
+        if starts_with_object(expr) {
+            self.write("(");
+            self.expression(expr);
+            self.write(")");
+        } else {
+            self.expression(expr);
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Literal { lit, .. } => {
+                self.mark(lit);
+                match lit.kind {
+                    TokenKind::Str => {
+                        let text = quote(lit.literal.as_deref().unwrap_or_default());
+                        self.write(&text)
+                    }
+                    _ => self.write(&lit.to_string()),
+                }
+            }
+            Expression::Variable { name, .. } => {
+                self.mark(name);
+                let js_name = self.lookup(&name.to_string());
+                self.write(&js_name);
+            }
+            Expression::Grouping { expr, .. } => {
+                self.write("(");
+                self.expression(expr);
+                self.write(")");
+            }
+            Expression::Construct { name, args, .. } => {
+                self.mark(name);
+                self.write(&format!("{{ tag: {}, args: [", quote(&name.to_string())));
+                self.list(args);
+                self.write("] }");
+            }
+            Expression::Record { brace, fields, .. } => {
+                self.mark(brace);
+                if fields.is_empty() {
+                    self.write("{}");
+                    return;
+                }
+                self.write("{ ");
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.mark(name);
+                    self.write(&format!("{}: ", name));
+                    self.expression(value);
+                }
+                self.write(" }");
+            }
+            Expression::Get { object, name, .. } => {
+                self.expression(object);
+                self.write(".");
+                self.mark(name);
+                self.write(&name.to_string());
+            }
+            Expression::Tuple {
+                paren, elements, ..
+            } => {
+                self.mark(paren);
+                self.write("[");
+                self.list(elements);
+                self.write("]");
+            }
+            Expression::Match {
+                keyword,
+                subject,
+                arms,
+                ..
+            } => self.match_expression(keyword, subject, arms),
+            Expression::Lambda {
+                arrow,
+                params,
+                body,
+                ..
+            } => {
+                self.scopes.push(Scope::default());
+                self.write("(");
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        self.write(", ");
+                    }
+                    self.mark(param);
+                    let js_name = self.declare(&param.to_string(), false);
+                    self.write(&js_name);
+                }
+                self.write(") ");
+                self.mark(arrow);
+                self.write("=> ");
+                if starts_with_object(body) {
+                    self.write("(");
+                    self.expression(body);
+                    self.write(")");
+                } else {
+                    self.expression(body);
+                }
+                self.scopes.pop();
+            }
+            Expression::Call {
+                callee,
+                paren,
+                args,
+                ..
+            } => {
+                self.expression(callee);
+                self.mark(paren);
+                self.write("(");
+                self.list(args);
+                self.write(")");
+            }
+            Expression::Unary { op, right, .. } => {
+                self.mark(op);
+                self.write(&op.to_string());
+                // `- -x` must not become the `--` operator.
+                if let Expression::Unary { op: inner, .. } = right.as_ref() {
+                    if inner.kind == TokenKind::Minus && op.kind == TokenKind::Minus {
+                        self.write(" ");
+                    }
+                }
+                self.expression(right);
+            }
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                let equality = match op.kind {
+                    TokenKind::EqualEqual => Some(""),
+                    TokenKind::BangEqual => Some("!"),
+                    _ => None,
+                };
+                match equality {
+                    // Values of sum, record and tuple types are compared structurally.
+                    Some(negation) if !is_primitive(left) && !is_primitive(right) => {
+                        self.uses_equal = true;
+                        self.mark(op);
+                        self.write(&format!("{}$equal(", negation));
+                        self.expression(left);
+                        self.write(", ");
+                        self.expression(right);
+                        self.write(")");
+                    }
+                    _ => {
+                        self.expression(left);
+                        self.write(" ");
+                        self.mark(op);
+                        match equality {
+                            Some("") => self.write("==="),
+                            Some(_) => self.write("!=="),
+                            None => self.write(&op.to_string()),
+                        }
+                        self.write(" ");
+                        self.expression(right);
+                    }
+                }
+            }
+        }
+    }
+
+    fn list(&mut self, expressions: &[Expression]) {
+        for (i, expr) in expressions.iter().enumerate() {
+            if i > 0 {
+                self.write(", ");
+            }
+            self.expression(expr);
+        }
+    }
+
+    // A match becomes an arrow function called on the subject, which switches
+    // on the tag or value when every arm tests only that.
+    fn match_expression(&mut self, keyword: &Token, subject: &Expression, arms: &[MatchArm]) {
+        self.mark(keyword);
+        self.write("(($) => {\n");
+        self.indent += 1;
+
+        let mut exhausted = false;
+        if arms.iter().all(|arm| is_switchable(&arm.pattern)) {
+            let discriminant = match arms.iter().map(|arm| &arm.pattern).next() {
+                Some(Pattern::Constructor { .. }) => "$.tag",
+                _ => "$",
+            };
+            self.start_line();
+            self.write(&format!("switch ({}) {{\n", discriminant));
+            self.indent += 1;
+            for arm in arms {
+                self.start_line();
+                self.mark(arm.pattern.token());
+                match &arm.pattern {
+                    Pattern::Constructor { name, .. } => {
+                        self.write(&format!("case {}: {{", quote(&name.to_string())))
+                    }
+                    Pattern::Literal { lit } => {
+                        self.write(&format!("case {}: {{", pattern_literal(lit)))
+                    }
+                    _ => {
+                        self.write("default: {");
+                        exhausted = true;
+                    }
+                }
+                self.arm_body(arm);
+                self.start_line();
+                self.write("}\n");
+                if exhausted {
+                    break;
+                }
+            }
+            self.indent -= 1;
+            self.start_line();
+            self.write("}\n");
+        } else {
+            for arm in arms {
+                let mut conditions = vec![];
+                conditions_of(&arm.pattern, "$", &mut conditions);
+                self.start_line();
+                self.mark(arm.pattern.token());
+                if conditions.is_empty() {
+                    self.write("{");
+                    exhausted = true;
+                } else {
+                    self.write(&format!("if ({}) {{", conditions.join(" && ")));
+                }
+                self.arm_body(arm);
+                self.start_line();
+                self.write("}\n");
+                if exhausted {
+                    break;
+                }
+            }
+        }
+
+        if !exhausted {
+            self.start_line();
+            self.write("throw new Error(\"No pattern matched\");\n");
+        }
+        self.indent -= 1;
+        self.start_line();
+        self.write("})(");
+        self.expression(subject);
+        self.write(")");
+    }
+
+    fn arm_body(&mut self, arm: &MatchArm) {
+        let mut bindings = vec![];
+        bindings_of(&arm.pattern, "$", &mut bindings);
+
+        self.write("\n");
+        self.indent += 1;
+        self.scopes.push(Scope::default());
+        for (name, path) in bindings {
+            self.start_line();
+            self.mark(name);
+            let js_name = self.declare(&name.to_string(), false);
+            self.write(&format!("const {0} = {1};\n", js_name, path));
+        }
+        self.start_line();
+        self.write("return ");
+        self.expression(&arm.body);
+        self.write(";\n");
+        self.scopes.pop();
+        self.indent -= 1;
+    }
+
+    fn declare(&mut self, name: &str, avoid_outer: bool) -> String {
+        let js_name = self.fresh_name(name, avoid_outer);
+        self.bind(name, &js_name);
+        js_name
+    }
+
+    // Finds a JavaScript name for `name` not yet declared in the innermost
+    // scope, nor visible from outer ones if `avoid_outer` is set.
+    fn fresh_name(&self, name: &str, avoid_outer: bool) -> String {
+        let taken = |candidate: &String| {
+            RESERVED.contains(&candidate.as_str())
+                || self
+                    .scopes
+                    .last()
+                    .is_some_and(|scope| scope.declared.contains(candidate))
+                || avoid_outer
+                    && self
+                        .scopes
+                        .iter()
+                        .any(|scope| scope.bindings.values().any(|js| js == candidate))
+        };
+
+        let mut js_name = name.to_owned();
+        let mut suffix = 0;
+        while taken(&js_name) {
+            suffix += 1;
+            js_name = format!("{0}${1}", name, suffix);
+        }
+        js_name
+    }
+
+    fn bind(&mut self, name: &str, js_name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.bindings.insert(name.to_owned(), js_name.to_owned());
+            scope.declared.insert(js_name.to_owned());
+        }
+    }
+
+    fn lookup(&self, name: &str) -> String {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(name))
+            .cloned()
+            .unwrap_or_else(|| name.to_owned())
+    }
+
+    fn mark(&mut self, token: &Token) {
+        self.map.add(
+            self.line,
+            self.col,
+            token.line.saturating_sub(1),
+            token.col.saturating_sub(1),
+        );
+    }
+
+    fn start_line(&mut self) {
+        for _ in 0..self.indent {
+            self.write(INDENT);
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        for c in text.chars() {
+            if c == '\n' {
+                self.line += 1;
+                self.col = 0;
+            } else {
+                // Source map columns count UTF-16 code units.
+                self.col += c.len_utf16() as u32;
+            }
+        }
+        self.code.push_str(text);
+    }
+}
+
+// Rewrites an import of `./option` or `./option.ls` to the emitted `./option.js`.
+fn module_path(path: &str) -> String {
+    let stem = path.strip_suffix(".ls").unwrap_or(path);
+    format!("{}.js", stem)
+}
+
+fn is_constructor_name(token: &Token) -> bool {
+    token
+        .literal
+        .as_ref()
+        .and_then(|name| name.chars().next())
+        .is_some_and(|c| c.is_ascii_uppercase())
+}
+
+fn is_primitive(expr: &Expression) -> bool {
+    match expr {
+        Expression::Literal { .. } | Expression::Unary { .. } | Expression::Binary { .. } => true,
+        Expression::Grouping { expr, .. } => is_primitive(expr),
+        _ => false,
+    }
+}
+
+fn starts_with_object(expr: &Expression) -> bool {
+    match expr {
+        Expression::Record { .. } | Expression::Construct { .. } => true,
+        Expression::Binary { left, .. } => starts_with_object(left),
+        Expression::Get { object, .. } => starts_with_object(object),
+        Expression::Call { callee, .. } => starts_with_object(callee),
+        _ => false,
+    }
+}
+
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    matches!(pattern, Pattern::Wildcard { .. } | Pattern::Binding { .. })
+}
+
+fn is_switchable(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Constructor { args, .. } => args.iter().all(is_irrefutable),
+        Pattern::Literal { .. } => true,
+        other => is_irrefutable(other),
+    }
+}
+
+fn pattern_literal(lit: &Token) -> String {
+    match lit.kind {
+        TokenKind::Str => quote(lit.literal.as_deref().unwrap_or_default()),
+        _ => lit.to_string(),
+    }
+}
+
+// Collects the tests a value at `path` must pass to match `pattern`.
+fn conditions_of(pattern: &Pattern, path: &str, conditions: &mut Vec<String>) {
+    match pattern {
+        Pattern::Wildcard { .. } | Pattern::Binding { .. } => {}
+        Pattern::Literal { lit } => {
+            conditions.push(format!("{0} === {1}", path, pattern_literal(lit)))
+        }
+        Pattern::Constructor { name, args } => {
+            conditions.push(format!("{0}.tag === {1}", path, quote(&name.to_string())));
+            for (i, arg) in args.iter().enumerate() {
+                conditions_of(arg, &format!("{0}.args[{1}]", path, i), conditions);
+            }
+        }
+        Pattern::Record { fields, .. } => {
+            for (name, field) in fields {
+                conditions_of(field, &format!("{0}.{1}", path, name), conditions);
+            }
+        }
+        Pattern::Tuple { elements, .. } => {
+            for (i, element) in elements.iter().enumerate() {
+                conditions_of(element, &format!("{0}[{1}]", path, i), conditions);
+            }
+        }
+    }
+}
+
+fn bindings_of<'a>(pattern: &'a Pattern, path: &str, bindings: &mut Vec<(&'a Token, String)>) {
+    match pattern {
+        Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        Pattern::Binding { name } => bindings.push((name, path.to_owned())),
+        Pattern::Constructor { args, .. } => {
+            for (i, arg) in args.iter().enumerate() {
+                bindings_of(arg, &format!("{0}.args[{1}]", path, i), bindings);
+            }
+        }
+        Pattern::Record { fields, .. } => {
+            for (name, field) in fields {
+                bindings_of(field, &format!("{0}.{1}", path, name), bindings);
+            }
+        }
+        Pattern::Tuple { elements, .. } => {
+            for (i, element) in elements.iter().enumerate() {
+                bindings_of(element, &format!("{0}[{1}]", path, i), bindings);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    fn emit(source: &str) -> String {
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        emit_js(&program).code
+    }
+
+    #[test]
+    fn erases_types_and_maps_modules() {
+        let source = "import { Option, one } from \"./option\";
+            export type Pair = { a: Num, b: Str };
+            export let pair = { a: 1, b: \"x\" };
+            let n = 1; let n = n + 1;
+            let f = (x, new) => Some(x);
+            if n == 2 { print (n, pair.a); } else if false { n; } else { f(1, 2); }";
+
+        assert_eq!(
+            emit(source),
+            "import { one } from \"./option.js\";
+const pair = { a: 1, b: \"x\" };
+const n = 1;
+const n$1 = n + 1;
+const f = (x, new$1) => ({ tag: \"Some\", args: [x] });
+if (n$1 === 2) {
+  console.log($show([n$1, pair.a]));
+} else if (false) {
+  n$1;
+} else {
+  f(1, 2);
+}
+export { pair };
+"
+            .to_string()
+                + SHOW
+        );
+    }
+
+    #[test]
+    fn compiles_matches_to_switches_and_tests() {
+        let source = "let area = s => match s { Circle(r) => r * r, Square(_) => 1 };
+            let first = l => match l { Cons(x, Nil) => x == Nil, _ => false };";
+
+        assert_eq!(
+            emit(source),
+            "const area = (s) => (($) => {
+  switch ($.tag) {
+    case \"Circle\": {
+      const r = $.args[0];
+      return r * r;
+    }
+    case \"Square\": {
+      return 1;
+    }
+  }
+  throw new Error(\"No pattern matched\");
+})(s);
+const first = (l) => (($) => {
+  if ($.tag === \"Cons\" && $.args[1].tag === \"Nil\") {
+    const x = $.args[0];
+    return $equal(x, { tag: \"Nil\", args: [] });
+  }
+  {
+    return false;
+  }
+})(l);
+"
+            .to_string()
+                + EQUAL
+        );
+    }
+
+    #[test]
+    fn avoids_the_globals_it_uses() {
+        let source = "let console = 1; print console;
+            let Object = 2; print (1, 2) == (1, 2);
+            let Error = 3;";
+        let code = emit(source);
+        assert!(code.starts_with("const console$1 = 1;\nconsole.log($show(console$1));\n"));
+        assert!(code.contains("const Error$1 = 3;"));
+        if let Some(output) = run(&code) {
+            assert_eq!(output, "1\ntrue\n");
+        }
+    }
+
+    #[test]
+    fn exports_the_last_binding_of_each_name() {
+        let code = emit("export let a = 1; export let b = a; export let a = 2;");
+        assert_eq!(
+            code,
+            "const a = 1;\nconst b = a;\nconst a$1 = 2;\nexport { a$1 as a, b };\n"
+        );
+        if let Some(output) = run(&code) {
+            assert_eq!(output, "");
+        }
+    }
+
+    #[test]
+    fn prints_values_like_the_interpreter() {
+        let source = "type Option<a> = Some(a) | None;
+            type R = { b: Num, a: Str };
+            print \"q\\\"\\t\";
+            print (1, Some((2, None)));
+            print { b: -0, a: \"\\x01\" };
+            print 0.1 + 0.2;
+            print (1e21, 1.5e-7, 1 / 0);";
+        let Some(output) = run(&emit(source)) else {
+            return;
+        };
+        // What the interpreter prints for the same program.
+        let expected = [
+            "\"q\\\"\\t\"",
+            "(1, Some((2, None)))",
+            "{ b: -0, a: \"\\x01\" }",
+            "0.30000000000000004",
+            "(1000000000000000000000, 0.00000015, inf)",
+        ]
+        .map(|line| format!("{}\n", line))
+        .concat();
+        assert_eq!(output, expected);
+    }
+
+    // Runs `code` as a module with Node and returns what it printed, or None
+    // without Node.
+    fn run(code: &str) -> Option<String> {
+        let mut node = Command::new("node")
+            .args(["--input-type=module", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .ok()?;
+        node.stdin
+            .take()
+            .unwrap()
+            .write_all(code.as_bytes())
+            .unwrap();
+        let output = node.wait_with_output().unwrap();
+        assert!(output.status.success(), "{}", code);
+        Some(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
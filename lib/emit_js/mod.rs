@@ -0,0 +1,7 @@
+mod emitter;
+mod source_map;
+mod writer;
+
+pub use emitter::{emit_js, JsEmitter, JsOutput};
+pub use source_map::{quote, JsSourceMap};
+pub use writer::JsWriter;
@@ -0,0 +1,152 @@
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// One generated position and the source position it came from, all zero based.
+#[derive(Debug, PartialEq, Copy, Clone)]
+struct Mapping {
+    generated_line: u32,
+    generated_col: u32,
+    source_line: u32,
+    source_col: u32,
+}
+
+/// A version 3 source map for one generated file with a single source.
+#[derive(Debug, Default)]
+pub struct JsSourceMap {
+    mappings: Vec<Mapping>,
+}
+
+impl JsSourceMap {
+    pub fn new() -> Self {
+        JsSourceMap::default()
+    }
+
+    /// Records that the generated code at `generated_line`, `generated_col`
+    /// came from the source at `source_line`, `source_col`.
+    pub fn add(
+        &mut self,
+        generated_line: u32,
+        generated_col: u32,
+        source_line: u32,
+        source_col: u32,
+    ) {
+        let mapping = Mapping {
+            generated_line,
+            generated_col,
+            source_line,
+            source_col,
+        };
+        if self.mappings.last() != Some(&mapping) {
+            self.mappings.push(mapping);
+        }
+    }
+
+    /// Encodes the mappings as base 64 VLQ segments.
+    pub fn mappings(&self) -> String {
+        let mut result = String::new();
+        let mut line = 0;
+        let mut previous_col = 0;
+        let mut previous_source_line = 0;
+        let mut previous_source_col = 0;
+
+        for (i, mapping) in self.mappings.iter().enumerate() {
+            if mapping.generated_line > line || i == 0 {
+                while line < mapping.generated_line {
+                    result.push(';');
+                    line += 1;
+                }
+                previous_col = 0;
+            } else {
+                result.push(',');
+            }
+
+            // The source index is always 0, so its delta is 0 after the first segment.
+            encode(&mut result, mapping.generated_col as i64 - previous_col);
+            encode(&mut result, 0);
+            encode(
+                &mut result,
+                mapping.source_line as i64 - previous_source_line,
+            );
+            encode(&mut result, mapping.source_col as i64 - previous_source_col);
+
+            previous_col = mapping.generated_col as i64;
+            previous_source_line = mapping.source_line as i64;
+            previous_source_col = mapping.source_col as i64;
+        }
+
+        result
+    }
+
+    /// Serializes the map as JSON, naming the generated `file` and its `source`.
+    pub fn to_json(&self, file: &str, source: &str) -> String {
+        format!(
+            "{{\"version\":3,\"file\":{0},\"sources\":[{1}],\"names\":[],\"mappings\":\"{2}\"}}",
+            quote(file),
+            quote(source),
+            self.mappings()
+        )
+    }
+}
+
+fn encode(result: &mut String, value: i64) {
+    let mut vlq = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        result.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+/// Quotes `text` as a JSON and JavaScript string literal.
+pub fn quote(text: &str) -> String {
+    let mut result = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            '\t' => result.push_str("\\t"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_relative_vlq_segments() {
+        let mut digits = String::new();
+        for value in &[0, 1, -1, 15, 16, -17, 1000] {
+            encode(&mut digits, *value);
+            digits.push(' ');
+        }
+        assert_eq!(digits, "A C D e gB jB w+B ");
+
+        let mut map = JsSourceMap::new();
+        map.add(0, 0, 0, 0);
+        map.add(0, 6, 0, 4);
+        map.add(2, 2, 1, 0);
+        map.add(2, 2, 1, 0);
+        assert_eq!(map.mappings(), "AAAA,MAAI;;EACJ");
+        assert_eq!(
+            map.to_json("a.js", "a.ls"),
+            "{\"version\":3,\"file\":\"a.js\",\"sources\":[\"a.ls\"],\"names\":[],\"mappings\":\"AAAA,MAAI;;EACJ\"}"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use super::emit_js;
+use crate::parser::Program;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Writes each compiled module next to its source, as `name.js` with a
+/// `name.js.map` source map.
+#[derive(Default)]
+pub struct JsWriter;
+
+impl JsWriter {
+    pub fn new() -> Self {
+        JsWriter
+    }
+
+    /// Compiles `program`, read from `source`, and returns the path of the
+    /// JavaScript file written.
+    pub fn write(&self, program: &Program, source: &Path) -> io::Result<PathBuf> {
+        let output = emit_js(program);
+        let js = source.with_extension("js");
+        let map = source.with_extension("js.map");
+
+        let file_name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        };
+        let code = format!(
+            "{0}//# sourceMappingURL={1}\n",
+            output.code,
+            file_name(&map)
+        );
+        fs::write(&js, code)?;
+        fs::write(
+            &map,
+            output.map.to_json(&file_name(&js), &file_name(source)),
+        )?;
+        Ok(js)
+    }
+}
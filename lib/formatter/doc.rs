@@ -0,0 +1,209 @@
+/// A layout to print, where groups are kept on one line when they fit and
+/// have their lines broken otherwise.
+#[derive(Debug, Clone)]
+pub enum Doc {
+    Text(String),
+    /// A space, or a newline if the enclosing group is broken.
+    Line,
+    /// Nothing, or a newline if the enclosing group is broken.
+    SoftLine,
+    /// Always a newline, which breaks every enclosing group.
+    HardLine,
+    /// A newline unless the line has nothing but indentation yet.
+    LineStart,
+    /// Text only printed if the enclosing group is broken.
+    IfBreak(&'static str),
+    /// Text held back until the end of the line, for trailing comments.
+    LineSuffix(String),
+    /// Breaks every enclosing group without printing anything.
+    BreakParent,
+    Indent(Vec<Doc>),
+    Group(Vec<Doc>),
+    Concat(Vec<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Doc {
+        Doc::Text(text.into())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Doc::Text(text) => text.is_empty(),
+            Doc::Concat(docs) => docs.iter().all(Doc::is_empty),
+            _ => false,
+        }
+    }
+
+    pub fn print(&self, width: usize, indent: usize) -> String {
+        let mut printer = Printer {
+            width,
+            indent,
+            out: String::new(),
+            column: 0,
+            suffix: vec![],
+        };
+        printer.print(self);
+        printer.flush_suffix();
+        printer.out
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+struct Printer {
+    width: usize,
+    indent: usize,
+    out: String,
+    column: usize,
+    suffix: Vec<String>,
+}
+
+impl Printer {
+    fn print(&mut self, doc: &Doc) {
+        let mut stack = vec![(0, Mode::Break, doc)];
+        while let Some((level, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => self.write(text),
+                Doc::Line if mode == Mode::Flat => self.write(" "),
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine | Doc::HardLine => self.newline(level),
+                Doc::LineStart => {
+                    if !self.current_line().trim().is_empty() {
+                        self.newline(level);
+                    }
+                }
+                Doc::IfBreak(text) if mode == Mode::Break => self.write(text),
+                Doc::IfBreak(_) | Doc::BreakParent => {}
+                Doc::LineSuffix(text) => self.suffix.push(text.clone()),
+                Doc::Indent(docs) => stack.extend(
+                    docs.iter()
+                        .rev()
+                        .map(|doc| (level + self.indent, mode, doc)),
+                ),
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (level, mode, doc))),
+                Doc::Group(docs) => {
+                    let remaining = self.width as isize - self.column as isize;
+                    let mode = if mode == Mode::Flat || fits(docs, &stack, remaining) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.extend(docs.iter().rev().map(|doc| (level, mode, doc)));
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+        self.column = self.current_line().chars().count();
+    }
+
+    fn current_line(&self) -> &str {
+        let start = self.out.rfind('\n').map_or(0, |index| index + 1);
+        &self.out[start..]
+    }
+
+    fn newline(&mut self, level: usize) {
+        self.flush_suffix();
+        let trimmed = self.out.trim_end_matches(' ').len();
+        self.out.truncate(trimmed);
+        self.out.push('\n');
+        self.out.extend(std::iter::repeat_n(' ', level));
+        self.column = level;
+    }
+
+    fn flush_suffix(&mut self) {
+        for text in std::mem::take(&mut self.suffix) {
+            self.write(&text);
+        }
+    }
+}
+
+// Whether a group fits on the rest of the line when printed flat, counting
+// what follows it up to the next line break.
+fn fits(group: &[Doc], rest: &[(usize, Mode, &Doc)], mut remaining: isize) -> bool {
+    let mut items: Vec<(Mode, &Doc)> = group.iter().rev().map(|doc| (Mode::Flat, doc)).collect();
+    let mut rest = rest.iter().rev();
+    loop {
+        if remaining < 0 {
+            return false;
+        }
+        let (mode, doc) = match items.pop() {
+            Some(item) => item,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => match text.split_once('\n') {
+                Some((first, _)) => {
+                    return mode == Mode::Break && remaining >= first.chars().count() as isize
+                }
+                None => remaining -= text.chars().count() as isize,
+            },
+            Doc::Line | Doc::SoftLine | Doc::HardLine | Doc::LineStart if mode == Mode::Break => {
+                return true
+            }
+            Doc::Line => remaining -= 1,
+            Doc::SoftLine => {}
+            Doc::HardLine | Doc::LineStart | Doc::BreakParent if mode == Mode::Flat => {
+                return false
+            }
+            Doc::IfBreak(text) if mode == Mode::Break => remaining -= text.len() as isize,
+            Doc::IfBreak(_) | Doc::BreakParent | Doc::LineSuffix(_) => {}
+            Doc::HardLine | Doc::LineStart => return true,
+            Doc::Indent(docs) | Doc::Group(docs) | Doc::Concat(docs) => {
+                items.extend(docs.iter().rev().map(|doc| (mode, doc)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(args: &[&str]) -> Doc {
+        let mut items = vec![Doc::SoftLine];
+        for (index, arg) in args.iter().enumerate() {
+            if index > 0 {
+                items.extend(vec![Doc::text(","), Doc::Line]);
+            }
+            items.push(Doc::text(*arg));
+        }
+        Doc::Group(vec![
+            Doc::text("f("),
+            Doc::Indent(items),
+            Doc::IfBreak(","),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ])
+    }
+
+    #[test]
+    fn breaks_groups_that_do_not_fit() {
+        let doc = call(&["first", "second"]);
+        assert_eq!(doc.print(20, 4), "f(first, second)");
+        assert_eq!(doc.print(10, 2), "f(\n  first,\n  second,\n)");
+    }
+
+    #[test]
+    fn holds_line_suffixes_until_the_line_ends() {
+        let doc = Doc::Concat(vec![
+            Doc::text("a"),
+            Doc::LineSuffix(" // note".to_owned()),
+            Doc::text(";"),
+            Doc::HardLine,
+            Doc::LineStart,
+            Doc::text("b"),
+        ]);
+        assert_eq!(doc.print(80, 4), "a; // note\nb");
+    }
+}
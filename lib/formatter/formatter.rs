@@ -0,0 +1,771 @@
+use super::doc::Doc;
+use crate::diagnostics::{Diagnostic, ToDiagnostics};
+use crate::lexer::TokenKind;
+use crate::source::FileId;
+use crate::syntax::{self, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+use std::collections::HashMap;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter as FmtFormatter},
+    mem,
+};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FormatOptions {
+    /// How many columns a line may take before groups are broken.
+    pub line_width: usize,
+    /// How many spaces each level of nesting is indented by.
+    pub indent_width: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        FormatOptions {
+            line_width: 80,
+            indent_width: 4,
+        }
+    }
+}
+
+/// The syntax errors that kept a source from being formatted.
+#[derive(Debug)]
+pub struct FormatError {
+    errors: Vec<Diagnostic>,
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.errors.iter().map(|err| err.message.as_str()).collect();
+        write!(f, "Cannot format invalid source: {}", messages.join(", "))
+    }
+}
+
+impl Error for FormatError {}
+
+impl ToDiagnostics for FormatError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors.clone()
+    }
+}
+
+/// Reprints a program in the canonical style, keeping its comments. Sources
+/// with syntax errors are left alone.
+pub fn format(
+    source: &str,
+    file_id: FileId,
+    options: &FormatOptions,
+) -> Result<String, FormatError> {
+    let parse = syntax::parse(source, file_id);
+    if !parse.errors().is_empty() {
+        return Err(FormatError {
+            errors: parse.errors().to_vec(),
+        });
+    }
+
+    let root = parse.syntax();
+    let mut formatter = Printer {
+        comments: attach_comments(&root),
+    };
+    let doc = formatter.root(&root);
+    let mut out = doc.print(options.line_width, options.indent_width);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+#[derive(Debug)]
+struct Comment {
+    text: String,
+    // Whether what follows the comment goes on the next line.
+    line: bool,
+    // Whether the comment starts a line of its own.
+    own_line: bool,
+    blank_before: bool,
+}
+
+// The comments around a token. Those on the lines before it lead, those
+// after it on its own line trail.
+#[derive(Debug, Default)]
+struct Comments {
+    leading: Vec<Comment>,
+    trailing: Vec<Comment>,
+    blank_before: bool,
+}
+
+// Hands every comment to the closest token, keyed by where the token starts.
+// Comments at the end of the source go to its length.
+fn attach_comments(root: &SyntaxNode) -> HashMap<usize, Comments> {
+    let mut comments: HashMap<usize, Comments> = HashMap::new();
+    let mut pending: Vec<Comment> = vec![];
+    let mut previous = None;
+    let mut after_open = false;
+    let mut newlines = 0;
+    for token in root.tokens() {
+        let kind = match token.kind() {
+            SyntaxKind::Token(kind) => kind,
+            _ => TokenKind::Eof,
+        };
+        if kind == TokenKind::Whitespace {
+            newlines += token.text().matches('\n').count();
+            continue;
+        }
+        if let Some(last) = pending.last_mut() {
+            last.line |= newlines > 0;
+        }
+
+        if matches!(
+            kind,
+            TokenKind::Comment | TokenKind::BlockComment | TokenKind::DocComment
+        ) {
+            let comment = Comment {
+                text: token.text().trim_end().to_owned(),
+                line: kind != TokenKind::BlockComment,
+                own_line: newlines > 0 || previous.is_none(),
+                blank_before: newlines > 1,
+            };
+            // A comment right after an opening bracket goes with what the
+            // bracket opens.
+            match previous {
+                Some(offset) if newlines == 0 && pending.is_empty() && !after_open => {
+                    comments.entry(offset).or_default().trailing.push(comment)
+                }
+                _ => pending.push(comment),
+            }
+        } else {
+            let offset = token.span().start;
+            let entry = comments.entry(offset).or_default();
+            entry.leading = mem::take(&mut pending);
+            entry.blank_before = newlines > 1;
+            previous = Some(offset);
+            after_open = matches!(
+                kind,
+                TokenKind::LeftParen
+                    | TokenKind::LeftBrace
+                    | TokenKind::LeftBracket
+                    | TokenKind::TemplateHead
+                    | TokenKind::TemplateMiddle
+            );
+        }
+        newlines = 0;
+    }
+
+    let end = root.span().end;
+    let entry = comments.entry(end).or_default();
+    entry.leading = pending;
+    comments
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TrailingComma {
+    Never,
+    IfBroken,
+    Always,
+}
+
+struct Printer {
+    comments: HashMap<usize, Comments>,
+}
+
+impl Printer {
+    fn root(&mut self, root: &SyntaxNode) -> Doc {
+        let statements = root.children();
+        let mut docs = self.statements(&statements);
+        let comments = self.take_comments(root.span().end);
+        docs.push(dangling(comments.leading, !statements.is_empty()));
+        Doc::Concat(docs)
+    }
+
+    fn node(&mut self, node: &SyntaxNode) -> Doc {
+        let elements = significant(node);
+        match node.kind() {
+            SyntaxKind::LetStatement | SyntaxKind::TypeStatement | SyntaxKind::PrintStatement => {
+                let mut docs = self.spaced(&elements);
+                if elements.last().map(SyntaxElement::kind) != Some(TokenKind::Semicolon.into()) {
+                    docs.push(Doc::text(";"));
+                }
+                Doc::Concat(docs)
+            }
+            SyntaxKind::ImportStatement => {
+                let close = position(&elements, TokenKind::RightBrace);
+                let mut docs = vec![self.element(&elements[0]), Doc::text(" ")];
+                docs.push(self.list(&elements[1..=close], true, TrailingComma::IfBroken));
+                for element in &elements[close + 1..] {
+                    if element.kind() != TokenKind::Semicolon.into() {
+                        docs.push(Doc::text(" "));
+                    }
+                    docs.push(self.element(element));
+                }
+                if elements.last().map(SyntaxElement::kind) != Some(TokenKind::Semicolon.into()) {
+                    docs.push(Doc::text(";"));
+                }
+                Doc::Concat(docs)
+            }
+            SyntaxKind::ExportStatement
+            | SyntaxKind::IfStatement
+            | SyntaxKind::LambdaExpr
+            | SyntaxKind::MatchArm => Doc::Concat(self.spaced(&elements)),
+            SyntaxKind::Block => self.block(&elements),
+            SyntaxKind::TypeParams | SyntaxKind::TypeArgs => {
+                self.list(&elements, false, TrailingComma::Never)
+            }
+            SyntaxKind::ParamList => {
+                let names = elements
+                    .iter()
+                    .filter(|element| element.kind() == TokenKind::Identifier.into())
+                    .count();
+                if names == 1 {
+                    Doc::Concat(
+                        elements
+                            .iter()
+                            .map(|element| match element {
+                                SyntaxElement::Token(token)
+                                    if token.kind() != TokenKind::Identifier.into() =>
+                                {
+                                    self.comments_only(token)
+                                }
+                                _ => self.element(element),
+                            })
+                            .collect(),
+                    )
+                } else {
+                    self.list(&elements, false, TrailingComma::Never)
+                }
+            }
+            SyntaxKind::SumType => self.sum_type(&elements),
+            SyntaxKind::Variant | SyntaxKind::ConstructorPattern if elements.len() > 1 => {
+                Doc::Concat(vec![
+                    self.element(&elements[0]),
+                    self.list(&elements[1..], false, TrailingComma::IfBroken),
+                ])
+            }
+            SyntaxKind::RecordType | SyntaxKind::RecordExpr | SyntaxKind::RecordPattern => {
+                self.list(&elements, true, TrailingComma::IfBroken)
+            }
+            SyntaxKind::ParenExpr | SyntaxKind::ParenType | SyntaxKind::ParenPattern => {
+                self.list(&elements, false, TrailingComma::Never)
+            }
+            SyntaxKind::TupleType | SyntaxKind::TuplePattern | SyntaxKind::ArgList => {
+                self.list(&elements, false, TrailingComma::IfBroken)
+            }
+            SyntaxKind::TupleExpr => {
+                let commas = elements
+                    .iter()
+                    .filter(|element| element.kind() == TokenKind::Comma.into())
+                    .count();
+                // A tuple of one is told apart from parentheses by its comma.
+                let trailing = match elements.len() - commas {
+                    3 => TrailingComma::Always,
+                    _ => TrailingComma::IfBroken,
+                };
+                self.list(&elements, false, trailing)
+            }
+            SyntaxKind::FunctionType => {
+                let arrow = position(&elements, TokenKind::Arrow);
+                let mut docs = vec![self.list(&elements[..arrow], false, TrailingComma::IfBroken)];
+                docs.push(Doc::text(" "));
+                docs.extend(self.spaced(&elements[arrow..]));
+                Doc::Concat(docs)
+            }
+            SyntaxKind::MatchExpr => {
+                let open = position(&elements, TokenKind::LeftBrace);
+                let mut docs = self.spaced(&elements[..open]);
+                docs.push(Doc::text(" "));
+                docs.push(self.list(&elements[open..], true, TrailingComma::IfBroken));
+                Doc::Concat(docs)
+            }
+            SyntaxKind::Field | SyntaxKind::RecordField | SyntaxKind::RecordPatternField => {
+                let docs = elements
+                    .iter()
+                    .map(|element| {
+                        let doc = self.element(element);
+                        match element.kind() {
+                            SyntaxKind::Token(TokenKind::Colon) => {
+                                Doc::Concat(vec![doc, Doc::text(" ")])
+                            }
+                            _ => doc,
+                        }
+                    })
+                    .collect();
+                Doc::Concat(docs)
+            }
+            SyntaxKind::BinaryExpr => self.binary(node),
+            SyntaxKind::UnaryExpr => {
+                let mut docs: Vec<Doc> = elements
+                    .iter()
+                    .map(|element| self.element(element))
+                    .collect();
+                // Two minuses in a row would read as `--`.
+                let minus = SyntaxKind::from(TokenKind::Minus);
+                if elements.first().map(SyntaxElement::kind) == Some(minus)
+                    && elements.get(1).and_then(first_token) == Some(minus)
+                {
+                    docs.insert(1, Doc::text(" "));
+                }
+                Doc::Concat(docs)
+            }
+            // Everything else is printed tight, such as calls, field access,
+            // literals and templates. A comma inside parentheses changes
+            // nothing so it goes.
+            _ => Doc::Concat(
+                elements
+                    .iter()
+                    .map(|element| match element {
+                        SyntaxElement::Token(token) if token.kind() == TokenKind::Comma.into() => {
+                            self.comments_only(token)
+                        }
+                        _ => self.element(element),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    fn element(&mut self, element: &SyntaxElement) -> Doc {
+        match element {
+            SyntaxElement::Node(node) => self.node(node),
+            SyntaxElement::Token(token) => self.token(token),
+        }
+    }
+
+    fn token(&mut self, token: &SyntaxToken) -> Doc {
+        let comments = self.take_comments(token.span().start);
+        with_comments(comments, Doc::text(token.text()))
+    }
+
+    // Keeps the comments of a token that the canonical style leaves out.
+    fn comments_only(&mut self, token: &SyntaxToken) -> Doc {
+        let comments = self.take_comments(token.span().start);
+        with_comments(comments, Doc::text(""))
+    }
+
+    fn take_comments(&mut self, offset: usize) -> Comments {
+        self.comments.remove(&offset).unwrap_or_default()
+    }
+
+    // Elements separated by spaces, with none before a semicolon or the
+    // brackets of type parameters and arguments.
+    fn spaced(&mut self, elements: &[SyntaxElement]) -> Vec<Doc> {
+        let mut docs = vec![];
+        for (index, element) in elements.iter().enumerate() {
+            let tight = matches!(
+                element.kind(),
+                SyntaxKind::Token(TokenKind::Semicolon) | SyntaxKind::TypeParams
+            );
+            if index > 0 && !tight {
+                docs.push(Doc::text(" "));
+            }
+            docs.push(self.element(element));
+        }
+        docs
+    }
+
+    fn statements(&mut self, statements: &[SyntaxNode]) -> Vec<Doc> {
+        let mut docs = vec![];
+        for (index, statement) in statements.iter().enumerate() {
+            if index > 0 {
+                docs.push(Doc::HardLine);
+                if self.blank_before(statement) {
+                    docs.push(Doc::HardLine);
+                }
+            }
+            docs.push(self.node(statement));
+        }
+        docs
+    }
+
+    // Whether a blank line came before the node or its leading comments.
+    fn blank_before(&self, node: &SyntaxNode) -> bool {
+        let Some(first) = node
+            .tokens()
+            .into_iter()
+            .find(|token| !token.kind().is_trivia())
+        else {
+            return false;
+        };
+        self.comments
+            .get(&first.span().start)
+            .is_some_and(|comments| match comments.leading.first() {
+                Some(comment) => comment.blank_before,
+                None => comments.blank_before,
+            })
+    }
+
+    fn block(&mut self, elements: &[SyntaxElement]) -> Doc {
+        let statements: Vec<SyntaxNode> = elements
+            .iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node.clone()),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect();
+        let open = self.element(&elements[0]);
+        let mut close = self.take_comments(elements[elements.len() - 1].span().start);
+        if statements.is_empty() && close.leading.is_empty() {
+            return Doc::Concat(vec![open, with_comments(close, Doc::text("}"))]);
+        }
+
+        let mut body = vec![Doc::HardLine];
+        body.extend(self.statements(&statements));
+        body.push(dangling(
+            mem::take(&mut close.leading),
+            !statements.is_empty(),
+        ));
+        Doc::Concat(vec![
+            open,
+            Doc::Indent(body),
+            Doc::HardLine,
+            with_comments(close, Doc::text("}")),
+        ])
+    }
+
+    // Brackets around items separated by commas, all on one line if they
+    // fit and one item per line otherwise.
+    fn list(&mut self, elements: &[SyntaxElement], spaced: bool, trailing: TrailingComma) -> Doc {
+        let last = elements.len() - 1;
+        let open = self.element(&elements[0]);
+        let close_text = match &elements[last] {
+            SyntaxElement::Token(token) => token.text().to_owned(),
+            SyntaxElement::Node(node) => node.text(),
+        };
+        let mut close = self.take_comments(elements[last].span().start);
+
+        // Each item with the comments of the comma after it.
+        let mut items: Vec<(Doc, Doc)> = vec![];
+        for element in &elements[1..last] {
+            match element {
+                SyntaxElement::Token(token) if token.kind() == TokenKind::Comma.into() => {
+                    let comments = self.comments_only(token);
+                    if let Some(item) = items.last_mut() {
+                        item.1 = comments;
+                    }
+                }
+                _ => items.push((self.element(element), Doc::text(""))),
+            }
+        }
+        let dangling = dangling(mem::take(&mut close.leading), !items.is_empty());
+        let close = with_comments(close, Doc::text(close_text));
+        if items.is_empty() && dangling.is_empty() {
+            return Doc::Concat(vec![open, close]);
+        }
+
+        let line = if spaced { Doc::Line } else { Doc::SoftLine };
+        let mut body = vec![line.clone()];
+        let count = items.len();
+        for (index, (item, comma)) in items.into_iter().enumerate() {
+            body.push(item);
+            if index + 1 < count {
+                body.extend(vec![Doc::text(","), comma, Doc::Line]);
+                continue;
+            }
+            match trailing {
+                TrailingComma::Never => {}
+                TrailingComma::IfBroken => body.push(Doc::IfBreak(",")),
+                TrailingComma::Always => body.push(Doc::text(",")),
+            }
+            body.push(comma);
+        }
+        body.push(dangling);
+        Doc::Group(vec![open, Doc::Indent(body), line, close])
+    }
+
+    // Variants on one line if they fit, and otherwise each on its own line
+    // after a bar.
+    fn sum_type(&mut self, elements: &[SyntaxElement]) -> Doc {
+        let mut body = vec![Doc::SoftLine, Doc::IfBreak("| ")];
+        for (index, element) in elements.iter().enumerate() {
+            match element {
+                SyntaxElement::Token(token) if index == 0 => body.push(self.comments_only(token)),
+                SyntaxElement::Token(token) => {
+                    body.extend(vec![Doc::Line, self.token(token), Doc::text(" ")])
+                }
+                SyntaxElement::Node(node) => body.push(self.node(node)),
+            }
+        }
+        Doc::Group(vec![Doc::Indent(body)])
+    }
+
+    // A chain of operations on one line if it fits, and otherwise broken
+    // before each operator.
+    fn binary(&mut self, node: &SyntaxNode) -> Doc {
+        let mut operations = vec![];
+        let mut left = SyntaxElement::Node(node.clone());
+        loop {
+            let elements = match &left {
+                SyntaxElement::Node(node) if node.kind() == SyntaxKind::BinaryExpr => {
+                    significant(node)
+                }
+                _ => break,
+            };
+            operations.push((elements[1].clone(), elements[2].clone()));
+            left = elements[0].clone();
+        }
+
+        // Comments before the chain go above it, so that they do not break
+        // its lines.
+        let first = left.span().start;
+        let leading = match self.comments.get_mut(&first) {
+            Some(comments) => Comments {
+                leading: mem::take(&mut comments.leading),
+                ..Comments::default()
+            },
+            None => Comments::default(),
+        };
+
+        let mut rest = vec![];
+        for (operator, right) in operations.iter().rev() {
+            rest.extend(vec![
+                Doc::Line,
+                self.element(operator),
+                Doc::text(" "),
+                self.element(right),
+            ]);
+        }
+        Doc::Concat(vec![
+            with_comments(leading, Doc::text("")),
+            Doc::Group(vec![self.element(&left), Doc::Indent(rest)]),
+        ])
+    }
+}
+
+// A token's text with the comments around it.
+fn with_comments(comments: Comments, text: Doc) -> Doc {
+    let mut docs = vec![];
+    for (index, comment) in comments.leading.iter().enumerate() {
+        if comment.own_line {
+            docs.push(Doc::LineStart);
+            if index > 0 && comment.blank_before {
+                docs.push(Doc::HardLine);
+            }
+        }
+        docs.push(Doc::text(&comment.text));
+        docs.push(if comment.line {
+            Doc::HardLine
+        } else {
+            Doc::text(" ")
+        });
+    }
+    if !comments.leading.is_empty() && comments.blank_before {
+        docs.push(Doc::HardLine);
+    }
+    docs.push(text);
+    docs.extend(trailing(comments.trailing));
+    Doc::Concat(docs)
+}
+
+fn trailing(comments: Vec<Comment>) -> Vec<Doc> {
+    let mut docs = vec![];
+    for comment in comments {
+        if comment.line {
+            docs.push(Doc::LineSuffix(format!(" {}", comment.text)));
+            docs.push(Doc::BreakParent);
+        } else {
+            docs.push(Doc::text(format!(" {}", comment.text)));
+        }
+    }
+    docs
+}
+
+// Comments with nothing after them before a closing bracket or the end of
+// the source, each on its own line.
+fn dangling(comments: Vec<Comment>, after_items: bool) -> Doc {
+    let mut docs = vec![];
+    for (index, comment) in comments.iter().enumerate() {
+        if index > 0 || after_items {
+            docs.push(Doc::HardLine);
+            if comment.blank_before {
+                docs.push(Doc::HardLine);
+            }
+        }
+        docs.push(Doc::text(&comment.text));
+        if comment.line {
+            docs.push(Doc::BreakParent);
+        }
+    }
+    Doc::Concat(docs)
+}
+
+fn significant(node: &SyntaxNode) -> Vec<SyntaxElement> {
+    node.children_with_tokens()
+        .into_iter()
+        .filter(|element| !element.kind().is_trivia())
+        .collect()
+}
+
+// The kind of the first token of `element` that is not trivia.
+fn first_token(element: &SyntaxElement) -> Option<SyntaxKind> {
+    match element {
+        SyntaxElement::Node(node) => node
+            .tokens()
+            .iter()
+            .map(SyntaxToken::kind)
+            .find(|kind| !kind.is_trivia()),
+        SyntaxElement::Token(token) => Some(token.kind()),
+    }
+}
+
+fn position(elements: &[SyntaxElement], kind: TokenKind) -> usize {
+    elements
+        .iter()
+        .position(|element| element.kind() == kind.into())
+        .unwrap_or(elements.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+    use crate::source::SourceMap;
+
+    fn fmt(source: &str, line_width: usize) -> String {
+        let file_id = SourceMap::new().add("test.ls", source);
+        let options = FormatOptions {
+            line_width,
+            ..FormatOptions::default()
+        };
+        let formatted = format(source, file_id, &options).unwrap();
+        assert_eq!(
+            format(&formatted, file_id, &options).unwrap(),
+            formatted,
+            "formatting is not idempotent"
+        );
+        formatted
+    }
+
+    #[test]
+    fn prints_programs_in_the_canonical_style() {
+        let source = "import {map,filter} from \"./list\";
+type Pair<a,b> = {first:a,second:b};
+type Option<a> = | Some(a) | None;
+let add = (x,y)=>x+y;
+let id = (x) => (x);
+let neg = - -1 - -(2);
+if add(1,2)>2 {print `sum ${ add(1, 2) }`} else { (1,) }
+";
+        assert_eq!(
+            fmt(source, 80),
+            "import { map, filter } from \"./list\";
+type Pair<a, b> = { first: a, second: b };
+type Option<a> = Some(a) | None;
+let add = (x, y) => x + y;
+let id = x => (x);
+let neg = - -1 - -(2);
+if add(1, 2) > 2 {
+    print `sum ${add(1, 2)}`;
+} else {
+    (1,)
+}
+"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        let source = "// header
+
+/// Adds one.
+let inc = x => x + 1; // trailing
+let f = g(1, // first
+  /* second */ 2);
+
+
+let r = { a: 1 /* inline */ };
+if r.a == 1 {
+    // only a comment
+}
+let y = f(/* a */ x);
+let s = `${ /* c */ x }`;
+let z = (
+  // lead
+  1 + 2
+);
+// the end
+";
+        assert_eq!(
+            fmt(source, 80),
+            "// header
+
+/// Adds one.
+let inc = x => x + 1; // trailing
+let f = g(
+    1, // first
+    /* second */ 2,
+);
+
+let r = { a: 1 /* inline */ };
+if r.a == 1 {
+    // only a comment
+}
+let y = f(/* a */ x);
+let s = `${/* c */ x}`;
+let z = (
+    // lead
+    1 + 2
+);
+// the end
+"
+        );
+    }
+
+    #[test]
+    fn breaks_lines_longer_than_the_width() {
+        let source = "let total = first + second + third;
+type Shape = Circle(Num) | Square(Num) | Rect(Num, Num);
+let p = point(12, 34, { label: \"origin\" });
+";
+        assert_eq!(fmt(source, 80), source);
+        assert_eq!(
+            fmt(source, 24),
+            "let total = first
+    + second
+    + third;
+type Shape =
+    | Circle(Num)
+    | Square(Num)
+    | Rect(Num, Num);
+let p = point(
+    12,
+    34,
+    { label: \"origin\" },
+);
+"
+        );
+    }
+
+    #[test]
+    fn formats_the_corpus_without_changing_its_meaning() {
+        let program = |source: &str| -> Vec<String> {
+            Parser::from_lexer(Lexer::new(source.as_bytes()))
+                .parse_program()
+                .unwrap()
+                .statements
+                .iter()
+                .map(ToString::to_string)
+                .collect()
+        };
+        for source in [
+            include_str!("../../tests/corpus/blocks.ls"),
+            include_str!("../../tests/corpus/closures.ls"),
+            include_str!("../../tests/corpus/data.ls"),
+            include_str!("../../tests/corpus/patterns.ls"),
+            include_str!("../../tests/corpus/recursion.ls"),
+            include_str!("../../tests/corpus/strings.ls"),
+        ] {
+            let formatted = fmt(source, 80);
+            assert_eq!(program(&formatted), program(source), "{}", formatted);
+        }
+    }
+
+    #[test]
+    fn refuses_sources_with_syntax_errors() {
+        let source = "let x = ;";
+        let file_id = SourceMap::new().add("test.ls", source);
+        let err = format(source, file_id, &FormatOptions::default()).unwrap_err();
+        assert_eq!(err.to_diagnostics().len(), 1);
+        assert_eq!(
+            err.to_string(),
+            "Cannot format invalid source: Expect expression"
+        );
+    }
+}
@@ -0,0 +1,4 @@
+mod doc;
+mod formatter;
+
+pub use formatter::{format, FormatError, FormatOptions};
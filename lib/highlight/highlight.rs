@@ -0,0 +1,212 @@
+use crate::lexer::{Lexer, TokenKind};
+use crate::parser::Parser;
+use crate::resolver::{self, DefinitionKind};
+use crate::source::Span;
+use std::collections::HashMap;
+
+/// What a range of source is, as far as highlighting it goes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    Keyword,
+    Identifier,
+    String,
+    Number,
+    Comment,
+    Operator,
+    TypeName,
+    Constructor,
+}
+
+/// A range of source and what it is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    pub span: Span,
+    pub kind: HighlightKind,
+}
+
+/// Classifies the tokens of `source` in order, leaving out whitespace and
+/// punctuation like brackets and commas. Names are classified by what they
+/// refer to when the source parses, and as identifiers when it does not.
+pub fn highlight(source: &str) -> Vec<Highlight> {
+    let names = names(source);
+    Lexer::new(source.as_bytes())
+        // What the lexer cannot read is left unclassified.
+        .filter_map(Result::ok)
+        .filter_map(|token| {
+            let kind = match classify(token.kind)? {
+                HighlightKind::Identifier => names
+                    .get(&token.span.start)
+                    .copied()
+                    .unwrap_or(HighlightKind::Identifier),
+                kind => kind,
+            };
+            Some(Highlight {
+                span: token.span,
+                kind,
+            })
+        })
+        .collect()
+}
+
+fn classify(kind: TokenKind) -> Option<HighlightKind> {
+    let class = match kind {
+        TokenKind::Let
+        | TokenKind::True
+        | TokenKind::False
+        | TokenKind::If
+        | TokenKind::Else
+        | TokenKind::Type
+        | TokenKind::Import
+        | TokenKind::From
+        | TokenKind::Export
+        | TokenKind::Print
+        | TokenKind::Match => HighlightKind::Keyword,
+        TokenKind::Identifier => HighlightKind::Identifier,
+        TokenKind::Str
+        | TokenKind::Template
+        | TokenKind::TemplateHead
+        | TokenKind::TemplateMiddle
+        | TokenKind::TemplateTail => HighlightKind::String,
+        TokenKind::Num => HighlightKind::Number,
+        TokenKind::Comment | TokenKind::BlockComment | TokenKind::DocComment => {
+            HighlightKind::Comment
+        }
+        TokenKind::Dot
+        | TokenKind::Minus
+        | TokenKind::Plus
+        | TokenKind::Slash
+        | TokenKind::Star
+        | TokenKind::Bang
+        | TokenKind::BangEqual
+        | TokenKind::Equal
+        | TokenKind::EqualEqual
+        | TokenKind::Arrow
+        | TokenKind::Greater
+        | TokenKind::GreaterEqual
+        | TokenKind::Less
+        | TokenKind::LessEqual
+        | TokenKind::Ampersand
+        | TokenKind::AmpersandAmpersand
+        | TokenKind::Bar
+        | TokenKind::BarBar => HighlightKind::Operator,
+        TokenKind::LeftParen
+        | TokenKind::RightParen
+        | TokenKind::LeftBrace
+        | TokenKind::RightBrace
+        | TokenKind::LeftBracket
+        | TokenKind::RightBracket
+        | TokenKind::Comma
+        | TokenKind::Colon
+        | TokenKind::Semicolon
+        | TokenKind::Whitespace
+        | TokenKind::Eof => return None,
+    };
+    Some(class)
+}
+
+// The names that are types or constructors, by where they start.
+fn names(source: &str) -> HashMap<usize, HighlightKind> {
+    let program = match Parser::from_lexer(Lexer::new(source.as_bytes())).parse_program() {
+        Ok(program) => program,
+        Err(_) => return HashMap::new(),
+    };
+    let resolution = resolver::resolve(&program);
+    let definitions = resolution.definitions();
+
+    let defined = definitions
+        .iter()
+        .map(|definition| (definition.span, definition.kind));
+    let referenced = resolution
+        .references()
+        .iter()
+        .map(|reference| (reference.span, definitions[reference.definition].kind));
+    defined
+        .chain(referenced)
+        .chain(resolution.unresolved().iter().copied())
+        .filter_map(|(span, kind)| match kind {
+            DefinitionKind::Type | DefinitionKind::TypeParameter => {
+                Some((span.start, HighlightKind::TypeName))
+            }
+            DefinitionKind::Constructor => Some((span.start, HighlightKind::Constructor)),
+            DefinitionKind::Value | DefinitionKind::Parameter => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HighlightKind::*;
+
+    fn highlighted(source: &str) -> Vec<(&str, HighlightKind)> {
+        highlight(source)
+            .into_iter()
+            .map(|highlight| {
+                (
+                    &source[highlight.span.start..highlight.span.end],
+                    highlight.kind,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classifies_tokens_by_what_they_are() {
+        let source = "type Shape = Circle(Num) | Dot;
+// Area.
+let area = s => match s { Circle(r) => r * 3, _ => 0 };
+print area(Circle(2)) + `${\"a\"}`;";
+        assert_eq!(
+            highlighted(source),
+            vec![
+                ("type", Keyword),
+                ("Shape", TypeName),
+                ("=", Operator),
+                ("Circle", Constructor),
+                ("Num", TypeName),
+                ("|", Operator),
+                ("Dot", Constructor),
+                ("// Area.", Comment),
+                ("let", Keyword),
+                ("area", Identifier),
+                ("=", Operator),
+                ("s", Identifier),
+                ("=>", Operator),
+                ("match", Keyword),
+                ("s", Identifier),
+                ("Circle", Constructor),
+                ("r", Identifier),
+                ("=>", Operator),
+                ("r", Identifier),
+                ("*", Operator),
+                ("3", Number),
+                ("_", Identifier),
+                ("=>", Operator),
+                ("0", Number),
+                ("print", Keyword),
+                ("area", Identifier),
+                ("Circle", Constructor),
+                ("2", Number),
+                ("+", Operator),
+                ("`${", String),
+                ("\"a\"", String),
+                ("}`", String),
+            ]
+        );
+    }
+
+    #[test]
+    fn classifies_names_as_identifiers_without_a_parse() {
+        let source = "let x: Num = Some(";
+        assert_eq!(
+            highlighted(source),
+            vec![
+                ("let", Keyword),
+                ("x", Identifier),
+                ("Num", Identifier),
+                ("=", Operator),
+                ("Some", Identifier),
+            ]
+        );
+    }
+}
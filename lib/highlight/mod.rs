@@ -0,0 +1,3 @@
+mod highlight;
+
+pub use highlight::{highlight, Highlight, HighlightKind};
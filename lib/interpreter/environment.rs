@@ -0,0 +1,52 @@
+use super::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Default)]
+struct Scope {
+    values: HashMap<String, Value>,
+    parent: Option<Rc<RefCell<Scope>>>,
+}
+
+/// A chain of scopes. Cloning an environment shares its scopes, which is how
+/// closures capture the bindings around them.
+#[derive(Default, Clone)]
+pub struct Environment {
+    current: Rc<RefCell<Scope>>,
+}
+
+impl Environment {
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.current
+            .borrow_mut()
+            .values
+            .insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        let mut scope = Some(self.current.to_owned());
+        while let Some(current) = scope {
+            if let Some(value) = current.borrow().values.get(name) {
+                return Some(value.to_owned());
+            }
+            scope = current.borrow().parent.to_owned();
+        }
+        None
+    }
+
+    pub fn push_scope(&mut self) {
+        let scope = Scope {
+            values: HashMap::new(),
+            parent: Some(self.current.to_owned()),
+        };
+        self.current = Rc::new(RefCell::new(scope));
+    }
+
+    pub fn pop_scope(&mut self) {
+        let parent = self.current.borrow().parent.to_owned();
+        if let Some(parent) = parent {
+            self.current = parent;
+        }
+    }
+}
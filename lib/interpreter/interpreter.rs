@@ -1,12 +1,557 @@
-use crate::lexer::Lexer;
+use super::{environment::Environment, Closure, InterpreterError, Value};
+use crate::diagnostics::ErrorCode;
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::loader::{ModuleLoader, INPUT};
+use crate::parser::{Expression, MatchArm, Parser, Pattern, Program, Statement};
+use crate::typechecker::TypeChecker;
 use crate::BoxError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
 
-pub fn eval(input: String) -> Result<(), BoxError> {
-    let tokens = Lexer::new(input.as_bytes()).tokenize()?;
+/// How deeply calls may nest, on either backend, before the program stops
+/// with a stack overflow.
+pub const MAX_CALL_DEPTH: usize = 10_000;
 
-    for expr in tokens {
-        println!("{:#?}", expr);
+/// The native stack a thread needs to run programs on the interpreter, whose
+/// calls nest Rust frames, up to `MAX_CALL_DEPTH`.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Runs source text, resolving imports against the current directory.
+pub fn eval(input: String) -> Result<Option<Value>, BoxError> {
+    let mut loader = ModuleLoader::new();
+    let file_id = loader.add_source(INPUT, &input);
+    let program =
+        Parser::from_lexer(Lexer::with_file(input.as_bytes(), file_id)).parse_program()?;
+
+    let mut checker = TypeChecker::new();
+    let mut interpreter = Interpreter::new();
+    loader.check_imports(&program, Path::new("."), &mut checker)?;
+    checker.check_program(&program)?;
+    loader.run_imports(&program, Path::new("."), &mut interpreter)?;
+
+    Ok(interpreter.run(&program)?)
+}
+
+#[derive(Default)]
+pub struct Interpreter {
+    environment: Environment,
+    modules: HashMap<String, HashMap<String, Value>>,
+    depth: usize,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::default()
+    }
+
+    /// Executes every statement in order, returning the value of the last
+    /// statement if it was an expression.
+    pub fn run(&mut self, program: &Program) -> Result<Option<Value>, InterpreterError> {
+        self.execute_all(&program.statements)
+    }
+
+    /// Runs `program` in a scope inside the one earlier programs defined
+    /// their names in, so that its definitions shadow theirs without
+    /// changing what their closures see. The scope is dropped if it fails.
+    pub fn run_nested(&mut self, program: &Program) -> Result<Option<Value>, InterpreterError> {
+        self.environment.push_scope();
+        let result = self.run(program);
+        if result.is_err() {
+            self.environment.pop_scope();
+        }
+        result
+    }
+
+    /// Makes a module's exported values available to `import` statements naming `path`.
+    pub fn provide_module(&mut self, path: &str, values: HashMap<String, Value>) {
+        self.modules.insert(path.to_owned(), values);
+    }
+
+    /// Collects the values exported by an already run program.
+    pub fn exports(&self, program: &Program) -> HashMap<String, Value> {
+        let mut values = HashMap::new();
+
+        for statement in &program.statements {
+            if let Statement::Export { statement } = statement {
+                if let Statement::Let { name, .. } = statement.as_ref() {
+                    if let Some(value) = self.environment.get(&name.to_string()) {
+                        values.insert(name.to_string(), value);
+                    }
+                }
+            }
+        }
+
+        values
+    }
+
+    fn execute_all(&mut self, statements: &[Statement]) -> Result<Option<Value>, InterpreterError> {
+        let mut last = None;
+        for statement in statements {
+            last = self.execute(statement)?;
+        }
+        Ok(last)
+    }
+
+    fn execute_block(
+        &mut self,
+        statements: &[Statement],
+    ) -> Result<Option<Value>, InterpreterError> {
+        self.environment.push_scope();
+        let result = self.execute_all(statements);
+        self.environment.pop_scope();
+        result
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<Option<Value>, InterpreterError> {
+        match statement {
+            Statement::Let {
+                name, initializer, ..
+            } => {
+                let value = self.evaluate(initializer)?;
+                self.environment.define(&name.to_string(), value);
+                Ok(None)
+            }
+            Statement::Expression { expr } => Ok(Some(self.evaluate(expr)?)),
+            Statement::Print { expr } => {
+                println!("{}", self.evaluate(expr)?);
+                Ok(None)
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => match self.evaluate(condition)? {
+                Value::Bool(true) => self.execute_block(then_branch),
+                Value::Bool(false) => match else_branch {
+                    Some(else_branch) => self.execute_block(else_branch),
+                    None => Ok(None),
+                },
+                other => Err(InterpreterError::new(
+                    ErrorCode::InvalidOperand,
+                    condition.token(),
+                    format!("Expect a boolean condition, found a {}", other.type_name()),
+                )),
+            },
+            Statement::Type { .. } => Ok(None),
+            Statement::Import { names, path } => {
+                let values = match self.modules.get(&path.to_string()) {
+                    Some(values) => values,
+                    None => {
+                        return Err(InterpreterError::new(
+                            ErrorCode::InvalidProgram,
+                            path,
+                            format!("Unresolved module {}", path),
+                        ))
+                    }
+                };
+
+                // Imported types have no runtime value, so only values are bound.
+                let imported: Vec<(String, Value)> = names
+                    .iter()
+                    .filter_map(|name| {
+                        values
+                            .get(&name.to_string())
+                            .map(|value| (name.to_string(), value.to_owned()))
+                    })
+                    .collect();
+                for (name, value) in imported {
+                    self.environment.define(&name, value);
+                }
+                Ok(None)
+            }
+            Statement::Export { statement } => self.execute(statement),
+        }
+    }
+
+    fn evaluate_match(
+        &mut self,
+        keyword: &Token,
+        subject: Value,
+        arms: &[MatchArm],
+    ) -> Result<Value, InterpreterError> {
+        for arm in arms {
+            let mut bindings = vec![];
+            if !match_pattern(&arm.pattern, &subject, &mut bindings)? {
+                continue;
+            }
+
+            self.environment.push_scope();
+            for (name, value) in bindings {
+                self.environment.define(&name, value);
+            }
+            let result = self.evaluate(&arm.body);
+            self.environment.pop_scope();
+            return result;
+        }
+
+        Err(InterpreterError::new(
+            ErrorCode::NoMatch,
+            keyword,
+            format!("No pattern matched {}", subject),
+        ))
+    }
+
+    fn call(
+        &mut self,
+        paren: &Token,
+        callee: Value,
+        args: Vec<Value>,
+    ) -> Result<Value, InterpreterError> {
+        let closure = match callee {
+            Value::Function(closure) => closure,
+            other => {
+                return Err(InterpreterError::new(
+                    ErrorCode::InvalidOperand,
+                    paren,
+                    format!("Cannot call a {}", other.type_name()),
+                ))
+            }
+        };
+        if closure.params.len() != args.len() {
+            return Err(InterpreterError::new(
+                ErrorCode::InvalidOperand,
+                paren,
+                format!(
+                    "Function expects {0} arguments, found {1}",
+                    closure.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(InterpreterError::new(
+                ErrorCode::StackOverflow,
+                paren,
+                String::from("Stack overflow"),
+            ));
+        }
+
+        // The body runs in the environment the closure was created in.
+        let caller = std::mem::replace(&mut self.environment, closure.environment.clone());
+        self.environment.push_scope();
+        for (param, arg) in closure.params.iter().zip(args) {
+            self.environment.define(&param.to_string(), arg);
+        }
+        self.depth += 1;
+        let result = self.evaluate(&closure.body);
+        self.depth -= 1;
+        self.environment = caller;
+
+        result
+    }
+
+    fn evaluate(&mut self, expr: &Expression) -> Result<Value, InterpreterError> {
+        match expr {
+            Expression::Literal { lit, .. } => literal(lit),
+            Expression::Variable { name, .. } => match self.environment.get(&name.to_string()) {
+                Some(value) => Ok(value),
+                None => Err(InterpreterError::new(
+                    ErrorCode::InvalidProgram,
+                    name,
+                    format!("Undefined variable {}", name),
+                )),
+            },
+            Expression::Grouping { expr, .. } => self.evaluate(expr),
+            Expression::Construct { name, args, .. } => {
+                let mut values = vec![];
+                for arg in args {
+                    values.push(self.evaluate(arg)?);
+                }
+                Ok(Value::Constructor {
+                    name: name.to_string(),
+                    args: values,
+                })
+            }
+            Expression::Record { fields, .. } => {
+                let mut values = vec![];
+                for (name, value) in fields {
+                    values.push((name.to_string(), self.evaluate(value)?));
+                }
+                Ok(Value::Record { fields: values })
+            }
+            Expression::Get { object, name, .. } => match self.evaluate(object)? {
+                Value::Record { fields } => fields
+                    .into_iter()
+                    .find(|(field, _)| *field == name.to_string())
+                    .map(|(_, value)| value)
+                    .ok_or_else(|| {
+                        InterpreterError::new(
+                            ErrorCode::InvalidOperand,
+                            name,
+                            format!("Undefined field {}", name),
+                        )
+                    }),
+                other => Err(InterpreterError::new(
+                    ErrorCode::InvalidOperand,
+                    name,
+                    format!("Cannot read field {0} of a {1}", name, other.type_name()),
+                )),
+            },
+            Expression::Tuple { elements, .. } => {
+                let mut values = vec![];
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            Expression::Match {
+                keyword,
+                subject,
+                arms,
+                ..
+            } => {
+                let subject = self.evaluate(subject)?;
+                self.evaluate_match(keyword, subject, arms)
+            }
+            Expression::Lambda { params, body, .. } => Ok(Value::Function(Rc::new(Closure {
+                params: params.to_owned(),
+                body: Rc::clone(body),
+                environment: self.environment.clone(),
+            }))),
+            Expression::Call {
+                callee,
+                paren,
+                args,
+                ..
+            } => {
+                let callee = self.evaluate(callee)?;
+                let mut values = vec![];
+                for arg in args {
+                    values.push(self.evaluate(arg)?);
+                }
+                self.call(paren, callee, values)
+            }
+            Expression::Unary { op, right, .. } => {
+                let right = self.evaluate(right)?;
+                unary(op, right)
+            }
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(left, op, right)
+            }
+        }
+    }
+}
+
+// Binds the variables of `pattern` if it matches `value`.
+fn match_pattern(
+    pattern: &Pattern,
+    value: &Value,
+    bindings: &mut Vec<(String, Value)>,
+) -> Result<bool, InterpreterError> {
+    match (pattern, value) {
+        (Pattern::Wildcard { .. }, _) => Ok(true),
+        (Pattern::Binding { name }, value) => {
+            bindings.push((name.to_string(), value.to_owned()));
+            Ok(true)
+        }
+        (Pattern::Literal { lit }, value) => Ok(literal(lit)? == *value),
+        (
+            Pattern::Constructor { name, args },
+            Value::Constructor {
+                name: tag,
+                args: values,
+            },
+        ) => {
+            if name.to_string() != *tag || args.len() != values.len() {
+                return Ok(false);
+            }
+            match_all(args.iter().zip(values), bindings)
+        }
+        (Pattern::Record { fields, .. }, Value::Record { fields: values }) => {
+            let mut pairs = vec![];
+            for (name, field_pattern) in fields {
+                match values.iter().find(|(field, _)| *field == name.to_string()) {
+                    Some((_, field_value)) => pairs.push((field_pattern, field_value)),
+                    None => return Ok(false),
+                }
+            }
+            match_all(pairs.into_iter(), bindings)
+        }
+        (Pattern::Tuple { elements, .. }, Value::Tuple(values)) => {
+            if elements.len() != values.len() {
+                return Ok(false);
+            }
+            match_all(elements.iter().zip(values), bindings)
+        }
+        _ => Ok(false),
+    }
+}
+
+fn match_all<'a>(
+    pairs: impl Iterator<Item = (&'a Pattern, &'a Value)>,
+    bindings: &mut Vec<(String, Value)>,
+) -> Result<bool, InterpreterError> {
+    for (pattern, value) in pairs {
+        if !match_pattern(pattern, value, bindings)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+pub(crate) fn literal(lit: &Token) -> Result<Value, InterpreterError> {
+    match lit.kind {
+        TokenKind::True => Ok(Value::Bool(true)),
+        TokenKind::False => Ok(Value::Bool(false)),
+        TokenKind::Str => Ok(Value::Str(lit.literal.to_owned().unwrap_or_default())),
+        TokenKind::Num => lit
+            .literal
+            .as_ref()
+            .and_then(|n| n.parse::<f64>().ok())
+            .map(Value::Num)
+            .ok_or_else(|| {
+                InterpreterError::new(
+                    ErrorCode::InvalidProgram,
+                    lit,
+                    format!("Invalid number literal {}", lit),
+                )
+            }),
+        _ => Err(InterpreterError::new(
+            ErrorCode::InvalidProgram,
+            lit,
+            format!("Unexpected literal {}", lit),
+        )),
+    }
+}
+
+pub(crate) fn unary(op: &Token, right: Value) -> Result<Value, InterpreterError> {
+    match (op.kind, right) {
+        (TokenKind::Minus, Value::Num(n)) => Ok(Value::Num(-n)),
+        (TokenKind::Bang, Value::Bool(b)) => Ok(Value::Bool(!b)),
+        (_, right) => Err(InterpreterError::new(
+            ErrorCode::InvalidOperand,
+            op,
+            format!("Cannot apply \"{}\" to a {}", op, right.type_name()),
+        )),
+    }
+}
+
+pub(crate) fn binary(left: Value, op: &Token, right: Value) -> Result<Value, InterpreterError> {
+    match (left, op.kind, right) {
+        (Value::Num(l), TokenKind::Plus, Value::Num(r)) => Ok(Value::Num(l + r)),
+        (Value::Num(l), TokenKind::Minus, Value::Num(r)) => Ok(Value::Num(l - r)),
+        (Value::Num(l), TokenKind::Star, Value::Num(r)) => Ok(Value::Num(l * r)),
+        (Value::Num(l), TokenKind::Slash, Value::Num(r)) => Ok(Value::Num(l / r)),
+        (Value::Num(l), TokenKind::Greater, Value::Num(r)) => Ok(Value::Bool(l > r)),
+        (Value::Num(l), TokenKind::GreaterEqual, Value::Num(r)) => Ok(Value::Bool(l >= r)),
+        (Value::Num(l), TokenKind::Less, Value::Num(r)) => Ok(Value::Bool(l < r)),
+        (Value::Num(l), TokenKind::LessEqual, Value::Num(r)) => Ok(Value::Bool(l <= r)),
+        (Value::Str(l), TokenKind::Plus, Value::Str(r)) => Ok(Value::Str(l + &r)),
+        (l, TokenKind::EqualEqual, r) if l.type_name() == r.type_name() => Ok(Value::Bool(l == r)),
+        (l, TokenKind::BangEqual, r) if l.type_name() == r.type_name() => Ok(Value::Bool(l != r)),
+        (l, _, r) => Err(InterpreterError::new(
+            ErrorCode::InvalidOperand,
+            op,
+            format!(
+                "Cannot apply \"{}\" to a {} and a {}",
+                op,
+                l.type_name(),
+                r.type_name()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_value(input: &str) -> Value {
+        eval(input.to_string()).unwrap().unwrap()
     }
 
-    Ok(())
+    #[test]
+    fn evaluates_arithmetic_with_precedence() {
+        assert_eq!(eval_value("1 + 2 * (3 - 1) / 4"), Value::Num(2.0));
+    }
+
+    #[test]
+    fn evaluates_comparisons_and_equality() {
+        assert_eq!(eval_value("1 < 2 == true"), Value::Bool(true));
+        assert_eq!(eval_value("!(3 >= 4)"), Value::Bool(true));
+        assert_eq!(
+            eval_value("\"light\" + \"script\""),
+            Value::Str("lightscript".to_string())
+        );
+    }
+
+    #[test]
+    fn executes_let_bindings_and_if_statements() {
+        let source = "let x = 2; let y = 0; if x > 1 { let y = 10; y } else { y }";
+        assert_eq!(eval_value(source), Value::Num(10.0));
+        assert!(eval("{ x }".to_string()).is_err());
+    }
+
+    #[test]
+    fn evaluates_constructors_and_records() {
+        let source = "type Option<a> = Some(a) | None;
+            type Point = { x: Num, y: Option<Num> };
+            let p = { x: 1, y: Some(2) };
+            p.y";
+        assert_eq!(
+            eval_value(source),
+            Value::Constructor {
+                name: "Some".to_string(),
+                args: vec![Value::Num(2.0)]
+            }
+        );
+        assert_eq!(
+            eval_value("type Option<a> = Some(a) | None; Some(1) == None"),
+            Value::Bool(false)
+        );
+    }
+
+    #[test]
+    fn evaluates_match_expressions() {
+        let source = "type Option<a> = Some(a) | None;
+            type Point = { x: Num, y: Num };
+            let classify = match (Some(2), { x: 0, y: 5 }) {
+                (None, _) => \"none\",
+                (Some(1), _) => \"one\",
+                (Some(n), { x: 0, y }) => \"axis\" + match n > y { true => \"!\", false => \"?\" },
+                (Some(_), _) => \"other\",
+            };
+            classify";
+        assert_eq!(eval_value(source), Value::Str("axis?".to_string()));
+    }
+
+    #[test]
+    fn reports_operand_mismatch() {
+        assert!(eval("1 + \"one\"".to_string()).is_err());
+        assert!(eval("-true".to_string()).is_err());
+    }
+
+    #[test]
+    fn calls_closures() {
+        let source = "let adder = n => x => x + n;
+            let add2 = adder(2);
+            let n = 100;
+            add2(1)";
+        assert_eq!(eval_value(source), Value::Num(3.0));
+
+        let source = "let fact = n => match n == 0 { true => 1, false => n * fact(n - 1) };
+            fact(5)";
+        assert_eq!(eval_value(source), Value::Num(120.0));
+    }
+
+    #[test]
+    fn interpolates_templates() {
+        let source = "let name = 'w\\u{f6}rld'; let greet = (n) => `hello ${n}!\\n`; greet(name)";
+        assert_eq!(eval_value(source), Value::Str("hello wörld!\n".to_string()));
+    }
+
+    #[test]
+    fn displays_strings_as_they_are_written() {
+        let source = "type Q = { a: Str }; ({ a: \"q\\\"\" }, \"tab\\t\\x01\\\\\")";
+        assert_eq!(
+            eval_value(source).to_string(),
+            "({ a: \"q\\\"\" }, \"tab\\t\\x01\\\\\")"
+        );
+    }
 }
@@ -0,0 +1,58 @@
+use crate::diagnostics::{Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::lexer::Token;
+use crate::source::Span;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug)]
+pub struct InterpreterError {
+    pub code: ErrorCode,
+    pub line: u32,
+    pub col: u32,
+    pub span: Span,
+    pub text: String,
+}
+
+impl InterpreterError {
+    pub fn new(code: ErrorCode, token: &Token, text: String) -> Self {
+        InterpreterError {
+            code,
+            line: token.line,
+            col: token.col,
+            span: token.span,
+            text,
+        }
+    }
+
+    pub fn at(code: ErrorCode, line: u32, col: u32, span: Span, text: String) -> Self {
+        InterpreterError {
+            code,
+            line,
+            col,
+            span,
+            text,
+        }
+    }
+}
+
+impl Display for InterpreterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Runtime error \"{0}\" at line {1}, column {2}.",
+            self.text, self.line, self.col
+        )
+    }
+}
+
+impl Error for InterpreterError {}
+
+impl ToDiagnostics for InterpreterError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        vec![Diagnostic::error(&self.text)
+            .with_code(self.code)
+            .with_label(Label::primary(self.span, ""))]
+    }
+}
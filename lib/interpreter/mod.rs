@@ -1,4 +1,9 @@
+mod environment;
 mod interpreter;
 mod interpreter_error;
+mod value;
 
-pub use interpreter::eval;
+pub(crate) use interpreter::{binary, literal, unary};
+pub use interpreter::{eval, Interpreter, MAX_CALL_DEPTH, STACK_SIZE};
+pub use interpreter_error::InterpreterError;
+pub use value::{Closure, Value};
@@ -0,0 +1,113 @@
+use super::environment::Environment;
+use crate::lexer::Token;
+use crate::parser::Expression;
+use std::fmt;
+use std::rc::Rc;
+
+/// A function value together with the environment it was created in.
+pub struct Closure {
+    pub params: Vec<Token>,
+    pub body: Rc<Expression>,
+    pub(crate) environment: Environment,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({})", self)
+    }
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+        write!(f, "({0}) => {1}", params.join(", "), self.body)
+    }
+}
+
+// Functions are only equal to themselves.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Num(f64),
+    Str(String),
+    Bool(bool),
+    Constructor { name: String, args: Vec<Value> },
+    Record { fields: Vec<(String, Value)> },
+    Tuple(Vec<Value>),
+    Function(Rc<Closure>),
+    Compiled(Rc<crate::vm::Closure>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", n),
+            Value::Str(s) => write!(f, "\"{}\"", escape(s)),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Constructor { name, args } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                    write!(f, "({})", args.join(", "))?;
+                }
+                Ok(())
+            }
+            Value::Record { fields } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{0}: {1}", name, value))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Value::Tuple(elements) => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+            Value::Function(closure) => write!(f, "{}", closure),
+            Value::Compiled(closure) => write!(f, "{}", closure),
+        }
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Num(_) => "number",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "boolean",
+            Value::Constructor { .. } => "constructor",
+            Value::Record { .. } => "record",
+            Value::Tuple(_) => "tuple",
+            Value::Function(_) | Value::Compiled(_) => "function",
+        }
+    }
+}
+
+// Writes `text` with the escapes the lexer reads, so a printed string reads
+// back as the same string.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{8}' => escaped.push_str("\\b"),
+            '\u{c}' => escaped.push_str("\\f"),
+            '\u{b}' => escaped.push_str("\\v"),
+            '\0' => escaped.push_str("\\0"),
+            c if c.is_control() && (c as u32) < 0x100 => {
+                escaped.push_str(&format!("\\x{:02x}", c as u32))
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -1,7 +1,11 @@
 use super::{LexerError, ParseTokenError, Token, TokenKind};
+use crate::diagnostics::ErrorCode;
+use crate::source::{FileId, Span};
 use phf::phf_map;
+use unicode_xid::UnicodeXID;
 
-static KEYWORDS: phf::Map<&'static str, TokenKind> = phf_map! {
+/// The reserved words and the token each one is read as.
+pub static KEYWORDS: phf::Map<&'static str, TokenKind> = phf_map! {
     "let" => TokenKind::Let,
     "true" => TokenKind::True,
     "false" => TokenKind::False,
@@ -12,161 +16,460 @@ static KEYWORDS: phf::Map<&'static str, TokenKind> = phf_map! {
     "from" => TokenKind::From,
     "export" => TokenKind::Export,
     "print" => TokenKind::Print,
+    "match" => TokenKind::Match,
 };
 
 pub struct Lexer<'a> {
     source: &'a [u8],
+    file_id: FileId,
     start: usize,
     current: usize,
     line: u32,
+    // The column of the next character, counted in characters.
+    column: u32,
+    // The line and column the current token starts at.
+    start_line: u32,
     col: u32,
     swap: Option<String>,
+    // The brace depth inside each template interpolation that is still open.
+    templates: Vec<u32>,
+    trivia: bool,
+    finished: bool,
 }
 
-fn is_identifier_start(b: u8) -> bool {
-    b.is_ascii_alphabetic() || b == b'_'
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_xid_start()
 }
 
-fn is_identifier_char(b: u8) -> bool {
-    is_identifier_start(b) || is_digit(b)
+fn is_identifier_char(c: char) -> bool {
+    c.is_xid_continue()
 }
 
-fn is_digit(b: u8) -> bool {
-    b.is_ascii_digit()
+fn is_digit(c: char) -> bool {
+    c.is_ascii_digit()
+}
+
+/// Decodes the character starting at `bytes[0]` and returns it with its
+/// width in bytes, or `None` if the bytes are not valid UTF-8.
+fn decode(bytes: &[u8]) -> Option<(char, usize)> {
+    let width = match bytes.first()? {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return None,
+    };
+    let c = std::str::from_utf8(bytes.get(..width)?)
+        .ok()?
+        .chars()
+        .next()?;
+    Some((c, width))
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(source: &'a [u8]) -> Self {
+        Lexer::with_file(source, FileId::default())
+    }
+
+    /// A lexer whose token spans point into the file `file_id`.
+    pub fn with_file(source: &'a [u8], file_id: FileId) -> Self {
         Lexer {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
             col: 1,
             source,
+            file_id,
             swap: None,
+            templates: vec![],
+            trivia: true,
+            finished: false,
         }
     }
 
+    /// Whether whitespace and comments other than doc comments are yielded.
+    /// They are by default.
+    pub fn with_trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    fn span(&self) -> Span {
+        Span::new(self.file_id, self.start, self.current)
+    }
+
     fn token(&mut self, kind: TokenKind, literal: Option<String>) -> Token {
         Token {
             kind,
             literal,
-            line: self.line,
+            line: self.start_line,
             col: self.col,
+            span: self.span(),
         }
     }
 
-    fn parse_error(&mut self, text: &str) -> ParseTokenError {
+    fn parse_error(&mut self, code: ErrorCode, text: &str) -> ParseTokenError {
         ParseTokenError {
-            line: self.line,
+            code,
+            line: self.start_line,
             col: self.col,
+            span: self.span(),
             text: text.to_owned(),
         }
     }
 
-    fn advance(&mut self) -> u8 {
-        if self.peek_n(1) == b'\n' {
+    // An error covering the source from `start` to the current character.
+    fn error_at(
+        &self,
+        code: ErrorCode,
+        start: usize,
+        line: u32,
+        col: u32,
+        text: &str,
+    ) -> ParseTokenError {
+        let span = Span::new(self.file_id, start, self.current);
+        ParseTokenError::new(code, line, col, span, text.to_owned())
+    }
+
+    /// Consumes one character. A byte that does not start a valid UTF-8
+    /// sequence is consumed on its own and read as U+FFFD.
+    fn advance(&mut self) -> char {
+        let (c, width) = decode(&self.source[self.current..]).unwrap_or(('\u{fffd}', 1));
+        self.current += width;
+        if c == '\n' {
             self.line += 1;
-            self.col = 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
-        self.current += 1;
-        self.source[self.current - 1]
+        c
     }
 
-    fn peek_n(&mut self, lookahead: usize) -> u8 {
-        let index = self.current + lookahead - 1;
-        if index >= self.source.len() {
-            return b'\0';
+    fn peek_n(&mut self, lookahead: usize) -> char {
+        let mut rest = &self.source[self.current..];
+        for _ in 1..lookahead {
+            let width = decode(rest).map_or(1, |(_, width)| width);
+            rest = rest.get(width..).unwrap_or(&[]);
+        }
+        if rest.is_empty() {
+            return '\0';
         }
-        self.source[index]
+        decode(rest).map_or('\u{fffd}', |(c, _)| c)
     }
 
     fn at_eof(&mut self) -> bool {
         self.current >= self.source.len()
     }
 
-    fn check_double(&mut self, expected: u8, double: TokenKind, single: TokenKind) -> TokenKind {
-        if self.at_eof() {
-            return single;
-        }
-        if self.source[self.current] != expected {
+    fn check_double(&mut self, expected: char, double: TokenKind, single: TokenKind) -> TokenKind {
+        if self.at_eof() || self.peek_n(1) != expected {
             return single;
         }
-        self.current += 1;
+        self.advance();
         double
     }
 
     fn eat_comment(&mut self) -> Result<TokenKind, ParseTokenError> {
-        if self.peek_n(1) != b'/' {
+        if self.peek_n(1) == '*' {
+            return self.eat_block_comment();
+        }
+        if self.peek_n(1) != '/' {
             return Ok(TokenKind::Slash);
         }
 
-        while self.peek_n(1) != b'\n' && !self.at_eof() {
+        // `///` starts a doc comment, but `////` and more are plain comments.
+        let (kind, prefix) = match (self.peek_n(2), self.peek_n(3)) {
+            ('/', '/') => (TokenKind::Comment, 2),
+            ('/', _) => (TokenKind::DocComment, 3),
+            _ => (TokenKind::Comment, 2),
+        };
+
+        while self.peek_n(1) != '\n' && !self.at_eof() {
+            self.advance();
+        }
+
+        self.comment_text(kind, self.start + prefix..self.current)
+    }
+
+    fn eat_block_comment(&mut self) -> Result<TokenKind, ParseTokenError> {
+        self.advance();
+        while !(self.peek_n(1) == '*' && self.peek_n(2) == '/') {
+            if self.at_eof() {
+                return Err(self.parse_error(
+                    ErrorCode::UnterminatedComment,
+                    "Unterminated block comment!",
+                ));
+            }
             self.advance();
         }
+        self.advance();
+        self.advance();
+
+        self.comment_text(TokenKind::BlockComment, self.start + 2..self.current - 2)
+    }
 
-        if let Some(lit_bytes) = self.source.get(self.start + 2..self.current) {
+    fn comment_text(
+        &mut self,
+        kind: TokenKind,
+        range: std::ops::Range<usize>,
+    ) -> Result<TokenKind, ParseTokenError> {
+        if let Some(lit_bytes) = self.source.get(range) {
             if let Ok(lit_string) = String::from_utf8(lit_bytes.to_vec()) {
                 self.swap = Some(lit_string);
-                return Ok(TokenKind::Comment);
+                return Ok(kind);
             }
         }
 
-        Err(self.parse_error("Error parsing comment!"))
+        Err(self.parse_error(ErrorCode::InvalidUtf8, "Error parsing comment!"))
     }
 
-    fn eat_whitespace(&mut self, newline: bool) -> TokenKind {
-        if newline {
-            self.line += 1;
-        }
-        while self.peek_n(1).is_ascii_whitespace() && !self.at_eof() {
+    fn eat_whitespace(&mut self) -> TokenKind {
+        while self.peek_n(1).is_whitespace() && !self.at_eof() {
             self.advance();
         }
         TokenKind::Whitespace
     }
 
-    fn eat_string(&mut self) -> Result<TokenKind, ParseTokenError> {
-        while self.peek_n(1) != b'"' && !self.at_eof() {
-            self.advance();
+    fn eat_string(&mut self, quote: char) -> Result<TokenKind, ParseTokenError> {
+        let (text, _) = self.eat_text(quote, false)?;
+        self.swap = Some(text);
+        Ok(TokenKind::Str)
+    }
+
+    /// Reads a template part, starting after its opening backtick or the `}`
+    /// closing an interpolation, up to the closing backtick or the next `${`.
+    fn eat_template(&mut self, opened: bool) -> Result<TokenKind, ParseTokenError> {
+        let (text, interpolated) = self.eat_text('`', true)?;
+        self.swap = Some(text);
+        Ok(match (opened, interpolated) {
+            (true, false) => TokenKind::Template,
+            (true, true) => TokenKind::TemplateHead,
+            (false, true) => TokenKind::TemplateMiddle,
+            (false, false) => TokenKind::TemplateTail,
+        })
+    }
+
+    /// Reads characters up to `close`, decoding escapes. Templates also stop
+    /// at `${`, which is reported in the second value. An invalid escape or
+    /// byte is reported only once the whole literal has been read.
+    fn eat_text(&mut self, close: char, template: bool) -> Result<(String, bool), ParseTokenError> {
+        let mut text = String::new();
+        let mut error = None;
+        let interpolated = loop {
+            if self.at_eof() {
+                let message = if template {
+                    "Unterminated template literal!"
+                } else {
+                    "Unterminated string!"
+                };
+                return Err(self.parse_error(ErrorCode::UnterminatedString, message));
+            }
+
+            let (start, line, col) = (self.current, self.line, self.column);
+            let valid = decode(&self.source[self.current..]).is_some();
+            let c = self.advance();
+            if !valid {
+                let err = self.error_at(
+                    ErrorCode::InvalidUtf8,
+                    start,
+                    line,
+                    col,
+                    "Error parsing string!",
+                );
+                error.get_or_insert(err);
+            } else if c == close {
+                break false;
+            } else if template && c == '$' && self.peek_n(1) == '{' {
+                self.advance();
+                // Opened even if the literal is in error, so the `}` closing
+                // the interpolation is still read as the start of a template.
+                self.templates.push(0);
+                break true;
+            } else if c == '\\' {
+                match self.eat_escape() {
+                    Ok(c) => text.push(c),
+                    Err(message) => {
+                        let err =
+                            self.error_at(ErrorCode::InvalidEscape, start, line, col, &message);
+                        error.get_or_insert(err);
+                    }
+                }
+            } else {
+                text.push(c);
+            }
+        };
+
+        match error {
+            Some(err) => Err(err),
+            None => Ok((text, interpolated)),
         }
+    }
 
+    /// Decodes the escape after a backslash.
+    fn eat_escape(&mut self) -> Result<char, String> {
         if self.at_eof() {
-            return Err(self.parse_error("Unterminated string!"));
+            return Err("Unterminated escape sequence".to_owned());
+        }
+        let c = match self.advance() {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' => '\0',
+            'x' => return self.eat_hex_escape('x', 2),
+            'u' if self.peek_n(1) != '{' => return self.eat_hex_escape('u', 4),
+            'u' => return self.eat_unicode_escape(),
+            c @ ('\\' | '"' | '\'' | '`' | '$') => c,
+            c => return Err(format!("Unknown escape sequence \\{}", c)),
+        };
+        Ok(c)
+    }
+
+    // Decodes `\xHH` or `\uHHHH`, which take exactly `count` hex digits.
+    fn eat_hex_escape(&mut self, escape: char, count: usize) -> Result<char, String> {
+        let mut digits = String::new();
+        while digits.len() < count && self.peek_n(1).is_ascii_hexdigit() {
+            digits.push(self.advance());
         }
+        if digits.len() < count {
+            return Err(format!(
+                "Invalid escape, expected {0} hex digits after \\{1}",
+                count, escape
+            ));
+        }
+
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("\\{0}{1} is not a Unicode scalar value", escape, digits))
+    }
 
+    fn eat_unicode_escape(&mut self) -> Result<char, String> {
+        let invalid =
+            || "Invalid unicode escape, expected \\u{...} with 1 to 6 hex digits".to_owned();
+        if self.peek_n(1) != '{' {
+            return Err(invalid());
+        }
         self.advance();
 
-        if let Some(lit_bytes) = self.source.get(self.start + 1..self.current - 1) {
-            if let Ok(lit_string) = String::from_utf8(lit_bytes.to_vec()) {
-                self.swap = Some(lit_string);
-                return Ok(TokenKind::Str);
-            }
+        let mut digits = String::new();
+        while self.peek_n(1).is_ascii_hexdigit() {
+            digits.push(self.advance());
+        }
+        if self.peek_n(1) != '}' || digits.is_empty() || digits.len() > 6 {
+            return Err(invalid());
         }
+        self.advance();
 
-        Err(self.parse_error("Error parsing string!"))
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| format!("\\u{{{}}} is not a Unicode scalar value", digits))
     }
 
-    fn eat_number(&mut self) -> Result<TokenKind, ParseTokenError> {
-        while is_digit(self.peek_n(1)) {
-            self.advance();
+    /// Reads a number after its first digit. The literal is the number
+    /// written in decimal without separators, so `0xff` reads as `255`.
+    fn eat_number(&mut self, first: char) -> Result<TokenKind, ParseTokenError> {
+        let number = match (first, self.peek_n(1)) {
+            ('0', 'x' | 'X') => self.eat_radix_number(16, "hexadecimal"),
+            ('0', 'o' | 'O') => self.eat_radix_number(8, "octal"),
+            ('0', 'b' | 'B') => self.eat_radix_number(2, "binary"),
+            _ => self.eat_decimal_number(first),
+        }
+        .and_then(|number| match self.peek_n(1) {
+            c if is_identifier_char(c) => Err(format!("Unexpected {} after number", c)),
+            _ => Ok(number),
+        });
+
+        match number {
+            Ok(number) => {
+                self.swap = Some(number);
+                Ok(TokenKind::Num)
+            }
+            Err(message) => {
+                // Skip the rest of the literal so it is reported only once.
+                while is_identifier_char(self.peek_n(1)) {
+                    self.advance();
+                }
+                Err(self.parse_error(ErrorCode::InvalidNumber, &message))
+            }
         }
+    }
 
-        if self.peek_n(1) == b'.' && is_digit(self.peek_n(2)) {
-            self.advance();
+    fn eat_radix_number(&mut self, radix: u32, name: &str) -> Result<String, String> {
+        self.advance();
+        let mut digits = String::new();
+        self.eat_digits(radix, &mut digits)?;
+        if digits.is_empty() {
+            return match self.peek_n(1) {
+                c if is_identifier_char(c) => {
+                    Err(format!("Invalid digit {} in {} literal", c, name))
+                }
+                _ => Err(format!("Expected {} digits", name)),
+            };
+        }
+        if is_digit(self.peek_n(1)) {
+            return Err(format!(
+                "Invalid digit {} in {} literal",
+                self.peek_n(1),
+                name
+            ));
+        }
 
-            while is_digit(self.peek_n(1)) {
-                self.advance();
-            }
+        let value = digits.chars().fold(0.0, |value: f64, digit| {
+            value * radix as f64 + digit.to_digit(radix).unwrap_or(0) as f64
+        });
+        Ok(value.to_string())
+    }
+
+    fn eat_decimal_number(&mut self, first: char) -> Result<String, String> {
+        let mut number = first.to_string();
+        self.eat_digits(10, &mut number)?;
+
+        if self.peek_n(1) == '.' && is_digit(self.peek_n(2)) {
+            number.push(self.advance());
+            self.eat_digits(10, &mut number)?;
         }
 
-        if let Some(lit_bytes) = self.source.get(self.start..self.current) {
-            if let Ok(lit_string) = String::from_utf8(lit_bytes.to_vec()) {
-                self.swap = Some(lit_string);
-                return Ok(TokenKind::Num);
+        if let 'e' | 'E' = self.peek_n(1) {
+            number.push(self.advance());
+            if let '+' | '-' = self.peek_n(1) {
+                number.push(self.advance());
+            }
+            let digits = number.len();
+            self.eat_digits(10, &mut number)?;
+            if number.len() == digits {
+                return Err("Expected digits in exponent".to_owned());
             }
         }
 
-        Err(self.parse_error("Error parsing number!"))
+        Ok(number)
+    }
+
+    /// Reads digits in `radix` onto `digits`. Single underscores may
+    /// separate them, but not follow anything else already read.
+    fn eat_digits(&mut self, radix: u32, digits: &mut String) -> Result<(), String> {
+        let mut previous = digits.chars().last();
+        loop {
+            let c = self.peek_n(1);
+            if c.is_digit(radix) {
+                digits.push(c);
+            } else if c == '_' {
+                if !previous.is_some_and(|p: char| p.is_digit(radix))
+                    || !self.peek_n(2).is_digit(radix)
+                {
+                    self.advance();
+                    return Err("A digit separator must be between two digits".to_owned());
+                }
+            } else {
+                return Ok(());
+            }
+            previous = Some(self.advance());
+        }
     }
 
     fn eat_identifier(&mut self) -> Result<TokenKind, ParseTokenError> {
@@ -187,74 +490,441 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        Err(self.parse_error("Error parsing identifier!"))
+        Err(self.parse_error(ErrorCode::InvalidUtf8, "Error parsing identifier!"))
     }
 
     fn match_token(&mut self) -> Result<Token, ParseTokenError> {
-        let b = self.advance();
-        let token_kind = match b {
-            b'(' => TokenKind::LeftParen,
-            b')' => TokenKind::RightParen,
-            b'{' => TokenKind::LeftBrace,
-            b'}' => TokenKind::RightBrace,
-            b'[' => TokenKind::LeftBracket,
-            b']' => TokenKind::RightBracket,
-            b',' => TokenKind::Comma,
-            b'.' => TokenKind::Dot,
-            b'-' => TokenKind::Minus,
-            b'+' => TokenKind::Plus,
-            b';' => TokenKind::Semicolon,
-            b'*' => TokenKind::Star,
-            b'!' => self.check_double(b'=', TokenKind::BangEqual, TokenKind::Bang),
-            b'=' => self.check_double(b'=', TokenKind::EqualEqual, TokenKind::Equal),
-            b'>' => self.check_double(b'=', TokenKind::GreaterEqual, TokenKind::Greater),
-            b'<' => self.check_double(b'=', TokenKind::LessEqual, TokenKind::Greater),
-            b'&' => self.check_double(b'&', TokenKind::AmpersandAmpersand, TokenKind::Ampersand),
-            b'|' => self.check_double(b'|', TokenKind::BarBar, TokenKind::Bar),
-            b'/' => self.eat_comment()?,
-            b'\n' => self.eat_whitespace(true),
-            b' ' => self.eat_whitespace(false),
-            b'\r' => self.eat_whitespace(false),
-            b'\t' => self.eat_whitespace(false),
-            b'"' => self.eat_string()?,
-            d if is_digit(d) => self.eat_number()?,
+        if decode(&self.source[self.current..]).is_none() {
+            let byte = self.source[self.current];
+            self.advance();
+            return Err(self.parse_error(
+                ErrorCode::InvalidUtf8,
+                &format!("Invalid UTF-8 byte 0x{:02x}", byte),
+            ));
+        }
+
+        let c = self.advance();
+        let token_kind = match c {
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            '{' => {
+                if let Some(depth) = self.templates.last_mut() {
+                    *depth += 1;
+                }
+                TokenKind::LeftBrace
+            }
+            '}' if self.templates.last() == Some(&0) => {
+                self.templates.pop();
+                self.eat_template(false)?
+            }
+            '}' => {
+                if let Some(depth) = self.templates.last_mut() {
+                    *depth -= 1;
+                }
+                TokenKind::RightBrace
+            }
+            '[' => TokenKind::LeftBracket,
+            ']' => TokenKind::RightBracket,
+            ',' => TokenKind::Comma,
+            ':' => TokenKind::Colon,
+            '.' => TokenKind::Dot,
+            '-' => TokenKind::Minus,
+            '+' => TokenKind::Plus,
+            ';' => TokenKind::Semicolon,
+            '*' => TokenKind::Star,
+            '!' => self.check_double('=', TokenKind::BangEqual, TokenKind::Bang),
+            '=' if self.peek_n(1) == '>' => {
+                self.check_double('>', TokenKind::Arrow, TokenKind::Equal)
+            }
+            '=' => self.check_double('=', TokenKind::EqualEqual, TokenKind::Equal),
+            '>' => self.check_double('=', TokenKind::GreaterEqual, TokenKind::Greater),
+            '<' => self.check_double('=', TokenKind::LessEqual, TokenKind::Less),
+            '&' => self.check_double('&', TokenKind::AmpersandAmpersand, TokenKind::Ampersand),
+            '|' => self.check_double('|', TokenKind::BarBar, TokenKind::Bar),
+            '/' => self.eat_comment()?,
+            w if w.is_whitespace() => self.eat_whitespace(),
+            q @ ('"' | '\'') => self.eat_string(q)?,
+            '`' => self.eat_template(true)?,
+            d if is_digit(d) => self.eat_number(d)?,
             a if is_identifier_start(a) => self.eat_identifier()?,
             e => {
-                return Err(self.parse_error(&format!(
-                    "Error parsing unknown character {}",
-                    String::from_utf8(vec![e]).unwrap()
-                )))
+                return Err(self.parse_error(
+                    ErrorCode::UnknownCharacter,
+                    &format!("Error parsing unknown character {}", e),
+                ))
             }
         };
 
         Ok(self.token(token_kind, self.swap.to_owned()))
     }
 
+    /// Lexes the rest of the source, reporting every error instead of
+    /// stopping at the first one.
     pub fn tokenize(&mut self) -> Result<Vec<Token>, LexerError> {
         let mut tokens: Vec<Token> = vec![];
         let mut errors: LexerError = LexerError::new();
-        let mut has_err = false;
 
-        while !self.at_eof() {
+        for result in self {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(lex_err) => errors.add(lex_err),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(tokens)
+    }
+}
+
+/// Yields each token or error in turn, ending with a single `Eof` token.
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token, ParseTokenError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
             self.start = self.current;
-            self.col = self.start as u32 + 1;
+            self.start_line = self.line;
+            self.col = self.column;
             self.swap = None;
 
+            if self.at_eof() {
+                self.finished = true;
+                return Some(Ok(self.token(TokenKind::Eof, None)));
+            }
+
             match self.match_token() {
-                Ok(tok) => tokens.push(tok),
-                Err(lex_err) => {
-                    has_err = true;
-                    errors.add(lex_err);
-                }
-            };
+                Ok(token) if !self.trivia && token.kind.is_trivia() => continue,
+                result => return Some(result),
+            }
         }
+        None
+    }
+}
 
-        tokens.push(self.token(TokenKind::Eof, None));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ToDiagnostics;
+
+    #[test]
+    fn tracks_lines_columns_and_spans() {
+        let source = "let a = 1;\n\n  // note\n  \"two\nlines\" b";
+        let tokens: Vec<(TokenKind, u32, u32, usize, usize)> =
+            Lexer::with_file(source.as_bytes(), FileId(3))
+                .tokenize()
+                .unwrap()
+                .into_iter()
+                .filter(|token| token.kind != TokenKind::Whitespace)
+                .map(|token| {
+                    assert_eq!(token.span.file_id, FileId(3));
+                    (
+                        token.kind,
+                        token.line,
+                        token.col,
+                        token.span.start,
+                        token.span.end,
+                    )
+                })
+                .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Let, 1, 1, 0, 3),
+                (TokenKind::Identifier, 1, 5, 4, 5),
+                (TokenKind::Equal, 1, 7, 6, 7),
+                (TokenKind::Num, 1, 9, 8, 9),
+                (TokenKind::Semicolon, 1, 10, 9, 10),
+                (TokenKind::Comment, 3, 3, 14, 21),
+                (TokenKind::Str, 4, 3, 24, 35),
+                (TokenKind::Identifier, 5, 8, 36, 37),
+                (TokenKind::Eof, 5, 9, 37, 37),
+            ]
+        );
+    }
 
-        if has_err {
-            return Err(errors);
-        }
+    #[test]
+    fn decodes_unicode_and_counts_columns_in_characters() {
+        let source = "let größe = \"日本\"; // ✓\nπ_2 ü";
+        let tokens: Vec<(TokenKind, Option<String>, u32, u32)> = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.literal, token.line, token.col))
+            .collect();
+
+        let text = |s: &str| Some(s.to_owned());
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Let, text("let"), 1, 1),
+                (TokenKind::Identifier, text("größe"), 1, 5),
+                (TokenKind::Equal, None, 1, 11),
+                (TokenKind::Str, text("日本"), 1, 13),
+                (TokenKind::Semicolon, None, 1, 17),
+                (TokenKind::Comment, text(" ✓"), 1, 19),
+                (TokenKind::Identifier, text("π_2"), 2, 1),
+                (TokenKind::Identifier, text("ü"), 2, 5),
+                (TokenKind::Eof, None, 2, 6),
+            ]
+        );
+    }
 
-        Ok(tokens)
+    #[test]
+    fn reports_invalid_utf8_and_unknown_characters() {
+        let diagnostics = Lexer::new(b"a \xff b \xe2\x82 \xe2\x82\xac")
+            .tokenize()
+            .unwrap_err()
+            .to_diagnostics();
+        let errors: Vec<(ErrorCode, usize, usize, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic.primary().unwrap().span;
+                (
+                    diagnostic.code.unwrap(),
+                    span.start,
+                    span.end,
+                    diagnostic.message.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                (ErrorCode::InvalidUtf8, 2, 3, "Invalid UTF-8 byte 0xff"),
+                (ErrorCode::InvalidUtf8, 6, 7, "Invalid UTF-8 byte 0xe2"),
+                (ErrorCode::InvalidUtf8, 7, 8, "Invalid UTF-8 byte 0x82"),
+                (
+                    ErrorCode::UnknownCharacter,
+                    9,
+                    12,
+                    "Error parsing unknown character €"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_escapes_and_splits_templates() {
+        let source = r#"'it\'s' "\t\"\\\u{1F600}" "\u00e9\x41\b\f\v" `a${ {x}.y }b${`c`}\${d}`"#;
+        let tokens: Vec<(TokenKind, Option<String>)> = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.literal))
+            .collect();
+
+        let text = |s: &str| Some(s.to_owned());
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Str, text("it's")),
+                (TokenKind::Str, text("\t\"\\😀")),
+                (TokenKind::Str, text("éA\u{8}\u{c}\u{b}")),
+                (TokenKind::TemplateHead, text("a")),
+                (TokenKind::LeftBrace, None),
+                (TokenKind::Identifier, text("x")),
+                (TokenKind::RightBrace, None),
+                (TokenKind::Dot, None),
+                (TokenKind::Identifier, text("y")),
+                (TokenKind::TemplateMiddle, text("b")),
+                (TokenKind::Template, text("c")),
+                (TokenKind::TemplateTail, text("${d}")),
+                (TokenKind::Eof, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_escapes_and_unterminated_templates() {
+        let source = r#""\q" "\u{110000}" "\x4g" "\uD800" `\u{}${1}` `open"#;
+        let diagnostics = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap_err()
+            .to_diagnostics();
+        let errors: Vec<(ErrorCode, &str, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let span = diagnostic.primary().unwrap().span;
+                (
+                    diagnostic.code.unwrap(),
+                    &source[span.start..span.end],
+                    diagnostic.message.as_str(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    ErrorCode::InvalidEscape,
+                    "\\q",
+                    "Unknown escape sequence \\q"
+                ),
+                (
+                    ErrorCode::InvalidEscape,
+                    "\\u{110000}",
+                    "\\u{110000} is not a Unicode scalar value"
+                ),
+                (
+                    ErrorCode::InvalidEscape,
+                    "\\x4",
+                    "Invalid escape, expected 2 hex digits after \\x"
+                ),
+                (
+                    ErrorCode::InvalidEscape,
+                    "\\uD800",
+                    "\\uD800 is not a Unicode scalar value"
+                ),
+                (
+                    ErrorCode::InvalidEscape,
+                    "\\u{",
+                    "Invalid unicode escape, expected \\u{...} with 1 to 6 hex digits"
+                ),
+                (
+                    ErrorCode::UnterminatedString,
+                    "`open",
+                    "Unterminated template literal!"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_numbers_in_every_base() {
+        let source = "0xFF 0o17 0b1010_0101 1_000.25 6.02e23 1E-9 2.5e+3 007 1.x";
+        let numbers: Vec<String> = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::Num)
+            .map(|token| token.literal.unwrap())
+            .collect();
+
+        assert_eq!(
+            numbers,
+            vec!["255", "15", "165", "1000.25", "6.02e23", "1E-9", "2.5e+3", "007", "1"]
+        );
+    }
+
+    #[test]
+    fn reports_malformed_numbers() {
+        let source = "0x; 0b; 1_; 0b102; 0o8; 1e+; 1__0; 12ab; 0x_1; 1._5";
+        let diagnostics = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap_err()
+            .to_diagnostics();
+        let errors: Vec<(&str, &str)> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                assert_eq!(diagnostic.code, Some(ErrorCode::InvalidNumber));
+                let span = diagnostic.primary().unwrap().span;
+                (&source[span.start..span.end], diagnostic.message.as_str())
+            })
+            .collect();
+
+        let separator = "A digit separator must be between two digits";
+        assert_eq!(
+            errors,
+            vec![
+                ("0x", "Expected hexadecimal digits"),
+                ("0b", "Expected binary digits"),
+                ("1_", separator),
+                ("0b102", "Invalid digit 2 in binary literal"),
+                ("0o8", "Invalid digit 8 in octal literal"),
+                ("1e+", "Expected digits in exponent"),
+                ("1__0", separator),
+                ("12ab", "Unexpected a after number"),
+                ("0x_1", separator),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_block_and_doc_comments() {
+        let source = "/* a\n * b */ 1 /**/ /// doc\n//// plain\n// line\n4 / 2";
+        let tokens: Vec<(TokenKind, Option<String>)> = Lexer::new(source.as_bytes())
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter(|token| token.kind != TokenKind::Whitespace)
+            .map(|token| (token.kind, token.literal))
+            .collect();
+
+        let text = |s: &str| Some(s.to_owned());
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::BlockComment, text(" a\n * b ")),
+                (TokenKind::Num, text("1")),
+                (TokenKind::BlockComment, text("")),
+                (TokenKind::DocComment, text(" doc")),
+                (TokenKind::Comment, text("// plain")),
+                (TokenKind::Comment, text(" line")),
+                (TokenKind::Num, text("4")),
+                (TokenKind::Slash, None),
+                (TokenKind::Num, text("2")),
+                (TokenKind::Eof, None),
+            ]
+        );
+
+        let diagnostics = Lexer::new(b"1 /* open */ 2 /* never closed * /")
+            .tokenize()
+            .unwrap_err()
+            .to_diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, Some(ErrorCode::UnterminatedComment));
+        assert_eq!(diagnostics[0].primary().unwrap().span.start, 15);
+    }
+
+    #[test]
+    fn yields_tokens_lazily_with_or_without_trivia() {
+        let source = b"let a = 1; // one\n/// doc\nlet b = \xff;";
+        let kinds = |lexer: Lexer| -> Vec<TokenKind> {
+            lexer
+                .take(8)
+                .map(|result| result.map_or(TokenKind::Eof, |token| token.kind))
+                .collect()
+        };
+
+        assert_eq!(
+            kinds(Lexer::new(source)),
+            vec![
+                TokenKind::Let,
+                TokenKind::Whitespace,
+                TokenKind::Identifier,
+                TokenKind::Whitespace,
+                TokenKind::Equal,
+                TokenKind::Whitespace,
+                TokenKind::Num,
+                TokenKind::Semicolon,
+            ]
+        );
+        assert_eq!(
+            kinds(Lexer::new(source).with_trivia(false)),
+            vec![
+                TokenKind::Let,
+                TokenKind::Identifier,
+                TokenKind::Equal,
+                TokenKind::Num,
+                TokenKind::Semicolon,
+                TokenKind::DocComment,
+                TokenKind::Let,
+                TokenKind::Identifier,
+            ]
+        );
+
+        let mut lexer = Lexer::new(b"\xff 1").with_trivia(false);
+        assert!(lexer.next().unwrap().is_err());
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Num);
+        assert_eq!(lexer.next().unwrap().unwrap().kind, TokenKind::Eof);
+        assert!(lexer.next().is_none());
     }
 }
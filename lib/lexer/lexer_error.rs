@@ -1,3 +1,5 @@
+use crate::diagnostics::{Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::source::Span;
 use std::{
     error::Error,
     fmt::{self, Display, Formatter},
@@ -5,14 +7,22 @@ use std::{
 
 #[derive(Debug)]
 pub struct ParseTokenError {
+    pub code: ErrorCode,
     pub line: u32,
     pub col: u32,
+    pub span: Span,
     pub text: String,
 }
 
 impl ParseTokenError {
-    pub fn new(line: u32, col: u32, text: String) -> Self {
-        ParseTokenError { line, col, text }
+    pub fn new(code: ErrorCode, line: u32, col: u32, span: Span, text: String) -> Self {
+        ParseTokenError {
+            code,
+            line,
+            col,
+            span,
+            text,
+        }
     }
 }
 
@@ -28,7 +38,15 @@ impl Display for ParseTokenError {
 
 impl Error for ParseTokenError {}
 
-#[derive(Debug)]
+impl ToDiagnostics for ParseTokenError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        vec![Diagnostic::error(&self.text)
+            .with_code(self.code)
+            .with_label(Label::primary(self.span, ""))]
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct LexerError {
     errors: Vec<ParseTokenError>,
 }
@@ -41,6 +59,14 @@ impl LexerError {
     pub fn add(&mut self, err: ParseTokenError) {
         self.errors.push(err);
     }
+
+    pub fn errors(&self) -> &[ParseTokenError] {
+        &self.errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
 }
 
 impl Display for LexerError {
@@ -54,3 +80,12 @@ impl Display for LexerError {
 }
 
 impl Error for LexerError {}
+
+impl ToDiagnostics for LexerError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .flat_map(|err| err.to_diagnostics())
+            .collect()
+    }
+}
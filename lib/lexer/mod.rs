@@ -2,6 +2,6 @@ mod lexer;
 mod lexer_error;
 mod token;
 
-pub use lexer::Lexer;
+pub use lexer::{Lexer, KEYWORDS};
 pub use lexer_error::{LexerError, ParseTokenError};
 pub use token::{Token, TokenKind};
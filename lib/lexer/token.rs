@@ -1,3 +1,4 @@
+use crate::source::Span;
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -6,9 +7,10 @@ pub struct Token {
     pub literal: Option<String>,
     pub line: u32,
     pub col: u32,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TokenKind {
     // Single character tokens.
     LeftParen,
@@ -18,6 +20,7 @@ pub enum TokenKind {
     LeftBracket,
     RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
     Plus,
@@ -30,6 +33,7 @@ pub enum TokenKind {
     BangEqual,
     Equal,
     EqualEqual,
+    Arrow,
     Greater,
     GreaterEqual,
     Less,
@@ -43,6 +47,12 @@ pub enum TokenKind {
     Identifier,
     Str,
     Num,
+    // A template without interpolations, and the parts of one with them:
+    // "`a${", "}b${" and "}c`".
+    Template,
+    TemplateHead,
+    TemplateMiddle,
+    TemplateTail,
 
     // Keywords
     Let,
@@ -55,12 +65,26 @@ pub enum TokenKind {
     From,
     Export,
     Print,
+    Match,
 
     Whitespace,
     Comment,
+    BlockComment,
+    DocComment,
     Eof,
 }
 
+impl TokenKind {
+    /// Whitespace and comments, which carry no meaning for the parser. Doc
+    /// comments are not trivia, since they document the next declaration.
+    pub fn is_trivia(self) -> bool {
+        matches!(
+            self,
+            TokenKind::Whitespace | TokenKind::Comment | TokenKind::BlockComment
+        )
+    }
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.kind {
@@ -71,6 +95,7 @@ impl fmt::Display for Token {
             TokenKind::LeftBracket => write!(f, "["),
             TokenKind::RightBracket => write!(f, "]"),
             TokenKind::Comma => write!(f, ","),
+            TokenKind::Colon => write!(f, ":"),
             TokenKind::Dot => write!(f, "."),
             TokenKind::Minus => write!(f, "-"),
             TokenKind::Plus => write!(f, "+"),
@@ -81,6 +106,7 @@ impl fmt::Display for Token {
             TokenKind::BangEqual => write!(f, "!="),
             TokenKind::Equal => write!(f, "="),
             TokenKind::EqualEqual => write!(f, "=="),
+            TokenKind::Arrow => write!(f, "=>"),
             TokenKind::Greater => write!(f, ">"),
             TokenKind::GreaterEqual => write!(f, ">="),
             TokenKind::Less => write!(f, "<"),
@@ -99,6 +125,16 @@ impl fmt::Display for Token {
                 "{}",
                 self.literal.to_owned().expect("String has no literal!")
             ),
+            TokenKind::Template => write!(f, "`{}`", self.literal.as_deref().unwrap_or_default()),
+            TokenKind::TemplateHead => {
+                write!(f, "`{}${{", self.literal.as_deref().unwrap_or_default())
+            }
+            TokenKind::TemplateMiddle => {
+                write!(f, "}}{}${{", self.literal.as_deref().unwrap_or_default())
+            }
+            TokenKind::TemplateTail => {
+                write!(f, "}}{}`", self.literal.as_deref().unwrap_or_default())
+            }
             TokenKind::Num => write!(
                 f,
                 "{}",
@@ -114,10 +150,17 @@ impl fmt::Display for Token {
             TokenKind::From => write!(f, "from"),
             TokenKind::Export => write!(f, "export"),
             TokenKind::Print => write!(f, "print"),
+            TokenKind::Match => write!(f, "match"),
             TokenKind::Whitespace => write!(f, " "),
             TokenKind::Comment => {
                 write!(f, "//{}", self.literal.to_owned().unwrap_or("".to_owned()))
             }
+            TokenKind::BlockComment => {
+                write!(f, "/*{}*/", self.literal.as_deref().unwrap_or_default())
+            }
+            TokenKind::DocComment => {
+                write!(f, "///{}", self.literal.as_deref().unwrap_or_default())
+            }
             TokenKind::Eof => write!(f, ""),
         }
     }
@@ -1,7 +1,21 @@
+#![allow(clippy::module_inception)]
+
+pub mod compiler;
+pub mod diagnostics;
+pub mod emit_js;
 mod error;
+pub mod formatter;
+pub mod highlight;
 pub mod interpreter;
 pub mod lexer;
-mod parser;
+pub mod loader;
+pub mod lsp;
+pub mod parser;
 pub mod repl;
+pub mod resolver;
+pub mod source;
+pub mod syntax;
+pub mod typechecker;
+pub mod vm;
 
 pub use error::BoxError;
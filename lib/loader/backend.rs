@@ -0,0 +1,92 @@
+use crate::emit_js::JsWriter;
+use crate::interpreter::{Interpreter, Value};
+use crate::parser::Program;
+use crate::vm::Vm;
+use crate::BoxError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+/// What a module is handed to once it has been checked.
+pub trait Runtime {
+    fn provide_module(&mut self, path: &str, values: HashMap<String, Value>);
+    /// Runs `program`, which was read from `path`.
+    fn run(&mut self, program: &Program, path: &Path) -> Result<Option<Value>, BoxError>;
+    fn exports(&self, program: &Program) -> HashMap<String, Value>;
+}
+
+impl Runtime for Interpreter {
+    fn provide_module(&mut self, path: &str, values: HashMap<String, Value>) {
+        Interpreter::provide_module(self, path, values)
+    }
+
+    fn run(&mut self, program: &Program, _: &Path) -> Result<Option<Value>, BoxError> {
+        Ok(Interpreter::run(self, program)?)
+    }
+
+    fn exports(&self, program: &Program) -> HashMap<String, Value> {
+        Interpreter::exports(self, program)
+    }
+}
+
+impl Runtime for Vm {
+    fn provide_module(&mut self, path: &str, values: HashMap<String, Value>) {
+        Vm::provide_module(self, path, values)
+    }
+
+    fn run(&mut self, program: &Program, _: &Path) -> Result<Option<Value>, BoxError> {
+        Vm::run(self, program)
+    }
+
+    fn exports(&self, program: &Program) -> HashMap<String, Value> {
+        Vm::exports(self, program)
+    }
+}
+
+// Compiling to JavaScript produces files rather than values, so imported
+// modules only contribute their types.
+impl Runtime for JsWriter {
+    fn provide_module(&mut self, _: &str, _: HashMap<String, Value>) {}
+
+    fn run(&mut self, program: &Program, path: &Path) -> Result<Option<Value>, BoxError> {
+        self.write(program, path)?;
+        Ok(None)
+    }
+
+    fn exports(&self, _: &Program) -> HashMap<String, Value> {
+        HashMap::new()
+    }
+}
+
+/// Selects between the tree-walking interpreter, the bytecode VM and
+/// compiling to JavaScript files.
+#[derive(Debug, PartialEq, Copy, Clone, Default)]
+pub enum Backend {
+    #[default]
+    Interpreter,
+    Vm,
+    Js,
+}
+
+impl Backend {
+    pub fn runtime(self) -> Box<dyn Runtime> {
+        match self {
+            Backend::Interpreter => Box::new(Interpreter::new()),
+            Backend::Vm => Box::new(Vm::new()),
+            Backend::Js => Box::new(JsWriter::new()),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "interpreter" => Ok(Backend::Interpreter),
+            "vm" => Ok(Backend::Vm),
+            "js" => Ok(Backend::Js),
+            _ => Err(format!("Unknown backend {}", name)),
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use super::{Backend, ModuleError, Runtime};
+use crate::interpreter::Value;
+use crate::lexer::Lexer;
+use crate::parser::{Parser, Program, Statement};
+use crate::source::{FileId, SourceMap};
+use crate::typechecker::{ModuleInterface, TypeChecker};
+use crate::BoxError;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// The extension tried when an import path names no existing file.
+pub const EXTENSION: &str = "ls";
+
+/// The name source text that was not read from a file is reported under.
+pub const INPUT: &str = "<input>";
+
+struct Module {
+    program: Rc<Program>,
+    interface: ModuleInterface,
+    // The exported values, once the module has run.
+    values: Option<HashMap<String, Value>>,
+}
+
+/// Loads modules from files, checking every module a program imports before
+/// running any of them, and running each one once no matter how many
+/// modules import it.
+#[derive(Default)]
+pub struct ModuleLoader {
+    backend: Backend,
+    loaded: HashMap<PathBuf, Module>,
+    loading: Vec<PathBuf>,
+    sources: SourceMap,
+    // Whether modules are only checked, never run.
+    check_only: bool,
+}
+
+impl ModuleLoader {
+    pub fn new() -> Self {
+        ModuleLoader::default()
+    }
+
+    /// Creates a loader that runs every module on `backend`.
+    pub fn with_backend(backend: Backend) -> Self {
+        ModuleLoader {
+            backend,
+            ..ModuleLoader::default()
+        }
+    }
+
+    /// Creates a loader that type checks modules without running them, for
+    /// tools that must not have a program's side effects.
+    pub fn checking() -> Self {
+        ModuleLoader {
+            check_only: true,
+            ..ModuleLoader::default()
+        }
+    }
+
+    /// Every file read so far, for turning spans into positions.
+    pub fn source_map(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// Registers source that was not read from a file, so the spans of the
+    /// files loaded later do not clash with its own.
+    pub fn add_source(&mut self, name: &str, source: &str) -> FileId {
+        self.sources.add(name, source)
+    }
+
+    /// Runs the file at `path` as the entry module, returning the value of
+    /// its last statement.
+    pub fn run_file(&mut self, path: &Path) -> Result<Option<Value>, ModuleError> {
+        let path = canonical(path)?;
+        self.load(&path)?;
+        if self.check_only {
+            return Ok(None);
+        }
+        self.run(&path)
+    }
+
+    /// Checks every module imported by `program`, resolving paths against
+    /// `dir`, and hands their types to the checker.
+    pub fn check_imports(
+        &mut self,
+        program: &Program,
+        dir: &Path,
+        checker: &mut TypeChecker,
+    ) -> Result<(), ModuleError> {
+        for (import, path) in imports(program, dir)? {
+            let module = self.load(&path)?;
+            checker.provide_module(&import, module.interface.to_owned());
+        }
+        Ok(())
+    }
+
+    /// Runs every module imported by `program`, which `check_imports` has
+    /// checked, and hands their exports to the runtime.
+    pub fn run_imports(
+        &mut self,
+        program: &Program,
+        dir: &Path,
+        runtime: &mut dyn Runtime,
+    ) -> Result<(), ModuleError> {
+        for (import, path) in imports(program, dir)? {
+            self.run(&path)?;
+            if let Some(values) = &self.loaded[&path].values {
+                runtime.provide_module(&import, values.to_owned());
+            }
+        }
+        Ok(())
+    }
+
+    // Reads and checks the module at `path` and every module it imports.
+    fn load(&mut self, path: &Path) -> Result<&Module, ModuleError> {
+        if let Some(start) = self.loading.iter().position(|loading| loading == path) {
+            let mut chain = self.loading[start..].to_vec();
+            chain.push(path.to_owned());
+            return Err(ModuleError::Cycle { chain });
+        }
+
+        if !self.loaded.contains_key(path) {
+            self.loading.push(path.to_owned());
+            let result = self.check(path);
+            self.loading.pop();
+
+            self.loaded.insert(path.to_owned(), result?);
+        }
+
+        Ok(&self.loaded[path])
+    }
+
+    // Each module is checked with its own environment.
+    fn check(&mut self, path: &Path) -> Result<Module, ModuleError> {
+        let source = fs::read_to_string(path).map_err(|source| ModuleError::Io {
+            path: path.to_owned(),
+            source,
+        })?;
+        let failed = |source: BoxError| ModuleError::Failed {
+            path: path.to_owned(),
+            source,
+        };
+
+        let file_id = self.sources.add(&path.to_string_lossy(), &source);
+        let program = Parser::from_lexer(Lexer::with_file(source.as_bytes(), file_id))
+            .parse_program()
+            .map_err(|err| failed(err.into()))?;
+
+        let mut checker = TypeChecker::new();
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        self.check_imports(&program, dir, &mut checker)?;
+        checker
+            .check_program(&program)
+            .map_err(|err| failed(err.into()))?;
+
+        Ok(Module {
+            interface: checker.exports(&program),
+            program: Rc::new(program),
+            values: None,
+        })
+    }
+
+    // Runs the checked module at `path` after the modules it imports, unless
+    // it has already run, returning the value of its last statement.
+    fn run(&mut self, path: &Path) -> Result<Option<Value>, ModuleError> {
+        let module = &self.loaded[path];
+        if module.values.is_some() {
+            return Ok(None);
+        }
+        let program = Rc::clone(&module.program);
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut runtime = self.backend.runtime();
+        self.run_imports(&program, dir, runtime.as_mut())?;
+        let value = runtime
+            .run(&program, path)
+            .map_err(|source| ModuleError::Failed {
+                path: path.to_owned(),
+                source,
+            })?;
+
+        if let Some(module) = self.loaded.get_mut(path) {
+            module.values = Some(runtime.exports(&program));
+        }
+        Ok(value)
+    }
+}
+
+// The path of every import in `program`, as written and as resolved
+// against `dir`.
+fn imports(program: &Program, dir: &Path) -> Result<Vec<(String, PathBuf)>, ModuleError> {
+    let mut imports = vec![];
+    for statement in &program.statements {
+        if let Statement::Import { path: token, .. } = statement {
+            let import = token.to_string();
+            let path = resolve(dir, &import).ok_or_else(|| ModuleError::Unresolved {
+                path: dir.join(&import),
+                token: token.to_owned(),
+            })?;
+            imports.push((import, path));
+        }
+    }
+    Ok(imports)
+}
+
+fn canonical(path: &Path) -> Result<PathBuf, ModuleError> {
+    path.canonicalize().map_err(|source| ModuleError::Io {
+        path: path.to_owned(),
+        source,
+    })
+}
+
+// Finds the file an import refers to, trying the default extension when
+// the path itself does not exist.
+fn resolve(dir: &Path, import: &str) -> Option<PathBuf> {
+    let path = dir.join(import);
+    if path.is_file() {
+        return path.canonicalize().ok();
+    }
+
+    let mut with_extension = OsString::from(path);
+    with_extension.push(".");
+    with_extension.push(EXTENSION);
+    let path = PathBuf::from(with_extension);
+    if path.is_file() {
+        return path.canonicalize().ok();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{ErrorCode, ToDiagnostics};
+    use std::env;
+
+    fn write_modules(name: &str, modules: &[(&str, &str)]) -> PathBuf {
+        let dir = env::temp_dir().join(format!("lightscript-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        for (file, source) in modules {
+            fs::write(dir.join(file), source).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn runs_imported_modules_once_with_their_exports() {
+        let dir = write_modules(
+            "imports",
+            &[
+                (
+                    "lib/option.ls",
+                    "export type Option<a> = Some(a) | None; let hidden = 1; export let one = Some(hidden);",
+                ),
+                (
+                    "lib/two.ls",
+                    "import { Option, one } from \"./option\"; export let two = match one { Some(n) => Some(n + 1), None => None };",
+                ),
+                (
+                    "main.ls",
+                    "import { one } from \"./lib/option.ls\"; import { two } from \"./lib/two\"; (one, two)",
+                ),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        let value = loader.run_file(&dir.join("main.ls")).unwrap();
+        assert_eq!(value.unwrap().to_string(), "(Some(1), Some(2))");
+        assert_eq!(loader.loaded.len(), 3);
+        assert!(loader.source_map().file(FileId(2)).is_some());
+        assert!(loader.source_map().file(FileId(3)).is_none());
+    }
+
+    #[test]
+    fn hides_unexported_bindings_and_detects_cycles() {
+        let dir = write_modules(
+            "errors",
+            &[
+                ("lib/a.ls", "import { b } from \"./b\"; export let a = 1;"),
+                ("lib/b.ls", "import { a } from \"./a\"; export let b = 2;"),
+                ("private.ls", "let secret = 1;"),
+                ("main.ls", "import { secret } from \"./private\";"),
+            ],
+        );
+
+        let err = ModuleLoader::new()
+            .run_file(&dir.join("main.ls"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("does not export secret"), "{}", err);
+
+        match ModuleLoader::new().run_file(&dir.join("lib/a.ls")) {
+            Err(ModuleError::Cycle { chain }) => assert_eq!(chain.len(), 3),
+            other => panic!("expected an import cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn checks_every_module_before_running_any() {
+        let dir = write_modules(
+            "link",
+            &[
+                (
+                    "lib/c.ls",
+                    "print \"loading c\"; let hidden = 1; export let shown = 2;",
+                ),
+                ("lib/d.ls", "export let d = 4;"),
+                (
+                    "main.ls",
+                    "import { d } from \"./lib/d\"; import { hidden } from \"./lib/c\";",
+                ),
+            ],
+        );
+
+        let mut loader = ModuleLoader::new();
+        let err = loader.run_file(&dir.join("main.ls")).unwrap_err();
+        assert!(
+            err.to_string().contains("does not export hidden"),
+            "{}",
+            err
+        );
+        assert_eq!(loader.loaded.len(), 2);
+        assert!(loader.loaded.values().all(|module| module.values.is_none()));
+    }
+
+    #[test]
+    fn imports_and_exports_only_at_the_top_level() {
+        let dir = write_modules(
+            "nested",
+            &[
+                ("d.ls", "export let shown = 1;"),
+                (
+                    "import.ls",
+                    "if true { import { shown } from \"./d\"; print shown; }",
+                ),
+                ("export.ls", "if true { export let z = 1; }"),
+            ],
+        );
+
+        for file in ["import.ls", "export.ls"] {
+            let err = ModuleLoader::new().run_file(&dir.join(file)).unwrap_err();
+            let codes: Vec<Option<ErrorCode>> = err
+                .to_diagnostics()
+                .iter()
+                .map(|diagnostic| diagnostic.code)
+                .collect();
+            assert_eq!(codes, [Some(ErrorCode::NotTopLevel)], "{}", err);
+        }
+    }
+}
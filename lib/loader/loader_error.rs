@@ -0,0 +1,73 @@
+use crate::diagnostics::{self, Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::lexer::Token;
+use crate::BoxError;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io,
+    path::PathBuf,
+};
+
+#[derive(Debug)]
+pub enum ModuleError {
+    Io { path: PathBuf, source: io::Error },
+    Unresolved { path: PathBuf, token: Token },
+    Cycle { chain: Vec<PathBuf> },
+    Failed { path: PathBuf, source: BoxError },
+}
+
+impl Display for ModuleError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ModuleError::Io { path, source } => {
+                write!(f, "Error reading module {0}: {1}", path.display(), source)
+            }
+            ModuleError::Unresolved { path, token } => write!(
+                f,
+                "Cannot find module \"{0}\" imported at line {1}, column {2} (looked for {3}).",
+                token,
+                token.line,
+                token.col,
+                path.display()
+            ),
+            ModuleError::Cycle { chain } => {
+                let chain: Vec<String> = chain
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect();
+                write!(f, "Import cycle detected: {}", chain.join(" -> "))
+            }
+            ModuleError::Failed { path, source } => {
+                write!(f, "Error in module {0}:\n{1}", path.display(), source)
+            }
+        }
+    }
+}
+
+impl Error for ModuleError {}
+
+impl ToDiagnostics for ModuleError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            ModuleError::Io { path, source } => vec![Diagnostic::error(format!(
+                "Error reading module {0}: {1}",
+                path.display(),
+                source
+            ))
+            .with_code(ErrorCode::UnreadableModule)],
+            ModuleError::Unresolved { path, token } => {
+                vec![
+                    Diagnostic::error(format!("Cannot find module \"{}\"", token))
+                        .with_code(ErrorCode::ModuleNotFound)
+                        .with_label(Label::primary(token.span, "imported here"))
+                        .with_note(format!("looked for {}", path.display())),
+                ]
+            }
+            ModuleError::Cycle { .. } => {
+                vec![Diagnostic::error(self.to_string()).with_code(ErrorCode::ImportCycle)]
+            }
+            // The module's own diagnostics already point into its file.
+            ModuleError::Failed { source, .. } => diagnostics::diagnostics(source.as_ref()),
+        }
+    }
+}
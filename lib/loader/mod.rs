@@ -0,0 +1,7 @@
+mod backend;
+mod loader;
+mod loader_error;
+
+pub use backend::{Backend, Runtime};
+pub use loader::{ModuleLoader, EXTENSION, INPUT};
+pub use loader_error::ModuleError;
@@ -0,0 +1,131 @@
+use crate::diagnostics::{Diagnostic, Label, ToDiagnostics};
+use crate::lexer::Lexer;
+use crate::loader::ModuleLoader;
+use crate::parser::{Parser, Program, Statement};
+use crate::resolver::{self, Definition, Resolution};
+use crate::source::{FileId, Span};
+use crate::typechecker::{Type, TypeChecker};
+use std::path::Path;
+
+/// What is known about one version of a document: its problems, the types
+/// of its expressions and what its names refer to.
+pub struct Analysis {
+    file_id: FileId,
+    diagnostics: Vec<Diagnostic>,
+    parsed: bool,
+    types: Vec<(Span, Type)>,
+    resolution: Resolution,
+}
+
+impl Analysis {
+    /// Checks `source`, resolving its imports next to `path` if it has one.
+    pub fn new(source: &str, name: &str, path: Option<&Path>) -> Self {
+        let mut loader = ModuleLoader::checking();
+        let file_id = loader.add_source(name, source);
+        let mut analysis = Analysis {
+            file_id,
+            diagnostics: vec![],
+            parsed: false,
+            types: vec![],
+            resolution: Resolution::default(),
+        };
+
+        let program = match Parser::from_lexer(Lexer::with_file(source.as_bytes(), file_id))
+            .parse_program()
+        {
+            Ok(program) => program,
+            Err(err) => {
+                analysis.diagnostics = err.to_diagnostics();
+                return analysis;
+            }
+        };
+        analysis.parsed = true;
+
+        let mut checker = TypeChecker::recording();
+        let imported = match path.and_then(Path::parent) {
+            Some(dir) => loader.check_imports(&program, dir, &mut checker),
+            None => Ok(()),
+        };
+        if let Err(err) = &imported {
+            // Problems inside imported files are shown on the imports.
+            let at = first_import(&program);
+            for mut diagnostic in err.to_diagnostics() {
+                let foreign = diagnostic
+                    .labels
+                    .iter()
+                    .find(|label| label.span.file_id != file_id)
+                    .map(|label| label.span.file_id);
+                if let Some(name) = foreign.and_then(|id| loader.source_map().file(id)) {
+                    diagnostic.message = format!("{} (in {})", diagnostic.message, name.name);
+                }
+                if foreign.is_some() || diagnostic.labels.is_empty() {
+                    diagnostic.labels = at
+                        .map(|span| Label::primary(span, ""))
+                        .into_iter()
+                        .collect();
+                }
+                analysis.diagnostics.push(diagnostic);
+            }
+        }
+        // Like the command line, stop at failed imports rather than report
+        // what follows from them, but still infer types for hovers.
+        let checked = checker.check_program(&program);
+        if let (Ok(()), Err(err)) = (imported, checked) {
+            analysis.diagnostics.extend(err.to_diagnostics());
+        }
+
+        analysis.types = checker.recorded_types();
+        analysis.resolution = resolver::resolve(&program);
+        analysis
+    }
+
+    pub fn file_id(&self) -> FileId {
+        self.file_id
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Whether the document parsed, so that its types and names are known.
+    pub fn parsed(&self) -> bool {
+        self.parsed
+    }
+
+    pub fn resolution(&self) -> &Resolution {
+        &self.resolution
+    }
+
+    /// The innermost expression or binding at `offset` with its type.
+    pub fn type_at(&self, offset: usize) -> Option<(Span, &Type)> {
+        self.types
+            .iter()
+            .filter(|(span, _)| span.start <= offset && offset <= span.end)
+            .min_by_key(|(span, _)| span.len())
+            .map(|(span, ty)| (*span, ty))
+    }
+
+    /// The definition of the name at `offset`, if it is defined here.
+    pub fn definition_at(&self, offset: usize) -> Option<&Definition> {
+        let index = self.resolution.definition_at(offset)?;
+        self.resolution.definitions().get(index)
+    }
+
+    /// The type inferred for a definition, which only values have.
+    pub fn type_of(&self, definition: &Definition) -> Option<&Type> {
+        self.types
+            .iter()
+            .find(|(span, _)| *span == definition.span)
+            .map(|(_, ty)| ty)
+    }
+}
+
+fn first_import(program: &Program) -> Option<Span> {
+    program
+        .statements
+        .iter()
+        .find_map(|statement| match statement {
+            Statement::Import { path, .. } => Some(path.span),
+            _ => None,
+        })
+}
@@ -0,0 +1,73 @@
+use lsp_types::{Position, Range};
+
+/// Converts between byte offsets and the positions editors use, whose
+/// columns count UTF-16 code units.
+pub struct LineIndex {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex {
+            text: text.to_owned(),
+            line_starts,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset) - 1;
+        let start = self.line_starts[line];
+        let column = self.text[start..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum::<usize>();
+        Position::new(line as u32, column as u32)
+    }
+
+    pub fn range(&self, start: usize, end: usize) -> Range {
+        Range::new(self.position(start), self.position(end))
+    }
+
+    /// The byte offset of a position, clamped to the end of its line.
+    pub fn offset(&self, position: Position) -> usize {
+        let Some(&start) = self.line_starts.get(position.line as usize) else {
+            return self.text.len();
+        };
+        let line = self.text[start..].split('\n').next().unwrap_or_default();
+        let mut column = 0;
+        for (index, c) in line.char_indices() {
+            if column >= position.character as usize {
+                return start + index;
+            }
+            column += c.len_utf16();
+        }
+        start + line.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_columns_in_utf16_code_units() {
+        let index = LineIndex::new("let a = 1;\nlet 𝔁 = \"é\";\n");
+        let x = index.text().find('𝔁').unwrap();
+        let e = index.text().find('é').unwrap();
+
+        assert_eq!(index.position(x), Position::new(1, 4));
+        assert_eq!(index.position(e), Position::new(1, 10));
+        assert_eq!(index.offset(Position::new(1, 10)), e);
+        assert_eq!(index.offset(Position::new(0, 99)), 10);
+        assert_eq!(index.offset(Position::new(9, 0)), index.text().len());
+    }
+}
@@ -0,0 +1,7 @@
+mod analysis;
+mod line_index;
+mod server;
+
+pub use analysis::Analysis;
+pub use line_index::LineIndex;
+pub use server::{capabilities, run};
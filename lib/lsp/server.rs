@@ -0,0 +1,384 @@
+use super::analysis::Analysis;
+use super::line_index::LineIndex;
+use crate::diagnostics::{self, LabelStyle, Severity};
+use crate::highlight::{self, HighlightKind};
+use crate::lexer::KEYWORDS;
+use crate::resolver::DefinitionKind;
+use crate::BoxError;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationKind, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, References, Request as RequestKind,
+    SemanticTokensFullRequest,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability, Location,
+    MarkupContent, MarkupKind, NumberOrString, OneOf, Position, PublishDiagnosticsParams,
+    ReferenceParams, SemanticToken, SemanticTokenType, SemanticTokens, SemanticTokensFullOptions,
+    SemanticTokensLegend, SemanticTokensOptions, SemanticTokensParams, SemanticTokensResult,
+    SemanticTokensServerCapabilities, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+
+/// The capabilities announced to clients when they connect.
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        references_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions::default()),
+        semantic_tokens_provider: Some(SemanticTokensServerCapabilities::SemanticTokensOptions(
+            SemanticTokensOptions {
+                legend: SemanticTokensLegend {
+                    token_types: TOKEN_TYPES.to_vec(),
+                    token_modifiers: vec![],
+                },
+                full: Some(SemanticTokensFullOptions::Bool(true)),
+                ..SemanticTokensOptions::default()
+            },
+        )),
+        ..ServerCapabilities::default()
+    }
+}
+
+// The semantic token types announced to clients, in the order of the kinds
+// of highlight they stand for.
+const TOKEN_TYPES: [SemanticTokenType; 8] = [
+    SemanticTokenType::KEYWORD,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::STRING,
+    SemanticTokenType::NUMBER,
+    SemanticTokenType::COMMENT,
+    SemanticTokenType::OPERATOR,
+    SemanticTokenType::TYPE,
+    SemanticTokenType::ENUM_MEMBER,
+];
+
+fn token_type(kind: HighlightKind) -> u32 {
+    match kind {
+        HighlightKind::Keyword => 0,
+        HighlightKind::Identifier => 1,
+        HighlightKind::String => 2,
+        HighlightKind::Number => 3,
+        HighlightKind::Comment => 4,
+        HighlightKind::Operator => 5,
+        HighlightKind::TypeName => 6,
+        HighlightKind::Constructor => 7,
+    }
+}
+
+/// Serves a client over `connection` until it shuts the server down.
+pub fn run(connection: &Connection) -> Result<(), BoxError> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+
+    let mut server = Server::default();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                connection
+                    .sender
+                    .send(Message::Response(server.request(request)))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(published) = server.notification(notification) {
+                    connection.sender.send(Message::Notification(published))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+// The text of a document and the last analysis of it that parsed, with the
+// text it was made from. Positions in newer text that does not parse are
+// looked up in the analysed text.
+struct Document {
+    text: LineIndex,
+    index: LineIndex,
+    analysis: Analysis,
+}
+
+#[derive(Default)]
+struct Server {
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn request(&mut self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.handle::<HoverRequest>(request, Server::hover),
+            GotoDefinition::METHOD => self.handle::<GotoDefinition>(request, Server::definition),
+            References::METHOD => self.handle::<References>(request, Server::references),
+            Completion::METHOD => self.handle::<Completion>(request, Server::completion),
+            SemanticTokensFullRequest::METHOD => {
+                self.handle::<SemanticTokensFullRequest>(request, Server::semantic_tokens)
+            }
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", method),
+            ),
+        }
+    }
+
+    fn handle<R: RequestKind>(
+        &self,
+        request: Request,
+        handler: impl FnOnce(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(err) => {
+                Response::new_err(request.id, ErrorCode::InvalidParams as i32, err.to_string())
+            }
+        }
+    }
+
+    // Returns the diagnostics to publish for a document that changed.
+    fn notification(&mut self, notification: Notification) -> Option<Notification> {
+        let (uri, text) = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                (params.text_document.uri, Some(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                // Changes are always the whole text.
+                let change = params.content_changes.pop()?;
+                (params.text_document.uri, Some(change.text))
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params).ok()?;
+                self.documents.remove(&params.text_document.uri);
+                (params.text_document.uri, None)
+            }
+            _ => return None,
+        };
+
+        let diagnostics = match text {
+            Some(text) => self.update(&uri, &text),
+            None => vec![],
+        };
+        Some(Notification::new(
+            PublishDiagnostics::METHOD.to_owned(),
+            PublishDiagnosticsParams::new(uri, diagnostics, None),
+        ))
+    }
+
+    fn update(&mut self, uri: &Url, text: &str) -> Vec<Diagnostic> {
+        let path = uri.to_file_path().ok();
+        let analysis = Analysis::new(text, uri.as_str(), path.as_deref());
+        let index = LineIndex::new(text);
+        let diagnostics = analysis
+            .diagnostics()
+            .iter()
+            .map(|diagnostic| to_lsp(diagnostic, uri, &index))
+            .collect();
+        match self.documents.get_mut(uri) {
+            Some(document) if !analysis.parsed() => document.text = index,
+            _ => {
+                let document = Document {
+                    text: LineIndex::new(text),
+                    index,
+                    analysis,
+                };
+                self.documents.insert(uri.to_owned(), document);
+            }
+        }
+        diagnostics
+    }
+
+    // The document and the offset a request is about.
+    fn at(&self, position: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&position.text_document.uri)?;
+        let offset = document.index.offset(position.position);
+        Some((document, offset))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (document, offset) = self.at(&params.text_document_position_params)?;
+        let analysis = &document.analysis;
+        let (span, ty) = analysis.type_at(offset)?;
+
+        let definition = analysis.definition_at(offset);
+        let mut value = match definition {
+            Some(definition) => format!("```lightscript\n{}: {}\n```", definition.name, ty),
+            None => format!("```lightscript\n{}\n```", ty),
+        };
+        if let Some(doc) = definition.and_then(|definition| definition.doc.as_ref()) {
+            value.push_str("\n\n");
+            value.push_str(doc.trim());
+        }
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value,
+            }),
+            range: Some(document.index.range(span.start, span.end)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = &params.text_document_position_params;
+        let (document, offset) = self.at(position)?;
+        let definition = document.analysis.definition_at(offset)?;
+        let range = document
+            .index
+            .range(definition.span.start, definition.span.end);
+        Some(GotoDefinitionResponse::Scalar(Location::new(
+            position.text_document.uri.to_owned(),
+            range,
+        )))
+    }
+
+    fn references(&self, params: ReferenceParams) -> Option<Vec<Location>> {
+        let position = &params.text_document_position;
+        let (document, offset) = self.at(position)?;
+        let resolution = document.analysis.resolution();
+        let index = resolution.definition_at(offset)?;
+
+        let mut spans = vec![];
+        if params.context.include_declaration {
+            spans.push(resolution.definitions()[index].span);
+        }
+        spans.extend(resolution.references_to(index));
+        let uri = &position.text_document.uri;
+        Some(
+            spans
+                .iter()
+                .map(|span| {
+                    Location::new(uri.to_owned(), document.index.range(span.start, span.end))
+                })
+                .collect(),
+        )
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let mut keywords: Vec<&str> = KEYWORDS.keys().copied().collect();
+        keywords.sort_unstable();
+        let mut items: Vec<CompletionItem> = keywords
+            .into_iter()
+            .map(|keyword| CompletionItem {
+                label: keyword.to_owned(),
+                kind: Some(CompletionItemKind::KEYWORD),
+                ..CompletionItem::default()
+            })
+            .collect();
+
+        if let Some((document, offset)) = self.at(&params.text_document_position) {
+            let analysis = &document.analysis;
+            for definition in analysis.resolution().visible_at(offset) {
+                let kind = match definition.kind {
+                    DefinitionKind::Value | DefinitionKind::Parameter => {
+                        CompletionItemKind::VARIABLE
+                    }
+                    DefinitionKind::Type => CompletionItemKind::CLASS,
+                    DefinitionKind::TypeParameter => CompletionItemKind::TYPE_PARAMETER,
+                    DefinitionKind::Constructor => CompletionItemKind::ENUM_MEMBER,
+                };
+                items.push(CompletionItem {
+                    label: definition.name.to_owned(),
+                    kind: Some(kind),
+                    detail: analysis.type_of(definition).map(ToString::to_string),
+                    ..CompletionItem::default()
+                });
+            }
+        }
+        Some(CompletionResponse::Array(items))
+    }
+
+    fn semantic_tokens(&self, params: SemanticTokensParams) -> Option<SemanticTokensResult> {
+        let text = &self.documents.get(&params.text_document.uri)?.text;
+        let mut data = vec![];
+        let mut previous = Position::default();
+        for highlight in highlight::highlight(text.text()) {
+            // Clients may not handle tokens over several lines, so those
+            // are sent a line at a time.
+            let mut start = highlight.span.start;
+            for line in text.text()[start..highlight.span.end].split('\n') {
+                let position = text.position(start);
+                let length = line.trim_end_matches('\r').encode_utf16().count() as u32;
+                start += line.len() + 1;
+                if length == 0 {
+                    continue;
+                }
+                data.push(SemanticToken {
+                    delta_line: position.line - previous.line,
+                    delta_start: match position.line == previous.line {
+                        true => position.character - previous.character,
+                        false => position.character,
+                    },
+                    length,
+                    token_type: token_type(highlight.kind),
+                    token_modifiers_bitset: 0,
+                });
+                previous = position;
+            }
+        }
+        Some(SemanticTokensResult::Tokens(SemanticTokens {
+            result_id: None,
+            data,
+        }))
+    }
+}
+
+fn to_lsp(diagnostic: &diagnostics::Diagnostic, uri: &Url, index: &LineIndex) -> Diagnostic {
+    let primary = diagnostic
+        .labels
+        .iter()
+        .find(|label| label.style == LabelStyle::Primary)
+        .or_else(|| diagnostic.labels.first());
+    let range = primary.map_or_else(Default::default, |label| {
+        index.range(label.span.start, label.span.end)
+    });
+
+    let mut message = diagnostic.message.to_owned();
+    for note in &diagnostic.notes {
+        message.push_str(&format!("\nnote: {}", note));
+    }
+    for help in &diagnostic.help {
+        message.push_str(&format!("\nhelp: {}", help));
+    }
+    let related: Vec<DiagnosticRelatedInformation> = diagnostic
+        .labels
+        .iter()
+        .filter(|label| label.style == LabelStyle::Secondary)
+        .map(|label| DiagnosticRelatedInformation {
+            location: Location::new(
+                uri.to_owned(),
+                index.range(label.span.start, label.span.end),
+            ),
+            message: label.message.to_owned(),
+        })
+        .collect();
+
+    Diagnostic {
+        range,
+        severity: Some(match diagnostic.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        code: diagnostic
+            .code
+            .map(|code| NumberOrString::String(code.to_string())),
+        source: Some("lightscript".to_owned()),
+        message,
+        related_information: (!related.is_empty()).then_some(related),
+        ..Diagnostic::default()
+    }
+}
@@ -1,52 +1,185 @@
 use crate::lexer::Token;
+use crate::parser::MatchArm;
+use crate::source::Span;
 use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, PartialEq)]
 pub enum Expression {
     Unary {
         op: Token,
         right: Box<Expression>,
+        span: Span,
     },
     Binary {
         left: Box<Expression>,
         op: Token,
         right: Box<Expression>,
+        span: Span,
     },
     Grouping {
         expr: Box<Expression>,
+        span: Span,
     },
     Literal {
         lit: Token,
+        span: Span,
+    },
+    Variable {
+        name: Token,
+        span: Span,
+    },
+    Construct {
+        name: Token,
+        args: Vec<Expression>,
+        span: Span,
+    },
+    Record {
+        brace: Token,
+        fields: Vec<(Token, Expression)>,
+        span: Span,
+    },
+    Get {
+        object: Box<Expression>,
+        name: Token,
+        span: Span,
+    },
+    Tuple {
+        paren: Token,
+        elements: Vec<Expression>,
+        span: Span,
+    },
+    Match {
+        keyword: Token,
+        subject: Box<Expression>,
+        arms: Vec<MatchArm>,
+        span: Span,
+    },
+    Lambda {
+        arrow: Token,
+        params: Vec<Token>,
+        body: Rc<Expression>,
+        span: Span,
+    },
+    Call {
+        callee: Box<Expression>,
+        paren: Token,
+        args: Vec<Expression>,
+        span: Span,
     },
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Expression::Unary { op, right } => write!(f, "{0}{1}", op, right.to_string()),
-            Expression::Binary { left, op, right } => {
-                write!(f, "{0} {1} {2}", left.to_string(), op, right.to_string())
+            Expression::Unary { op, right, .. } => write!(f, "{0}{1}", op, right),
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                write!(f, "{0} {1} {2}", left, op, right)
+            }
+            Expression::Grouping { expr, .. } => write!(f, "({})", expr),
+            Expression::Literal { lit, .. } => write!(f, "{}", lit),
+            Expression::Variable { name, .. } => write!(f, "{}", name),
+            Expression::Construct { name, args, .. } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                    write!(f, "({})", args.join(", "))?;
+                }
+                Ok(())
+            }
+            Expression::Record { fields, .. } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{0}: {1}", name, value))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Expression::Get { object, name, .. } => write!(f, "{0}.{1}", object, name),
+            Expression::Tuple { elements, .. } => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                write!(f, "({})", elements.join(", "))
+            }
+            Expression::Match { subject, arms, .. } => {
+                let arms: Vec<String> = arms.iter().map(|arm| arm.to_string()).collect();
+                write!(f, "match {0} {{ {1} }}", subject, arms.join(", "))
+            }
+            Expression::Lambda { params, body, .. } => {
+                let params: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+                write!(f, "({0}) => {1}", params.join(", "), body)
+            }
+            Expression::Call { callee, args, .. } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+                write!(f, "{0}({1})", callee, args.join(", "))
             }
-            Expression::Grouping { expr } => write!(f, "({})", expr.to_string()),
-            Expression::Literal { lit } => write!(f, "{}", lit),
         }
     }
 }
 
 impl Expression {
     pub fn new_unary(op: Token, right: Box<Expression>) -> Expression {
-        Expression::Unary { op, right }
+        let span = op.span.to(right.span());
+        Expression::Unary { op, right, span }
     }
     pub fn new_binary(left: Box<Expression>, op: Token, right: Box<Expression>) -> Expression {
-        Expression::Binary { left, op, right }
+        let span = left.span().to(right.span());
+        Expression::Binary {
+            left,
+            op,
+            right,
+            span,
+        }
     }
 
-    pub fn new_grouping(expr: Box<Expression>) -> Expression {
-        Expression::Grouping { expr }
+    pub fn new_grouping(expr: Box<Expression>, span: Span) -> Expression {
+        Expression::Grouping { expr, span }
     }
 
     pub fn new_literal(lit: Token) -> Expression {
-        Expression::Literal { lit }
+        let span = lit.span;
+        Expression::Literal { lit, span }
+    }
+
+    pub fn new_variable(name: Token) -> Expression {
+        let span = name.span;
+        Expression::Variable { name, span }
+    }
+
+    /// The source the whole expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Expression::Unary { span, .. }
+            | Expression::Binary { span, .. }
+            | Expression::Grouping { span, .. }
+            | Expression::Literal { span, .. }
+            | Expression::Variable { span, .. }
+            | Expression::Construct { span, .. }
+            | Expression::Record { span, .. }
+            | Expression::Get { span, .. }
+            | Expression::Tuple { span, .. }
+            | Expression::Match { span, .. }
+            | Expression::Lambda { span, .. }
+            | Expression::Call { span, .. } => *span,
+        }
+    }
+
+    /// A representative token to point at when reporting on the whole expression.
+    pub fn token(&self) -> &Token {
+        match self {
+            Expression::Unary { op, .. } => op,
+            Expression::Binary { op, .. } => op,
+            Expression::Grouping { expr, .. } => expr.token(),
+            Expression::Literal { lit, .. } => lit,
+            Expression::Variable { name, .. } => name,
+            Expression::Construct { name, .. } => name,
+            Expression::Record { brace, .. } => brace,
+            Expression::Get { name, .. } => name,
+            Expression::Tuple { paren, .. } => paren,
+            Expression::Match { keyword, .. } => keyword,
+            Expression::Lambda { arrow, .. } => arrow,
+            Expression::Call { paren, .. } => paren,
+        }
     }
 }
 
@@ -54,26 +187,28 @@ impl Expression {
 mod tests {
     use super::*;
     use crate::lexer::TokenKind;
+    use crate::source::FileId;
 
     #[test]
     fn expression_prints() {
-        let expr = Expression::Unary {
-            op: Token {
+        let expr = Expression::new_unary(
+            Token {
                 kind: TokenKind::Minus,
                 col: 1,
                 line: 1,
                 literal: Some("-".to_string()),
+                span: Span::new(FileId(0), 0, 1),
             },
-            right: Box::new(Expression::Literal {
-                lit: Token {
-                    kind: TokenKind::Num,
-                    col: 2,
-                    line: 1,
-                    literal: Some("1".to_string()),
-                },
-            }),
-        };
-        println!("{}", expr.to_string());
+            Box::new(Expression::new_literal(Token {
+                kind: TokenKind::Num,
+                col: 2,
+                line: 1,
+                literal: Some("1".to_string()),
+                span: Span::new(FileId(0), 1, 2),
+            })),
+        );
+        println!("{}", expr);
         assert_eq!(expr.to_string(), "-1".to_string());
+        assert_eq!(expr.span(), Span::new(FileId(0), 0, 2));
     }
 }
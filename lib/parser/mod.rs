@@ -1,4 +1,13 @@
 mod expression;
-pub mod parser;
+mod parser;
+mod parser_error;
+mod pattern;
+mod statement;
+mod type_expression;
 
 pub use expression::Expression;
+pub use parser::Parser;
+pub use parser_error::{ParseError, ParserError};
+pub use pattern::{MatchArm, Pattern};
+pub use statement::{Program, Statement};
+pub use type_expression::{Field, TypeDefinition, TypeExpression, Variant};
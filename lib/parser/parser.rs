@@ -1,32 +1,647 @@
-use crate::lexer::{Token, TokenKind};
-use crate::parser::Expression;
+use crate::diagnostics::ErrorCode;
+use crate::lexer::{Lexer, ParseTokenError, Token, TokenKind};
+use crate::parser::{
+    Expression, Field, MatchArm, ParseError, ParserError, Pattern, Program, Statement,
+    TypeDefinition, TypeExpression, Variant,
+};
+use crate::source::Span;
+use crate::syntax::{Checkpoint, GreenNode, SyntaxKind, TreeSink};
+use std::collections::HashMap;
+use std::rc::Rc;
 
-pub struct Parser {
+type Tokens<'a> = Box<dyn Iterator<Item = Result<Token, ParseTokenError>> + 'a>;
+
+pub struct Parser<'a> {
+    source: Tokens<'a>,
+    // The tokens pulled from the source and not yet forgotten, the first of
+    // which is at index `base`.
     tokens: Vec<Token>,
+    base: usize,
+    // Doc comments by the index of the token they precede, and the one being
+    // read before that token is pulled.
+    docs: HashMap<usize, String>,
+    doc: Option<String>,
     current: i64,
+    depth: u32,
+    errors: ParserError,
+    // The concrete syntax tree built alongside the AST, if any, and the
+    // trivia not yet added to it by the index of the token it precedes.
+    tree: Option<TreeSink<'a>>,
+    trivia: HashMap<usize, Vec<Token>>,
+    leading: Vec<Token>,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Parser::from_tokens(Box::new(tokens.into_iter().map(Ok)), None)
     }
 
-    fn expression(&mut self) -> Box<Expression> {
-        self.equality_or_higher()
+    /// A parser that pulls tokens from `lexer` only as it needs them. Errors
+    /// the lexer finds are reported instead of any found by the parser.
+    pub fn from_lexer(lexer: Lexer<'a>) -> Self {
+        Parser::from_tokens(Box::new(lexer.with_trivia(false)), None)
+    }
+
+    /// A parser that also builds the lossless syntax tree of `source`, which
+    /// `lexer` reads, for `into_tree` to return.
+    pub(crate) fn lossless(lexer: Lexer<'a>, source: &'a str) -> Self {
+        Parser::from_tokens(
+            Box::new(lexer.with_trivia(true)),
+            Some(TreeSink::new(source)),
+        )
+    }
+
+    fn from_tokens(source: Tokens<'a>, tree: Option<TreeSink<'a>>) -> Self {
+        let mut parser = Self {
+            source,
+            tokens: vec![],
+            base: 0,
+            docs: HashMap::new(),
+            doc: None,
+            current: 0,
+            depth: 0,
+            errors: ParserError::new(),
+            tree,
+            trivia: HashMap::new(),
+            leading: vec![],
+        };
+        parser.pull(0);
+        parser
+    }
+
+    /// The syntax tree of a `lossless` parser, once the program is parsed.
+    pub(crate) fn into_tree(mut self) -> Option<GreenNode> {
+        self.flush_trivia();
+        let mut tree = self.tree?;
+        for token in &self.leading {
+            tree.token(token);
+        }
+        Some(tree.finish())
+    }
+
+    // Pulls tokens from the source until the one at `index` is buffered or
+    // the source runs out.
+    fn pull(&mut self, index: usize) {
+        while self.base + self.tokens.len() <= index {
+            let token = match self.source.next() {
+                Some(Ok(token)) => token,
+                Some(Err(err)) => {
+                    self.errors.add_lexer_error(err);
+                    continue;
+                }
+                None => return,
+            };
+            if self.tree.is_some()
+                && (token.kind.is_trivia() || token.kind == TokenKind::DocComment)
+            {
+                self.leading.push(token.to_owned());
+            }
+
+            match token.kind {
+                kind if kind.is_trivia() => {}
+                TokenKind::DocComment => {
+                    let literal = token.literal.unwrap_or_default();
+                    let line = literal.strip_prefix(' ').unwrap_or(&literal).trim_end();
+                    match self.doc.as_mut() {
+                        Some(doc) => {
+                            doc.push('\n');
+                            doc.push_str(line);
+                        }
+                        None => self.doc = Some(line.to_owned()),
+                    }
+                }
+                _ => {
+                    let index = self.base + self.tokens.len();
+                    if let Some(doc) = self.doc.take() {
+                        self.docs.insert(index, doc);
+                    }
+                    if !self.leading.is_empty() {
+                        self.trivia.insert(index, std::mem::take(&mut self.leading));
+                    }
+                    self.tokens.push(token);
+                }
+            }
+        }
+    }
+
+    // Drops the tokens before the previous one, which are never looked at
+    // again once a top level statement is parsed.
+    fn forget_parsed(&mut self) {
+        let keep = (self.current as usize).saturating_sub(1).max(self.base);
+        self.tokens.drain(..keep - self.base);
+        self.docs.retain(|index, _| *index >= keep);
+        self.trivia.retain(|index, _| *index >= keep);
+        self.base = keep;
+    }
+
+    /// Parses the whole token stream, reporting every error found instead of
+    /// stopping at the first one.
+    pub fn parse_program(&mut self) -> Result<Program, ParserError> {
+        let mut statements = vec![];
+
+        while !self.at_eof() {
+            if let Some(statement) = self.declaration() {
+                statements.push(statement);
+            }
+            self.forget_parsed();
+        }
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+
+        Ok(Program { statements })
+    }
+
+    fn declaration(&mut self) -> Option<Statement> {
+        let start = self.current;
+        let depth = self.tree.as_ref().map_or(0, TreeSink::open_nodes);
+        match self.declaration_or_error() {
+            Ok(statement) => Some(statement),
+            Err(err) => {
+                self.errors.add(err);
+                // What was parsed of the statement and the tokens skipped
+                // stay in its node.
+                self.finish_nodes(depth + 1);
+                self.synchronize(start);
+                self.finish_nodes(depth);
+                None
+            }
+        }
+    }
+
+    // Skips tokens until the start of the next statement, so that parsing can
+    // resume after an error. The tokens skipped make an error node.
+    fn synchronize(&mut self, start: i64) {
+        let mut skipped = false;
+        while !self.at_eof() {
+            if self.current > start && self.previous().kind == TokenKind::Semicolon {
+                break;
+            }
+
+            match self.peek().kind {
+                TokenKind::Let
+                | TokenKind::If
+                | TokenKind::Type
+                | TokenKind::Import
+                | TokenKind::Export
+                | TokenKind::Print
+                    if self.current > start =>
+                {
+                    break
+                }
+                TokenKind::RightBrace if self.depth > 0 => break,
+                _ => {}
+            }
+
+            if !skipped {
+                self.start_node(SyntaxKind::Error);
+                skipped = true;
+            }
+            self.advance();
+        }
+        if skipped {
+            self.finish_node();
+        }
+    }
+
+    fn declaration_or_error(&mut self) -> Result<Statement, ParseError> {
+        // Doc comments on anything but a declaration are ignored.
+        let doc = self.docs.remove(&(self.current as usize));
+        // Nested imports and exports are parsed whole so that the error is
+        // only reported once.
+        if self.depth > 0 && self.check_any(&[TokenKind::Import, TokenKind::Export]) {
+            let keyword = self.peek().to_owned();
+            match keyword.kind {
+                TokenKind::Import => self.import_declaration()?,
+                _ => self.export_declaration(doc)?,
+            };
+            return Err(ParseError::new(
+                ErrorCode::NotTopLevel,
+                keyword,
+                "Expect 'import' and 'export' only at the top level",
+            ));
+        }
+        if self.check(TokenKind::Let) {
+            return self.let_declaration(doc);
+        }
+        if self.check(TokenKind::Type) {
+            return self.type_declaration(doc);
+        }
+        if self.check(TokenKind::Import) {
+            return self.import_declaration();
+        }
+        if self.check(TokenKind::Export) {
+            return self.export_declaration(doc);
+        }
+
+        self.statement()
+    }
+
+    fn let_declaration(&mut self, doc: Option<String>) -> Result<Statement, ParseError> {
+        self.declaration_node(SyntaxKind::LetStatement, |parser| {
+            parser.advance();
+            let name = parser
+                .consume(TokenKind::Identifier, "Expect variable name after 'let'")?
+                .to_owned();
+            parser.consume(TokenKind::Equal, "Expect '=' after variable name")?;
+            let initializer = parser.expression()?;
+            parser.consume_terminator("Expect ';' after variable declaration")?;
+
+            Ok(Statement::Let {
+                doc,
+                name,
+                initializer,
+            })
+        })
     }
 
-    fn equality_or_higher(&mut self) -> Box<Expression> {
-        let mut expr = self.comparison_or_higher();
+    fn type_declaration(&mut self, doc: Option<String>) -> Result<Statement, ParseError> {
+        self.declaration_node(SyntaxKind::TypeStatement, |parser| {
+            parser.advance();
+            let name = parser
+                .consume(TokenKind::Identifier, "Expect type name after 'type'")?
+                .to_owned();
+
+            let mut params = vec![];
+            if parser.check(TokenKind::Less) {
+                parser.node(SyntaxKind::TypeParams, |parser| {
+                    parser.advance();
+                    loop {
+                        let param =
+                            parser.consume(TokenKind::Identifier, "Expect type parameter name")?;
+                        params.push(param.to_owned());
+                        if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                            break;
+                        }
+                    }
+                    parser.consume(TokenKind::Greater, "Expect '>' after type parameters")?;
+                    Ok(())
+                })?;
+            }
+
+            parser.consume(TokenKind::Equal, "Expect '=' after type name")?;
+            let definition = parser.type_definition()?;
+            parser.consume_terminator("Expect ';' after type declaration")?;
+
+            Ok(Statement::Type {
+                doc,
+                name,
+                params,
+                definition,
+            })
+        })
+    }
+
+    // A lone type name is an alias; constructors with payloads or separated by
+    // '|' make a sum type, and braces make a record.
+    fn type_definition(&mut self) -> Result<TypeDefinition, ParseError> {
+        if self.check(TokenKind::LeftBrace) {
+            return self.node(SyntaxKind::RecordType, |parser| {
+                parser.advance();
+                Ok(TypeDefinition::Record(parser.record_fields()?))
+            });
+        }
+        if self.check(TokenKind::LeftParen) {
+            return Ok(TypeDefinition::Alias(self.type_expression()?));
+        }
+
+        let sum = self.checkpoint();
+        let leading_bar = self.match_token_kinds(vec![TokenKind::Bar]);
+        let first = self.checkpoint();
+        let name = self
+            .consume(TokenKind::Identifier, "Expect type or constructor name")?
+            .to_owned();
+
+        if !leading_bar && (self.check(TokenKind::Less) || !self.check_variant_follows()) {
+            let alias = self.node_at(first, SyntaxKind::NamedType, |parser| {
+                parser.type_arguments(name)
+            })?;
+            return Ok(TypeDefinition::Alias(alias));
+        }
+
+        let mut variants =
+            vec![self.node_at(first, SyntaxKind::Variant, |parser| parser.variant(name))?];
+        while self.match_token_kinds(vec![TokenKind::Bar]) {
+            let variant = self.node(SyntaxKind::Variant, |parser| {
+                let name = parser
+                    .consume(TokenKind::Identifier, "Expect constructor name after '|'")?
+                    .to_owned();
+                parser.variant(name)
+            })?;
+            variants.push(variant);
+        }
+        self.wrap(sum, SyntaxKind::SumType);
+
+        Ok(TypeDefinition::Sum(variants))
+    }
+
+    fn check_variant_follows(&self) -> bool {
+        self.check(TokenKind::LeftParen) || self.check(TokenKind::Bar)
+    }
 
-        while self.match_token_kinds(vec![TokenKind::BangEqual, TokenKind::EqualEqual]) {
-            expr = Box::new(Expression::new_binary(
-                expr,
-                self.previous().to_owned(),
-                self.comparison_or_higher(),
+    fn variant(&mut self, name: Token) -> Result<Variant, ParseError> {
+        if !is_constructor_name(&name) {
+            return Err(ParseError::new(
+                ErrorCode::InvalidConstructorName,
+                name,
+                "Expect constructor name to start with an uppercase letter",
             ));
         }
 
-        expr
+        let mut fields = vec![];
+        if self.match_token_kinds(vec![TokenKind::LeftParen]) {
+            while !self.check(TokenKind::RightParen) {
+                fields.push(self.type_expression()?);
+                if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenKind::RightParen, "Expect ')' after constructor fields")?;
+        }
+
+        Ok(Variant { name, fields })
+    }
+
+    fn record_fields(&mut self) -> Result<Vec<Field>, ParseError> {
+        let mut fields = vec![];
+        while !self.check(TokenKind::RightBrace) {
+            let field = self.node(SyntaxKind::Field, |parser| {
+                let name = parser
+                    .consume(TokenKind::Identifier, "Expect field name")?
+                    .to_owned();
+                parser.consume(TokenKind::Colon, "Expect ':' after field name")?;
+                let ty = parser.type_expression()?;
+                Ok(Field { name, ty })
+            })?;
+            fields.push(field);
+            if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                break;
+            }
+        }
+
+        self.consume(TokenKind::RightBrace, "Expect '}' after record fields")?;
+        Ok(fields)
+    }
+
+    fn type_expression(&mut self) -> Result<TypeExpression, ParseError> {
+        if self.check(TokenKind::LeftParen) {
+            let start = self.checkpoint();
+            let paren = self.advance().to_owned();
+            let mut elements = vec![];
+            while !self.check(TokenKind::RightParen) {
+                elements.push(self.type_expression()?);
+                if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenKind::RightParen, "Expect ')' after tuple type")?;
+
+            if self.match_token_kinds(vec![TokenKind::Arrow]) {
+                let ret = self.type_expression()?;
+                self.wrap(start, SyntaxKind::FunctionType);
+                return Ok(TypeExpression::Function {
+                    paren,
+                    params: elements,
+                    ret: Box::new(ret),
+                });
+            }
+            if elements.len() == 1 {
+                self.wrap(start, SyntaxKind::ParenType);
+                return Ok(elements.remove(0));
+            }
+            self.wrap(start, SyntaxKind::TupleType);
+            return Ok(TypeExpression::Tuple { paren, elements });
+        }
+
+        self.node(SyntaxKind::NamedType, |parser| {
+            let name = parser
+                .consume(TokenKind::Identifier, "Expect type name")?
+                .to_owned();
+            parser.type_arguments(name)
+        })
+    }
+
+    fn type_arguments(&mut self, name: Token) -> Result<TypeExpression, ParseError> {
+        let mut args = vec![];
+        if self.check(TokenKind::Less) {
+            self.node(SyntaxKind::TypeArgs, |parser| {
+                parser.advance();
+                loop {
+                    args.push(parser.type_expression()?);
+                    if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                        break;
+                    }
+                }
+                parser.consume(TokenKind::Greater, "Expect '>' after type arguments")?;
+                Ok(())
+            })?;
+        }
+
+        Ok(TypeExpression::Named { name, args })
+    }
+
+    fn import_declaration(&mut self) -> Result<Statement, ParseError> {
+        self.node(SyntaxKind::ImportStatement, |parser| {
+            parser.advance();
+            parser.consume(TokenKind::LeftBrace, "Expect '{' after 'import'")?;
+
+            let mut names = vec![];
+            while !parser.check(TokenKind::RightBrace) {
+                let name = parser.consume(TokenKind::Identifier, "Expect imported name")?;
+                names.push(name.to_owned());
+                if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                    break;
+                }
+            }
+
+            parser.consume(TokenKind::RightBrace, "Expect '}' after imported names")?;
+            parser.consume(TokenKind::From, "Expect 'from' after imported names")?;
+            let path = parser
+                .consume(TokenKind::Str, "Expect module path after 'from'")?
+                .to_owned();
+            parser.consume_terminator("Expect ';' after import")?;
+
+            Ok(Statement::Import { names, path })
+        })
+    }
+
+    fn export_declaration(&mut self, doc: Option<String>) -> Result<Statement, ParseError> {
+        self.declaration_node(SyntaxKind::ExportStatement, |parser| {
+            parser.advance();
+            let statement = if parser.check(TokenKind::Let) {
+                parser.let_declaration(doc)?
+            } else if parser.check(TokenKind::Type) {
+                parser.type_declaration(doc)?
+            } else {
+                return Err(parser.error(
+                    ErrorCode::ExpectedDeclaration,
+                    "Expect 'let' or 'type' after 'export'",
+                ));
+            };
+
+            Ok(Statement::Export {
+                statement: Box::new(statement),
+            })
+        })
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.check(TokenKind::Print) {
+            return self.node(SyntaxKind::PrintStatement, |parser| {
+                parser.advance();
+                let expr = parser.expression()?;
+                parser.consume_terminator("Expect ';' after value")?;
+                Ok(Statement::Print { expr })
+            });
+        }
+        if self.check(TokenKind::If) {
+            return self.if_statement();
+        }
+
+        self.node(SyntaxKind::ExpressionStatement, |parser| {
+            let expr = parser.expression()?;
+            parser.consume_terminator("Expect ';' after expression")?;
+            Ok(Statement::Expression { expr })
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<Statement, ParseError> {
+        self.node(SyntaxKind::IfStatement, |parser| {
+            parser.advance();
+            let condition = parser.expression()?;
+            let then_branch = parser.block()?;
+
+            let else_branch = if parser.match_token_kinds(vec![TokenKind::Else]) {
+                if parser.check(TokenKind::If) {
+                    Some(vec![parser.if_statement()?])
+                } else {
+                    Some(parser.block()?)
+                }
+            } else {
+                None
+            };
+
+            Ok(Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            })
+        })
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, ParseError> {
+        self.node(SyntaxKind::Block, |parser| {
+            parser.consume(TokenKind::LeftBrace, "Expect '{' before block")?;
+
+            parser.depth += 1;
+            let mut statements = vec![];
+            while !parser.check(TokenKind::RightBrace) && !parser.at_eof() {
+                if let Some(statement) = parser.declaration() {
+                    statements.push(statement);
+                }
+            }
+            parser.depth -= 1;
+
+            parser.consume(TokenKind::RightBrace, "Expect '}' after block")?;
+            Ok(statements)
+        })
+    }
+
+    fn expression(&mut self) -> Result<Box<Expression>, ParseError> {
+        if self.check_lambda() {
+            return self.lambda();
+        }
+
+        self.equality_or_higher()
+    }
+
+    // Looks ahead for `name =>` or `(name, ...) =>` without consuming anything.
+    fn check_lambda(&mut self) -> bool {
+        match self.peek().kind {
+            TokenKind::Identifier => self.peek_kind(1) == Some(TokenKind::Arrow),
+            TokenKind::LeftParen => {
+                let mut offset = 1;
+                if self.peek_kind(offset) != Some(TokenKind::RightParen) {
+                    loop {
+                        if self.peek_kind(offset) != Some(TokenKind::Identifier) {
+                            return false;
+                        }
+                        offset += 1;
+                        match self.peek_kind(offset) {
+                            Some(TokenKind::Comma) => offset += 1,
+                            Some(TokenKind::RightParen) => break,
+                            _ => return false,
+                        }
+                    }
+                }
+                self.peek_kind(offset + 1) == Some(TokenKind::Arrow)
+            }
+            _ => false,
+        }
+    }
+
+    fn lambda(&mut self) -> Result<Box<Expression>, ParseError> {
+        self.node(SyntaxKind::LambdaExpr, |parser| {
+            let start = parser.peek().span;
+            let mut params = vec![];
+            parser.node(SyntaxKind::ParamList, |parser| {
+                if parser.match_token_kinds(vec![TokenKind::LeftParen]) {
+                    while !parser.check(TokenKind::RightParen) {
+                        let param =
+                            parser.consume(TokenKind::Identifier, "Expect parameter name")?;
+                        params.push(param.to_owned());
+                        if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                            break;
+                        }
+                    }
+                    parser.consume(TokenKind::RightParen, "Expect ')' after parameters")?;
+                } else {
+                    let param = parser.consume(TokenKind::Identifier, "Expect parameter name")?;
+                    params.push(param.to_owned());
+                }
+                Ok(())
+            })?;
+
+            let arrow = parser
+                .consume(TokenKind::Arrow, "Expect '=>' after parameters")?
+                .to_owned();
+            let body = parser.expression()?;
+
+            Ok(Box::new(Expression::Lambda {
+                arrow,
+                params,
+                span: start.to(body.span()),
+                body: Rc::new(*body),
+            }))
+        })
+    }
+
+    // Parses operands with `operand`, joined by any of the left-associative
+    // operators in `operators`.
+    fn binary(
+        &mut self,
+        operators: &[TokenKind],
+        operand: fn(&mut Self) -> Result<Box<Expression>, ParseError>,
+    ) -> Result<Box<Expression>, ParseError> {
+        let start = self.checkpoint();
+        let mut expr = operand(self)?;
+
+        while operators.iter().any(|op| self.check(*op)) {
+            expr = self.node_at(start, SyntaxKind::BinaryExpr, |parser| {
+                let op = parser.advance().to_owned();
+                Ok(Box::new(Expression::new_binary(expr, op, operand(parser)?)))
+            })?;
+        }
+
+        Ok(expr)
+    }
+
+    fn equality_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        self.binary(
+            &[TokenKind::BangEqual, TokenKind::EqualEqual],
+            Parser::comparison_or_higher,
+        )
     }
 
     fn match_token_kinds(&mut self, token_kinds: Vec<TokenKind>) -> bool {
@@ -49,12 +664,23 @@ impl Parser {
     }
 
     fn peek(&self) -> &Token {
-        &self.tokens[self.current as usize]
+        &self.tokens[self.current as usize - self.base]
+    }
+
+    fn peek_kind(&mut self, offset: i64) -> Option<TokenKind> {
+        let index = (self.current + offset) as usize;
+        self.pull(index);
+        self.tokens.get(index - self.base).map(|token| token.kind)
     }
 
     fn advance(&mut self) -> &Token {
-        if self.at_eof() {
+        if !self.at_eof() {
+            self.flush_trivia();
+            if let Some(tree) = self.tree.as_mut() {
+                tree.token(&self.tokens[self.current as usize - self.base]);
+            }
             self.current += 1;
+            self.pull(self.current as usize);
         }
         self.previous()
     }
@@ -64,88 +690,758 @@ impl Parser {
     }
 
     fn previous(&self) -> &Token {
-        &self.tokens[self.current as usize - 1]
+        &self.tokens[self.current as usize - 1 - self.base]
+    }
+
+    fn comparison_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        self.binary(
+            &[
+                TokenKind::Greater,
+                TokenKind::GreaterEqual,
+                TokenKind::Less,
+                TokenKind::LessEqual,
+            ],
+            Parser::term_or_higher,
+        )
     }
 
-    fn comparison_or_higher(&mut self) -> Box<Expression> {
-        let mut expr = self.term_or_higher();
+    fn term_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        self.binary(
+            &[TokenKind::Minus, TokenKind::Plus],
+            Parser::factor_or_higher,
+        )
+    }
 
-        while self.match_token_kinds(vec![
-            TokenKind::Greater,
-            TokenKind::GreaterEqual,
-            TokenKind::Less,
-            TokenKind::LessEqual,
-        ]) {
-            expr = Box::new(Expression::new_binary(
-                expr,
-                self.previous().to_owned(),
-                self.term_or_higher(),
-            ));
+    fn factor_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        self.binary(
+            &[TokenKind::Slash, TokenKind::Star],
+            Parser::unary_or_higher,
+        )
+    }
+
+    fn unary_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        if self.check(TokenKind::Bang) || self.check(TokenKind::Minus) {
+            return self.node(SyntaxKind::UnaryExpr, |parser| {
+                let op = parser.advance().to_owned();
+                let right = parser.unary_or_higher()?;
+                Ok(Box::new(Expression::new_unary(op, right)))
+            });
         }
 
-        expr
+        self.call_or_higher()
     }
 
-    fn term_or_higher(&mut self) -> Box<Expression> {
-        let mut expr = self.factor_or_higher();
+    fn call_or_higher(&mut self) -> Result<Box<Expression>, ParseError> {
+        let start = self.checkpoint();
+        let mut expr = self.primary()?;
 
-        while self.match_token_kinds(vec![TokenKind::Minus, TokenKind::Plus]) {
-            expr = Box::new(Expression::new_binary(
-                expr,
-                self.previous().to_owned(),
-                self.factor_or_higher(),
-            ));
+        loop {
+            if self.check(TokenKind::Dot) {
+                expr = self.node_at(start, SyntaxKind::GetExpr, |parser| {
+                    parser.advance();
+                    let name = parser
+                        .consume(TokenKind::Identifier, "Expect field name after '.'")?
+                        .to_owned();
+                    let span = expr.span().to(name.span);
+                    Ok(Box::new(Expression::Get {
+                        object: expr,
+                        name,
+                        span,
+                    }))
+                })?;
+            } else if self.check(TokenKind::LeftParen) {
+                expr = self.node_at(start, SyntaxKind::CallExpr, |parser| {
+                    let (paren, args, end) = parser.arguments("Expect ')' after arguments")?;
+                    Ok(Box::new(Expression::Call {
+                        span: expr.span().to(end),
+                        callee: expr,
+                        paren,
+                        args,
+                    }))
+                })?;
+            } else {
+                break;
+            }
         }
 
-        expr
+        Ok(expr)
     }
 
-    fn factor_or_higher(&mut self) -> Box<Expression> {
-        let mut expr = self.unary_or_higher();
+    // Parses parenthesized arguments, returning the opening parenthesis, the
+    // arguments and where the closing one is.
+    fn arguments(&mut self, message: &str) -> Result<(Token, Vec<Expression>, Span), ParseError> {
+        self.node(SyntaxKind::ArgList, |parser| {
+            let paren = parser.advance().to_owned();
+            let mut args = vec![];
+            while !parser.check(TokenKind::RightParen) {
+                args.push(*parser.expression()?);
+                if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                    break;
+                }
+            }
+            let end = parser.consume(TokenKind::RightParen, message)?.span;
+            Ok((paren, args, end))
+        })
+    }
 
-        while self.match_token_kinds(vec![TokenKind::Slash, TokenKind::Star]) {
-            expr = Box::new(Expression::new_binary(
-                expr,
-                self.previous().to_owned(),
-                self.unary_or_higher(),
-            ));
+    fn primary(&mut self) -> Result<Box<Expression>, ParseError> {
+        let kind = match self.at_eof() {
+            true => TokenKind::Eof,
+            false => self.peek().kind,
+        };
+        let expr = match kind {
+            TokenKind::False | TokenKind::True | TokenKind::Num | TokenKind::Str => self
+                .node(SyntaxKind::LiteralExpr, |parser| {
+                    Ok(Expression::new_literal(parser.advance().to_owned()))
+                })?,
+            TokenKind::Template | TokenKind::TemplateHead => {
+                self.node(SyntaxKind::TemplateExpr, |parser| {
+                    let head = parser.advance().to_owned();
+                    parser.template(head)
+                })?
+            }
+            TokenKind::Identifier if is_constructor_name(self.peek()) => {
+                self.node(SyntaxKind::ConstructExpr, |parser| {
+                    let name = parser.advance().to_owned();
+                    parser.construct(name)
+                })?
+            }
+            TokenKind::Identifier => self.node(SyntaxKind::NameExpr, |parser| {
+                Ok(Expression::new_variable(parser.advance().to_owned()))
+            })?,
+            TokenKind::LeftBrace => self.node(SyntaxKind::RecordExpr, |parser| {
+                let brace = parser.advance().to_owned();
+                parser.record(brace)
+            })?,
+            TokenKind::LeftParen => {
+                let start = self.checkpoint();
+                let paren = self.advance().to_owned();
+                let inner = self.expression()?;
+                if self.check(TokenKind::Comma) {
+                    let tuple = self.tuple(paren, *inner)?;
+                    self.wrap(start, SyntaxKind::TupleExpr);
+                    tuple
+                } else {
+                    let end = self
+                        .consume(TokenKind::RightParen, "Expect ')' after expression")?
+                        .span;
+                    self.wrap(start, SyntaxKind::ParenExpr);
+                    Expression::new_grouping(inner, paren.span.to(end))
+                }
+            }
+            TokenKind::Match => self.node(SyntaxKind::MatchExpr, |parser| {
+                let keyword = parser.advance().to_owned();
+                parser.match_expression(keyword)
+            })?,
+            _ => return Err(self.error(ErrorCode::ExpectedExpression, "Expect expression")),
+        };
+
+        Ok(Box::new(expr))
+    }
+
+    /// Parses a template literal as the concatenation of its text and its
+    /// interpolated expressions. It starts with its text even when that is
+    /// empty, so it is always a Str.
+    fn template(&mut self, head: Token) -> Result<Expression, ParseError> {
+        let text = |part: &Token| {
+            Box::new(Expression::new_literal(Token {
+                kind: TokenKind::Str,
+                ..part.to_owned()
+            }))
+        };
+        let plus = |part: &Token| Token {
+            kind: TokenKind::Plus,
+            literal: None,
+            ..part.to_owned()
+        };
+
+        let mut expr = text(&head);
+        let mut part = head.to_owned();
+        while part.kind == TokenKind::TemplateHead || part.kind == TokenKind::TemplateMiddle {
+            let value = self.expression()?;
+            expr = Box::new(Expression::new_binary(expr, plus(&part), value));
+            if !self.match_token_kinds(vec![TokenKind::TemplateMiddle, TokenKind::TemplateTail]) {
+                return Err(self.error(
+                    ErrorCode::ExpectedToken,
+                    "Expect '}' after template expression",
+                ));
+            }
+            part = self.previous().to_owned();
+            if part.literal.as_deref().is_some_and(|text| !text.is_empty()) {
+                expr = Box::new(Expression::new_binary(expr, plus(&part), text(&part)));
+            }
         }
 
-        expr
+        if let Expression::Binary { span, .. } = expr.as_mut() {
+            *span = head.span.to(part.span);
+        }
+        Ok(*expr)
     }
 
-    fn unary_or_higher(&mut self) -> Box<Expression> {
-        if self.match_token_kinds(vec![TokenKind::Bang, TokenKind::Minus]) {
-            return Box::new(Expression::new_unary(
-                self.previous().to_owned(),
-                self.unary_or_higher(),
-            ));
+    fn construct(&mut self, name: Token) -> Result<Expression, ParseError> {
+        let mut args = vec![];
+        let mut span = name.span;
+        if self.check(TokenKind::LeftParen) {
+            let (_, arguments, end) = self.arguments("Expect ')' after constructor arguments")?;
+            args = arguments;
+            span = span.to(end);
         }
 
-        self.primary()
+        Ok(Expression::Construct { name, args, span })
     }
 
-    fn primary(&mut self) -> Box<Expression> {
-        let expr = if self.match_token_kinds(vec![
-            TokenKind::False,
-            TokenKind::True,
+    fn record(&mut self, brace: Token) -> Result<Expression, ParseError> {
+        let mut fields = vec![];
+        while !self.check(TokenKind::RightBrace) {
+            let field = self.node(SyntaxKind::RecordField, |parser| {
+                let name = parser
+                    .consume(TokenKind::Identifier, "Expect field name")?
+                    .to_owned();
+                parser.consume(TokenKind::Colon, "Expect ':' after field name")?;
+                Ok((name, *parser.expression()?))
+            })?;
+            fields.push(field);
+            if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                break;
+            }
+        }
+
+        let end = self
+            .consume(TokenKind::RightBrace, "Expect '}' after record fields")?
+            .span;
+        Ok(Expression::Record {
+            span: brace.span.to(end),
+            brace,
+            fields,
+        })
+    }
+
+    fn tuple(&mut self, paren: Token, first: Expression) -> Result<Expression, ParseError> {
+        let mut elements = vec![first];
+        while self.match_token_kinds(vec![TokenKind::Comma]) {
+            if self.check(TokenKind::RightParen) {
+                break;
+            }
+            elements.push(*self.expression()?);
+        }
+
+        let end = self
+            .consume(TokenKind::RightParen, "Expect ')' after tuple elements")?
+            .span;
+        Ok(Expression::Tuple {
+            span: paren.span.to(end),
+            paren,
+            elements,
+        })
+    }
+
+    fn match_expression(&mut self, keyword: Token) -> Result<Expression, ParseError> {
+        let subject = self.expression()?;
+        self.consume(TokenKind::LeftBrace, "Expect '{' after match subject")?;
+
+        let mut arms = vec![];
+        while !self.check(TokenKind::RightBrace) {
+            let arm = self.node(SyntaxKind::MatchArm, |parser| {
+                let pattern = parser.pattern()?;
+                parser.consume(TokenKind::Arrow, "Expect '=>' after pattern")?;
+                let body = parser.expression()?;
+                Ok(MatchArm { pattern, body })
+            })?;
+            arms.push(arm);
+            if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                break;
+            }
+        }
+
+        let end = self
+            .consume(TokenKind::RightBrace, "Expect '}' after match arms")?
+            .span;
+        Ok(Expression::Match {
+            span: keyword.span.to(end),
+            keyword,
+            subject,
+            arms,
+        })
+    }
+
+    fn pattern(&mut self) -> Result<Pattern, ParseError> {
+        if self.check_any(&[
             TokenKind::Num,
             TokenKind::Str,
+            TokenKind::True,
+            TokenKind::False,
         ]) {
-            Expression::new_literal(self.previous().to_owned())
-        } else if self.match_token_kinds(vec![TokenKind::LeftParen]) {
-            let inner = self.expression();
-            self.consume(TokenKind::RightParen, "Expect ')' after expression");
-            Expression::new_grouping(inner)
-        } else {
-            //error
-            Expression::new_literal(self.previous().to_owned())
+            return self.node(SyntaxKind::LiteralPattern, |parser| {
+                Ok(Pattern::Literal {
+                    lit: parser.advance().to_owned(),
+                })
+            });
+        }
+
+        if self.check(TokenKind::Minus) {
+            return self.node(SyntaxKind::LiteralPattern, |parser| {
+                let minus = parser.advance().to_owned();
+                let number =
+                    parser.consume(TokenKind::Num, "Expect number after '-' in pattern")?;
+                let lit = Token {
+                    kind: TokenKind::Num,
+                    literal: number.literal.as_ref().map(|n| format!("-{}", n)),
+                    span: minus.span.to(number.span),
+                    ..minus
+                };
+                Ok(Pattern::Literal { lit })
+            });
+        }
+
+        if self.check(TokenKind::Identifier) && !is_constructor_name(self.peek()) {
+            return self.node(SyntaxKind::BindingPattern, |parser| {
+                let name = parser.advance().to_owned();
+                if name.literal.as_deref() == Some("_") {
+                    return Ok(Pattern::Wildcard { token: name });
+                }
+                Ok(Pattern::Binding { name })
+            });
+        }
+
+        if self.check(TokenKind::Identifier) {
+            return self.node(SyntaxKind::ConstructorPattern, |parser| {
+                let name = parser.advance().to_owned();
+                let mut args = vec![];
+                if parser.match_token_kinds(vec![TokenKind::LeftParen]) {
+                    while !parser.check(TokenKind::RightParen) {
+                        args.push(parser.pattern()?);
+                        if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                            break;
+                        }
+                    }
+                    parser.consume(
+                        TokenKind::RightParen,
+                        "Expect ')' after constructor patterns",
+                    )?;
+                }
+                Ok(Pattern::Constructor { name, args })
+            });
+        }
+
+        if self.check(TokenKind::LeftBrace) {
+            return self.node(SyntaxKind::RecordPattern, |parser| {
+                let brace = parser.advance().to_owned();
+                let mut fields = vec![];
+                while !parser.check(TokenKind::RightBrace) {
+                    let field = parser.node(SyntaxKind::RecordPatternField, |parser| {
+                        let name = parser
+                            .consume(TokenKind::Identifier, "Expect field name")?
+                            .to_owned();
+                        // `{ x }` is shorthand for `{ x: x }`.
+                        let pattern = if parser.match_token_kinds(vec![TokenKind::Colon]) {
+                            parser.pattern()?
+                        } else {
+                            Pattern::Binding {
+                                name: name.to_owned(),
+                            }
+                        };
+                        Ok((name, pattern))
+                    })?;
+                    fields.push(field);
+                    if !parser.match_token_kinds(vec![TokenKind::Comma]) {
+                        break;
+                    }
+                }
+                parser.consume(TokenKind::RightBrace, "Expect '}' after record pattern")?;
+                Ok(Pattern::Record { brace, fields })
+            });
+        }
+
+        if self.check(TokenKind::LeftParen) {
+            let start = self.checkpoint();
+            let paren = self.advance().to_owned();
+            let mut elements = vec![];
+            while !self.check(TokenKind::RightParen) {
+                elements.push(self.pattern()?);
+                if !self.match_token_kinds(vec![TokenKind::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenKind::RightParen, "Expect ')' after tuple pattern")?;
+
+            if elements.len() == 1 {
+                self.wrap(start, SyntaxKind::ParenPattern);
+                return Ok(elements.remove(0));
+            }
+            self.wrap(start, SyntaxKind::TuplePattern);
+            return Ok(Pattern::Tuple { paren, elements });
+        }
+
+        Err(self.error(ErrorCode::ExpectedPattern, "Expect pattern"))
+    }
+
+    fn check_any(&self, token_kinds: &[TokenKind]) -> bool {
+        token_kinds.iter().any(|kind| self.check(*kind))
+    }
+
+    fn consume(&mut self, token_kind: TokenKind, expect_msg: &str) -> Result<&Token, ParseError> {
+        if self.check(token_kind) {
+            return Ok(self.advance());
+        }
+
+        let code = match token_kind {
+            TokenKind::Identifier => ErrorCode::ExpectedName,
+            _ => ErrorCode::ExpectedToken,
+        };
+        Err(self.error(code, expect_msg))
+    }
+
+    fn error(&self, code: ErrorCode, expect_msg: &str) -> ParseError {
+        ParseError::new(code, self.peek().to_owned(), expect_msg)
+    }
+
+    // The last statement of a program or block may omit its semicolon.
+    fn consume_terminator(&mut self, expect_msg: &str) -> Result<(), ParseError> {
+        if self.at_eof() || self.check(TokenKind::RightBrace) {
+            return Ok(());
+        }
+
+        self.consume(TokenKind::Semicolon, expect_msg).map(|_| ())
+    }
+
+    // Adds the trivia before the current token to the tree. Trivia before a
+    // node belongs to its parent.
+    fn flush_trivia(&mut self) {
+        let Some(tree) = self.tree.as_mut() else {
+            return;
         };
+        for token in self
+            .trivia
+            .remove(&(self.current as usize))
+            .iter()
+            .flatten()
+        {
+            tree.token(token);
+        }
+    }
+
+    fn start_node(&mut self, kind: SyntaxKind) {
+        self.flush_trivia();
+        if let Some(tree) = self.tree.as_mut() {
+            tree.start_node(kind);
+        }
+    }
+
+    fn finish_node(&mut self) {
+        if let Some(tree) = self.tree.as_mut() {
+            tree.finish_node();
+        }
+    }
+
+    // Closes the nodes of the tree until only `depth` are open.
+    fn finish_nodes(&mut self, depth: usize) {
+        while let Some(tree) = self.tree.as_mut().filter(|tree| tree.open_nodes() > depth) {
+            tree.finish_node();
+        }
+    }
+
+    fn checkpoint(&mut self) -> Option<Checkpoint> {
+        self.flush_trivia();
+        self.tree.as_ref().map(TreeSink::checkpoint)
+    }
+
+    // Makes a node of everything added to the tree since `checkpoint`.
+    fn wrap(&mut self, checkpoint: Option<Checkpoint>, kind: SyntaxKind) {
+        if let (Some(tree), Some(checkpoint)) = (self.tree.as_mut(), checkpoint) {
+            tree.start_node_at(checkpoint, kind);
+            tree.finish_node();
+        }
+    }
+
+    // Parses with `parse` inside a node of `kind`. On an error the node is
+    // left open for `declaration` to close once it has recovered.
+    fn node<T>(
+        &mut self,
+        kind: SyntaxKind,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        self.start_node(kind);
+        let result = parse(self)?;
+        self.finish_node();
+        Ok(result)
+    }
+
+    // Like `node`, but the node also holds everything since `checkpoint`.
+    fn node_at<T>(
+        &mut self,
+        checkpoint: Option<Checkpoint>,
+        kind: SyntaxKind,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if let (Some(tree), Some(checkpoint)) = (self.tree.as_mut(), checkpoint) {
+            tree.start_node_at(checkpoint, kind);
+        }
+        let result = parse(self)?;
+        self.finish_node();
+        Ok(result)
+    }
+
+    // Like `node`, but doc comments right before the declaration belong to it.
+    fn declaration_node<T>(
+        &mut self,
+        kind: SyntaxKind,
+        parse: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if let Some(tree) = self.tree.as_mut() {
+            let trivia = self
+                .trivia
+                .get_mut(&(self.current as usize))
+                .map(std::mem::take)
+                .unwrap_or_default();
+            let mut docs = trivia.len();
+            for (index, token) in trivia.iter().enumerate().rev() {
+                match token.kind {
+                    TokenKind::DocComment => docs = index,
+                    TokenKind::Whitespace => {}
+                    _ => break,
+                }
+            }
+            for token in &trivia[..docs] {
+                tree.token(token);
+            }
+            tree.start_node(kind);
+            for token in &trivia[docs..] {
+                tree.token(token);
+            }
+        }
+        let result = parse(self)?;
+        self.finish_node();
+        Ok(result)
+    }
+}
+
+// Constructors are told apart from variables by their leading uppercase letter.
+fn is_constructor_name(name: &Token) -> bool {
+    name.literal
+        .as_ref()
+        .is_some_and(|name| name.starts_with(char::is_uppercase))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ToDiagnostics;
+
+    fn parse(source: &str) -> Program {
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        Parser::new(tokens).parse_program().unwrap()
+    }
+
+    #[test]
+    fn parses_statements() {
+        let source = "import { a, b } from \"./lib\";
+            export type Pair<a, b> = Tuple<a, b>;
+            let x = a + 1;
+            if x > 2 { print x; } else if x == 2 { print \"two\"; } else { x; }";
+        let program = parse(source);
+
+        assert_eq!(program.statements.len(), 4);
+        assert_eq!(
+            program.to_string(),
+            "import { a, b } from \"./lib\";
+export type Pair<a, b> = Tuple<a, b>;
+let x = a + 1;
+if x > 2 { print x; } else { if x == 2 { print two; } else { x; } }"
+        );
+    }
+
+    #[test]
+    fn reports_every_error_after_synchronizing() {
+        let source = "let = 1;
+            let y = (2;
+            if y { let z = ; print z; }
+            print y;";
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        let errors = Parser::new(tokens).parse_program().unwrap_err();
+
+        let expected: Vec<(ErrorCode, &str, TokenKind)> = errors
+            .errors()
+            .iter()
+            .map(|err| (err.code, err.expected.as_str(), err.token.kind))
+            .collect();
+        assert_eq!(
+            expected,
+            vec![
+                (
+                    ErrorCode::ExpectedName,
+                    "Expect variable name after 'let'",
+                    TokenKind::Equal
+                ),
+                (
+                    ErrorCode::ExpectedToken,
+                    "Expect ')' after expression",
+                    TokenKind::Semicolon
+                ),
+                (
+                    ErrorCode::ExpectedExpression,
+                    "Expect expression",
+                    TokenKind::Semicolon
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_algebraic_data_types() {
+        let source = "type Option<a> = Some(a) | None;
+            type Shape = | Circle(Num);
+            type Point = { x: Num, y: Num };
+            type Name = Str;
+            let p = { x: 1, y: Some(2) }.y;";
+
+        assert_eq!(
+            parse(source).to_string(),
+            "type Option<a> = Some(a) | None;
+type Shape = Circle(Num);
+type Point = { x: Num, y: Num };
+type Name = Str;
+let p = { x: 1, y: Some(2) }.y;"
+        );
+    }
+
+    #[test]
+    fn parses_match_expressions() {
+        let source = "let n = match (x, { a: Some(1) }) {
+                (0, { a: None }) => \"zero\",
+                (-1, { a }) => \"negative\",
+                (_, { a: Some(n) }) => n,
+            };";
 
-        Box::new(expr)
+        assert_eq!(
+            parse(source).to_string(),
+            "let n = match (x, { a: Some(1) }) { (0, { a: None }) => zero, \
+             (-1, { a: a }) => negative, (_, { a: Some(n) }) => n };"
+        );
     }
 
-    fn consume(&self, token_kind: TokenKind, expect_msg: &str) {
-        unimplemented!();
+    #[test]
+    fn parses_functions_and_calls() {
+        let source = "type Op = (Num, Num) => Num;
+            let add = (a, b) => a + b;
+            let twice = f => x => f(f(x));
+            let unit = () => (1, 2);
+            let n = twice(x => x * 2)(add(1, (2)));";
+
+        assert_eq!(
+            parse(source).to_string(),
+            "type Op = (Num, Num) => Num;
+let add = (a, b) => a + b;
+let twice = (f) => (x) => f(f(x));
+let unit = () => (1, 2);
+let n = twice((x) => x * 2)(add(1, (2)));"
+        );
+    }
+
+    #[test]
+    fn last_semicolon_is_optional() {
+        let program = parse("let x = 1; x");
+        assert_eq!(program.statements.len(), 2);
+    }
+
+    #[test]
+    fn expressions_span_their_source() {
+        let source = "let f = (a) => a;\nlet n = f(match (1, 2) { _ => { x: -3 } }.x) + 4";
+        let program = parse(source);
+        let spanned: Vec<&str> = program
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Let { initializer, .. } => {
+                    let span = initializer.span();
+                    &source[span.start..span.end]
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(
+            spanned,
+            vec!["(a) => a", "f(match (1, 2) { _ => { x: -3 } }.x) + 4"]
+        );
+    }
+
+    #[test]
+    fn parses_templates_as_concatenation() {
+        let source = "let s = `a${x}b${`c${y}`}`;\nlet t = `${x}`;\nlet u = `plain`;";
+        let program = parse(source);
+        assert_eq!(
+            program.to_string(),
+            "let s = a + x + b + c + y;\nlet t =  + x;\nlet u = plain;"
+        );
+
+        let spans: Vec<&str> = program
+            .statements
+            .iter()
+            .map(|statement| match statement {
+                Statement::Let { initializer, .. } => {
+                    let span = initializer.span();
+                    &source[span.start..span.end]
+                }
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(spans, vec!["`a${x}b${`c${y}`}`", "`${x}`", "`plain`"]);
+
+        let tokens = Lexer::new(b"`a${1 + }`").tokenize().unwrap();
+        let errors = Parser::new(tokens).parse_program().unwrap_err();
+        assert_eq!(errors.errors()[0].code, ErrorCode::ExpectedExpression);
+    }
+
+    #[test]
+    fn attaches_doc_comments_to_declarations() {
+        let source = "/// The answer.
+            ///
+            ///   Indented.
+            let answer = 42;
+            // not documentation
+            let plain = /** block */ 1;
+            /// Exported shapes.
+            export type Shape = Circle(Num) | Square(Num);
+            /// Ignored on a statement.
+            print answer;";
+        let program = parse(source);
+
+        let docs: Vec<Option<&str>> = program.statements.iter().map(|s| s.doc()).collect();
+        assert_eq!(
+            docs,
+            vec![
+                Some("The answer.\n\n  Indented."),
+                None,
+                Some("Exported shapes."),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn pulls_tokens_from_the_lexer_as_needed() {
+        let source = "/// Doubles.\nlet double = (x) => x * 2;\n".repeat(100) + "double(1)";
+        let mut parser = Parser::from_lexer(Lexer::new(source.as_bytes()));
+        assert_eq!(parser.tokens.len(), 1);
+
+        let program = parser.parse_program().unwrap();
+        assert_eq!(program.statements.len(), 101);
+        assert_eq!(program.statements[99].doc(), Some("Doubles."));
+        assert!(parser.tokens.len() <= 6);
+
+        let errors = Parser::from_lexer(Lexer::new(b"let a = 1 # 2;\nlet = 0x;"))
+            .parse_program()
+            .unwrap_err();
+        let codes: Vec<ErrorCode> = errors
+            .lexer_errors()
+            .errors()
+            .iter()
+            .map(|err| err.code)
+            .collect();
+        assert_eq!(
+            codes,
+            vec![ErrorCode::UnknownCharacter, ErrorCode::InvalidNumber]
+        );
+        assert_eq!(errors.to_diagnostics().len(), 2);
     }
 }
@@ -0,0 +1,128 @@
+use crate::diagnostics::{Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::lexer::{LexerError, ParseTokenError, Token, TokenKind};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    pub code: ErrorCode,
+    pub token: Token,
+    pub expected: String,
+}
+
+impl ParseError {
+    pub fn new(code: ErrorCode, token: Token, expected: &str) -> Self {
+        ParseError {
+            code,
+            token,
+            expected: expected.to_owned(),
+        }
+    }
+
+    pub fn line(&self) -> u32 {
+        self.token.line
+    }
+
+    pub fn col(&self) -> u32 {
+        self.token.col
+    }
+
+    fn found(&self) -> String {
+        match self.token.kind {
+            TokenKind::Eof => "end of input".to_owned(),
+            _ => format!("\"{}\"", self.token),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{0}, found {1} at line {2}, column {3}.",
+            self.expected,
+            self.found(),
+            self.line(),
+            self.col()
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+impl ToDiagnostics for ParseError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        vec![Diagnostic::error(&self.expected)
+            .with_code(self.code)
+            .with_label(Label::primary(
+                self.token.span,
+                format!("found {}", self.found()),
+            ))]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ParserError {
+    errors: Vec<ParseError>,
+    // Errors in the tokens the parser pulled, which stand in for any parse
+    // errors since those are likely caused by them.
+    lexer: LexerError,
+}
+
+impl ParserError {
+    pub fn new() -> Self {
+        ParserError {
+            errors: vec![],
+            lexer: LexerError::new(),
+        }
+    }
+
+    pub fn add(&mut self, err: ParseError) {
+        self.errors.push(err);
+    }
+
+    pub fn add_lexer_error(&mut self, err: ParseTokenError) {
+        self.lexer.add(err);
+    }
+
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    pub fn lexer_errors(&self) -> &LexerError {
+        &self.lexer
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty() && self.lexer.is_empty()
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if !self.lexer.is_empty() {
+            return self.lexer.fmt(f);
+        }
+        let mut err_strings: Vec<String> = vec![];
+        for err in &self.errors {
+            err_strings.push(format!("{}", err));
+        }
+        write!(f, "Error parsing program: \n{}", err_strings.join("\n"))
+    }
+}
+
+impl Error for ParserError {}
+
+impl ToDiagnostics for ParserError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        if !self.lexer.is_empty() {
+            return self.lexer.to_diagnostics();
+        }
+        self.errors
+            .iter()
+            .flat_map(|err| err.to_diagnostics())
+            .collect()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::lexer::Token;
+use crate::parser::Expression;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pattern {
+    Wildcard {
+        token: Token,
+    },
+    Binding {
+        name: Token,
+    },
+    Literal {
+        lit: Token,
+    },
+    Constructor {
+        name: Token,
+        args: Vec<Pattern>,
+    },
+    Record {
+        brace: Token,
+        fields: Vec<(Token, Pattern)>,
+    },
+    Tuple {
+        paren: Token,
+        elements: Vec<Pattern>,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: Box<Expression>,
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl Pattern {
+    /// A representative token to point at when reporting on the whole pattern.
+    pub fn token(&self) -> &Token {
+        match self {
+            Pattern::Wildcard { token } => token,
+            Pattern::Binding { name } => name,
+            Pattern::Literal { lit } => lit,
+            Pattern::Constructor { name, .. } => name,
+            Pattern::Record { brace, .. } => brace,
+            Pattern::Tuple { paren, .. } => paren,
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard { .. } => write!(f, "_"),
+            Pattern::Binding { name } => write!(f, "{}", name),
+            Pattern::Literal { lit } => write!(f, "{}", lit),
+            Pattern::Constructor { name, args } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    write!(f, "({})", join(args))?;
+                }
+                Ok(())
+            }
+            Pattern::Record { fields, .. } => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, pattern)| format!("{0}: {1}", name, pattern))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Pattern::Tuple { elements, .. } => write!(f, "({})", join(elements)),
+        }
+    }
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{0} => {1}", self.pattern, self.body)
+    }
+}
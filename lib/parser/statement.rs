@@ -0,0 +1,116 @@
+use crate::lexer::Token;
+use crate::parser::{Expression, TypeDefinition};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Statement {
+    Let {
+        doc: Option<String>,
+        name: Token,
+        initializer: Box<Expression>,
+    },
+    Expression {
+        expr: Box<Expression>,
+    },
+    Print {
+        expr: Box<Expression>,
+    },
+    If {
+        condition: Box<Expression>,
+        then_branch: Vec<Statement>,
+        else_branch: Option<Vec<Statement>>,
+    },
+    Type {
+        doc: Option<String>,
+        name: Token,
+        params: Vec<Token>,
+        definition: TypeDefinition,
+    },
+    Import {
+        names: Vec<Token>,
+        path: Token,
+    },
+    Export {
+        statement: Box<Statement>,
+    },
+}
+
+fn write_block(f: &mut fmt::Formatter, statements: &[Statement]) -> fmt::Result {
+    write!(f, "{{ ")?;
+    for statement in statements {
+        write!(f, "{} ", statement)?;
+    }
+    write!(f, "}}")
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl Statement {
+    /// The doc comment written before a declaration, without its slashes.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Statement::Let { doc, .. } | Statement::Type { doc, .. } => doc.as_deref(),
+            Statement::Export { statement } => statement.doc(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Statement::Let {
+                name, initializer, ..
+            } => write!(f, "let {0} = {1};", name, initializer),
+            Statement::Expression { expr } => write!(f, "{};", expr),
+            Statement::Print { expr } => write!(f, "print {};", expr),
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                write!(f, "if {} ", condition)?;
+                write_block(f, then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    write!(f, " else ")?;
+                    write_block(f, else_branch)?;
+                }
+                Ok(())
+            }
+            Statement::Type {
+                name,
+                params,
+                definition,
+                ..
+            } => {
+                write!(f, "type {}", name)?;
+                if !params.is_empty() {
+                    write!(f, "<{}>", join(params))?;
+                }
+                write!(f, " = {};", definition)
+            }
+            Statement::Import { names, path } => {
+                write!(f, "import {{ {0} }} from \"{1}\";", join(names), path)
+            }
+            Statement::Export { statement } => write!(f, "export {}", statement),
+        }
+    }
+}
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let lines: Vec<String> = self.statements.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
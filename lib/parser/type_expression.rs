@@ -0,0 +1,104 @@
+use crate::lexer::Token;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeExpression {
+    Named {
+        name: Token,
+        args: Vec<TypeExpression>,
+    },
+    Tuple {
+        paren: Token,
+        elements: Vec<TypeExpression>,
+    },
+    Function {
+        paren: Token,
+        params: Vec<TypeExpression>,
+        ret: Box<TypeExpression>,
+    },
+}
+
+/// The right-hand side of a `type` declaration.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TypeDefinition {
+    Alias(TypeExpression),
+    Sum(Vec<Variant>),
+    Record(Vec<Field>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Variant {
+    pub name: Token,
+    pub fields: Vec<TypeExpression>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Field {
+    pub name: Token,
+    pub ty: TypeExpression,
+}
+
+fn join<T: fmt::Display>(items: &[T]) -> String {
+    items
+        .iter()
+        .map(|item| item.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl TypeExpression {
+    pub fn token(&self) -> &Token {
+        match self {
+            TypeExpression::Named { name, .. } => name,
+            TypeExpression::Tuple { paren, .. } => paren,
+            TypeExpression::Function { paren, .. } => paren,
+        }
+    }
+}
+
+impl fmt::Display for TypeExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeExpression::Named { name, args } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    write!(f, "<{}>", join(args))?;
+                }
+                Ok(())
+            }
+            TypeExpression::Tuple { elements, .. } => write!(f, "({})", join(elements)),
+            TypeExpression::Function { params, ret, .. } => {
+                write!(f, "({0}) => {1}", join(params), ret)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.fields.is_empty() {
+            write!(f, "({})", join(&self.fields))?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{0}: {1}", self.name, self.ty)
+    }
+}
+
+impl fmt::Display for TypeDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TypeDefinition::Alias(ty) => write!(f, "{}", ty),
+            TypeDefinition::Sum(variants) => {
+                let variants: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+                write!(f, "{}", variants.join(" | "))
+            }
+            TypeDefinition::Record(fields) => write!(f, "{{ {} }}", join(fields)),
+        }
+    }
+}
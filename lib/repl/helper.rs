@@ -0,0 +1,110 @@
+use crate::highlight::{highlight, HighlightKind};
+use crate::lexer::{Lexer, TokenKind};
+use rustyline::completion::Completer;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::Helper;
+use std::borrow::Cow;
+
+const RESET: &str = "\x1b[0m";
+const MAGENTA: &str = "\x1b[35m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const GRAY: &str = "\x1b[90m";
+const CYAN: &str = "\x1b[36m";
+const BLUE: &str = "\x1b[34m";
+
+/// Highlights what is typed at the prompt and keeps reading lines while
+/// braces or parentheses are left open.
+pub struct InputHelper;
+
+impl Helper for InputHelper {}
+
+impl Completer for InputHelper {
+    type Candidate = String;
+}
+
+impl Hinter for InputHelper {
+    type Hint = String;
+}
+
+impl Validator for InputHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        Ok(match is_complete(ctx.input()) {
+            true => ValidationResult::Valid(None),
+            false => ValidationResult::Incomplete,
+        })
+    }
+}
+
+impl Highlighter for InputHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(colored(line))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        true
+    }
+}
+
+/// Whether `input` closes every brace and parenthesis it opens.
+pub fn is_complete(input: &str) -> bool {
+    let mut depth = 0;
+    for token in Lexer::new(input.as_bytes()).filter_map(Result::ok) {
+        match token.kind {
+            TokenKind::LeftBrace | TokenKind::LeftParen => depth += 1,
+            TokenKind::RightBrace | TokenKind::RightParen => depth -= 1,
+            _ => {}
+        }
+    }
+    depth <= 0
+}
+
+// The source with each token in the color for its kind.
+fn colored(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut end = 0;
+    for highlight in highlight(source) {
+        let color = match highlight.kind {
+            HighlightKind::Keyword => MAGENTA,
+            HighlightKind::String => GREEN,
+            HighlightKind::Number => YELLOW,
+            HighlightKind::Comment => GRAY,
+            HighlightKind::TypeName => CYAN,
+            HighlightKind::Constructor => BLUE,
+            HighlightKind::Identifier | HighlightKind::Operator => continue,
+        };
+        let span = highlight.span;
+        out.push_str(&source[end..span.start]);
+        out.push_str(color);
+        out.push_str(&source[span.start..span.end]);
+        out.push_str(RESET);
+        end = span.end;
+    }
+    out.push_str(&source[end..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn continues_while_brackets_are_open() {
+        assert!(is_complete("let a = 1;"));
+        assert!(!is_complete("let f = x => {"));
+        assert!(!is_complete("f(1,\n  2"));
+        assert!(is_complete("let f = x => {\n  x\n};"));
+        assert!(is_complete("`${ { a: 1 }.a }`"));
+        assert!(is_complete("\"(\""));
+    }
+
+    #[test]
+    fn colors_tokens_by_kind() {
+        assert_eq!(
+            colored("let x = Some(\"a\"); // c"),
+            "\x1b[35mlet\x1b[0m x = \x1b[34mSome\x1b[0m(\x1b[32m\"a\"\x1b[0m); \x1b[90m// c\x1b[0m"
+        );
+    }
+}
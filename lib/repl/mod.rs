@@ -1,3 +1,4 @@
+mod helper;
 mod repl;
 
 pub use repl::REPL;
@@ -1,40 +1,192 @@
-use crate::{interpreter, BoxError};
-use std::io::{self, Write};
+use super::helper::InputHelper;
+use crate::diagnostics::{self, Renderer};
+use crate::interpreter::{Interpreter, Value};
+use crate::lexer::Lexer;
+use crate::loader::{ModuleLoader, INPUT};
+use crate::parser::{Parser, Program};
+use crate::typechecker::TypeChecker;
+use crate::BoxError;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
 
 pub struct REPL {
-    commands: Vec<String>,
+    history: Option<PathBuf>,
+    // What the inputs so far have defined and imported.
+    loader: ModuleLoader,
+    checker: TypeChecker,
+    interpreter: Interpreter,
+}
+
+impl Default for REPL {
+    fn default() -> Self {
+        REPL {
+            history: dirs::data_dir().map(|dir| dir.join("lightscript").join("history")),
+            loader: ModuleLoader::new(),
+            checker: TypeChecker::new(),
+            interpreter: Interpreter::new(),
+        }
+    }
 }
 
 impl REPL {
+    /// A REPL that keeps its history in the user's data directory.
     pub fn new() -> REPL {
-        REPL { commands: vec![] }
+        REPL::default()
+    }
+
+    /// A REPL that keeps its history in the file at `path`, or nowhere.
+    pub fn with_history(history: Option<PathBuf>) -> REPL {
+        REPL {
+            history,
+            ..REPL::default()
+        }
     }
 
     pub fn run(&mut self) -> Result<(), BoxError> {
+        let mut editor: Editor<InputHelper, DefaultHistory> = Editor::new()?;
+        editor.set_helper(Some(InputHelper));
+        if let Some(path) = &self.history {
+            // There is no history before the first session.
+            let _ = editor.load_history(path);
+        }
+
         println!("Welcome to the Iodine REPL!");
         loop {
-            let mut buffer = String::new();
-            let stdin = io::stdin();
-
-            print!(">> ");
-            io::stdout().flush().expect("Unable to flush stdout!");
-            stdin.read_line(&mut buffer).expect("Unable to read line!");
-            self.commands.push(buffer.to_owned());
-
-            match buffer.as_str() {
-                ".quit" => {
-                    println!("See you!");
-                    std::process::exit(0);
-                }
+            let input = match editor.readline(">> ") {
+                Ok(input) => input,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err.into()),
+            };
+            if input.trim().is_empty() {
+                continue;
+            }
+            editor.add_history_entry(input.as_str())?;
+
+            match input.trim() {
+                ".quit" => break,
                 ".history" => {
-                    for command in &self.commands {
+                    for command in editor.history().iter() {
                         println!("{}", command);
                     }
                 }
-                _ => {
-                    interpreter::eval(buffer)?;
-                }
+                _ => match self.eval(&input) {
+                    Ok(Some(value)) => println!("{}", value),
+                    Ok(None) => {}
+                    Err(err) => {
+                        let renderer = Renderer::with_color(io::stdout().is_terminal());
+                        print!(
+                            "{}",
+                            renderer.render_all(
+                                &diagnostics::diagnostics(err.as_ref()),
+                                self.loader.source_map()
+                            )
+                        );
+                    }
+                },
+            }
+        }
+        println!("See you!");
+
+        if let Some(path) = &self.history {
+            let saved = match path.parent() {
+                Some(dir) => fs::create_dir_all(dir).map_err(ReadlineError::from),
+                None => Ok(()),
+            }
+            .and_then(|_| editor.save_history(path));
+            if let Err(err) = saved {
+                eprintln!("Unable to save history to {}: {}", path.display(), err);
             }
         }
+        Ok(())
+    }
+
+    /// Runs `input` with the names that earlier inputs defined and
+    /// imported, returning the value of its last statement. Input that fails
+    /// defines nothing.
+    pub fn eval(&mut self, input: &str) -> Result<Option<Value>, BoxError> {
+        let file_id = self.loader.add_source(INPUT, input);
+        let program =
+            Parser::from_lexer(Lexer::with_file(input.as_bytes(), file_id)).parse_program()?;
+
+        let checkpoint = self.checker.checkpoint();
+        let result = self.check_and_run(&program);
+        if result.is_err() {
+            self.checker.restore(checkpoint);
+        }
+        result
+    }
+
+    fn check_and_run(&mut self, program: &Program) -> Result<Option<Value>, BoxError> {
+        self.loader
+            .check_imports(program, Path::new("."), &mut self.checker)?;
+        self.checker.check_program(program)?;
+        self.loader
+            .run_imports(program, Path::new("."), &mut self.interpreter)?;
+        Ok(self.interpreter.run_nested(program)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(inputs: &[&str]) -> Vec<String> {
+        let mut repl = REPL::with_history(None);
+        inputs
+            .iter()
+            .map(|input| match repl.eval(input) {
+                Ok(value) => value.map_or_else(String::new, |value| value.to_string()),
+                Err(err) => format!("error: {}", err),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_definitions_for_later_inputs() {
+        let results = session(&[
+            "type Shape = Circle(Num) | Square(Num);",
+            "let area = s => match s { Circle(r) => r * r * 3, Square(w) => w * w };",
+            "area(Square(2)) + area(Circle(1))",
+        ]);
+        assert_eq!(results, ["", "", "7"]);
+    }
+
+    #[test]
+    fn shadows_earlier_definitions() {
+        let results = session(&[
+            "let x = 1;",
+            "let f = () => x;",
+            "let x = \"one\";",
+            "(x, f())",
+        ]);
+        assert_eq!(results[3], "(\"one\", 1)");
+    }
+
+    #[test]
+    fn shadows_earlier_types() {
+        let results = session(&[
+            "type T = A(Num);",
+            "let a = A(1);",
+            "type T = A(Str);",
+            "let f = t => match t { A(s) => s + \"x\" };",
+            "f(a)",
+            "f(A(\"y\"))",
+        ]);
+        assert!(results[4].starts_with("error"), "{}", results[4]);
+        assert!(!results[4].contains("R0"), "{}", results[4]);
+        assert_eq!(results[5], "\"yx\"");
+    }
+
+    #[test]
+    fn forgets_input_that_fails() {
+        let results = session(&["let x = 1;", "let x = \"a\"; let y = x + 1;", "x + 1", "y"]);
+        assert_eq!(results[2], "2");
+        assert!(results[1].starts_with("error"), "{}", results[1]);
+        assert!(results[3].starts_with("error"), "{}", results[3]);
     }
 }
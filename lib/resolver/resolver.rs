@@ -0,0 +1,476 @@
+use crate::lexer::Token;
+use crate::parser::{Expression, Pattern, Program, Statement, TypeDefinition, TypeExpression};
+use crate::source::Span;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DefinitionKind {
+    /// A name bound by `let`, an import or a pattern.
+    Value,
+    Parameter,
+    Type,
+    TypeParameter,
+    Constructor,
+}
+
+impl DefinitionKind {
+    fn is_type(self) -> bool {
+        matches!(self, DefinitionKind::Type | DefinitionKind::TypeParameter)
+    }
+
+    // Whether a name used where a definition of this kind is needed can refer
+    // to `other`.
+    fn accepts(self, other: DefinitionKind) -> bool {
+        match self {
+            DefinitionKind::Value | DefinitionKind::Parameter => {
+                matches!(other, DefinitionKind::Value | DefinitionKind::Parameter)
+            }
+            DefinitionKind::Type | DefinitionKind::TypeParameter => other.is_type(),
+            DefinitionKind::Constructor => other == DefinitionKind::Constructor,
+        }
+    }
+}
+
+/// A name introduced by the program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: DefinitionKind,
+    /// The name where it is defined.
+    pub span: Span,
+    /// The source the name can be used in.
+    pub scope: Span,
+    pub doc: Option<String>,
+}
+
+/// A use of a name and the definition it refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Reference {
+    pub span: Span,
+    pub definition: usize,
+}
+
+/// What each name in a program refers to. Names that are not defined in the
+/// program, like builtin types, have no references.
+#[derive(Debug, Default)]
+pub struct Resolution {
+    definitions: Vec<Definition>,
+    references: Vec<Reference>,
+    unresolved: Vec<(Span, DefinitionKind)>,
+}
+
+impl Resolution {
+    pub fn definitions(&self) -> &[Definition] {
+        &self.definitions
+    }
+
+    pub fn references(&self) -> &[Reference] {
+        &self.references
+    }
+
+    /// The uses of names the program does not define, with the kind of
+    /// definition each would need.
+    pub fn unresolved(&self) -> &[(Span, DefinitionKind)] {
+        &self.unresolved
+    }
+
+    /// The index of the definition named at `offset`, by the definition
+    /// itself or by a reference to it.
+    pub fn definition_at(&self, offset: usize) -> Option<usize> {
+        let covers = |span: &Span| span.start <= offset && offset <= span.end;
+        self.references
+            .iter()
+            .find(|reference| covers(&reference.span))
+            .map(|reference| reference.definition)
+            .or_else(|| {
+                self.definitions
+                    .iter()
+                    .position(|definition| covers(&definition.span))
+            })
+    }
+
+    /// Every reference to the definition at `index`.
+    pub fn references_to(&self, index: usize) -> impl Iterator<Item = Span> + '_ {
+        self.references
+            .iter()
+            .filter(move |reference| reference.definition == index)
+            .map(|reference| reference.span)
+    }
+
+    /// The definitions that can be used at `offset`, leaving out those
+    /// shadowed by a later one of the same name.
+    pub fn visible_at(&self, offset: usize) -> Vec<&Definition> {
+        let mut visible: Vec<&Definition> = vec![];
+        for definition in &self.definitions {
+            let scope = definition.scope;
+            if offset < scope.start || offset > scope.end {
+                continue;
+            }
+            match visible.iter_mut().find(|seen| {
+                seen.name == definition.name && seen.kind.is_type() == definition.kind.is_type()
+            }) {
+                Some(seen) => *seen = definition,
+                None => visible.push(definition),
+            }
+        }
+        visible
+    }
+}
+
+/// Finds the definition of every name used in `program`.
+pub fn resolve(program: &Program) -> Resolution {
+    let mut resolver = Resolver::default();
+    resolver.scopes.push(vec![]);
+    // Top level names can be used up to the end of the file.
+    resolver.statements(&program.statements, usize::MAX);
+    resolver.resolution
+}
+
+#[derive(Default)]
+struct Resolver {
+    resolution: Resolution,
+    // The definitions of each open scope, innermost last.
+    scopes: Vec<Vec<usize>>,
+}
+
+impl Resolver {
+    fn statements(&mut self, statements: &[Statement], end: usize) {
+        for statement in statements {
+            self.statement(statement, end);
+        }
+    }
+
+    fn block(&mut self, statements: &[Statement]) {
+        let end = statements
+            .last()
+            .and_then(statement_span)
+            .map_or(0, |span| span.end);
+        self.scopes.push(vec![]);
+        self.statements(statements, end);
+        self.scopes.pop();
+    }
+
+    // Defines the names of a statement, visible until `end`.
+    fn statement(&mut self, statement: &Statement, end: usize) {
+        match statement {
+            Statement::Let {
+                doc,
+                name,
+                initializer,
+            } => {
+                // Functions may call themselves.
+                if let Expression::Lambda { .. } = initializer.as_ref() {
+                    self.define(name, DefinitionKind::Value, end, doc.to_owned());
+                    self.expression(initializer);
+                } else {
+                    self.expression(initializer);
+                    self.define(name, DefinitionKind::Value, end, doc.to_owned());
+                }
+            }
+            Statement::Expression { expr } | Statement::Print { expr } => self.expression(expr),
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.expression(condition);
+                self.block(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.block(else_branch);
+                }
+            }
+            Statement::Type {
+                doc,
+                name,
+                params,
+                definition,
+            } => {
+                // Types may refer to themselves.
+                self.define(name, DefinitionKind::Type, end, doc.to_owned());
+                let own_end = statement_span(statement).map_or(end, |span| span.end);
+                self.scopes.push(vec![]);
+                for param in params {
+                    self.define(param, DefinitionKind::TypeParameter, own_end, None);
+                }
+                match definition {
+                    TypeDefinition::Alias(target) => self.type_expression(target),
+                    TypeDefinition::Sum(variants) => variants
+                        .iter()
+                        .flat_map(|variant| &variant.fields)
+                        .for_each(|field| self.type_expression(field)),
+                    TypeDefinition::Record(fields) => fields
+                        .iter()
+                        .for_each(|field| self.type_expression(&field.ty)),
+                }
+                self.scopes.pop();
+
+                if let TypeDefinition::Sum(variants) = definition {
+                    for variant in variants {
+                        self.define(&variant.name, DefinitionKind::Constructor, end, None);
+                    }
+                }
+            }
+            Statement::Import { names, .. } => {
+                for name in names {
+                    let kind = match name.to_string().starts_with(char::is_uppercase) {
+                        true => DefinitionKind::Type,
+                        false => DefinitionKind::Value,
+                    };
+                    self.define(name, kind, end, None);
+                }
+            }
+            Statement::Export { statement } => self.statement(statement, end),
+        }
+    }
+
+    fn expression(&mut self, expr: &Expression) {
+        match expr {
+            Expression::Variable { name, .. } => self.refer(name, DefinitionKind::Value),
+            Expression::Construct { name, args, .. } => {
+                self.refer(name, DefinitionKind::Constructor);
+                args.iter().for_each(|arg| self.expression(arg));
+            }
+            Expression::Lambda { params, body, .. } => {
+                self.scopes.push(vec![]);
+                for param in params {
+                    self.define_in(param, DefinitionKind::Parameter, body.span(), None);
+                }
+                self.expression(body);
+                self.scopes.pop();
+            }
+            Expression::Match { subject, arms, .. } => {
+                self.expression(subject);
+                for arm in arms {
+                    self.scopes.push(vec![]);
+                    self.pattern(&arm.pattern, arm.body.span());
+                    self.expression(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Expression::Unary { right, .. } => self.expression(right),
+            Expression::Binary { left, right, .. } => {
+                self.expression(left);
+                self.expression(right);
+            }
+            Expression::Grouping { expr, .. } => self.expression(expr),
+            Expression::Literal { .. } => {}
+            Expression::Record { fields, .. } => {
+                fields.iter().for_each(|(_, value)| self.expression(value))
+            }
+            Expression::Get { object, .. } => self.expression(object),
+            Expression::Tuple { elements, .. } => {
+                elements.iter().for_each(|element| self.expression(element))
+            }
+            Expression::Call { callee, args, .. } => {
+                self.expression(callee);
+                args.iter().for_each(|arg| self.expression(arg));
+            }
+        }
+    }
+
+    // Defines the variables bound by a pattern, visible in `scope`.
+    fn pattern(&mut self, pattern: &Pattern, scope: Span) {
+        match pattern {
+            Pattern::Binding { name } => self.define_in(name, DefinitionKind::Value, scope, None),
+            Pattern::Constructor { name, args } => {
+                self.refer(name, DefinitionKind::Constructor);
+                args.iter().for_each(|arg| self.pattern(arg, scope));
+            }
+            Pattern::Record { fields, .. } => fields
+                .iter()
+                .for_each(|(_, pattern)| self.pattern(pattern, scope)),
+            Pattern::Tuple { elements, .. } => elements
+                .iter()
+                .for_each(|element| self.pattern(element, scope)),
+            Pattern::Wildcard { .. } | Pattern::Literal { .. } => {}
+        }
+    }
+
+    fn type_expression(&mut self, ty: &TypeExpression) {
+        match ty {
+            TypeExpression::Named { name, args } => {
+                self.refer(name, DefinitionKind::Type);
+                args.iter().for_each(|arg| self.type_expression(arg));
+            }
+            TypeExpression::Tuple { elements, .. } => elements
+                .iter()
+                .for_each(|element| self.type_expression(element)),
+            TypeExpression::Function { params, ret, .. } => {
+                params.iter().for_each(|param| self.type_expression(param));
+                self.type_expression(ret);
+            }
+        }
+    }
+
+    fn define(&mut self, name: &Token, kind: DefinitionKind, end: usize, doc: Option<String>) {
+        let scope = Span::new(name.span.file_id, name.span.start, end.max(name.span.end));
+        self.define_in(name, kind, scope, doc);
+    }
+
+    fn define_in(&mut self, name: &Token, kind: DefinitionKind, scope: Span, doc: Option<String>) {
+        self.resolution.definitions.push(Definition {
+            name: name.to_string(),
+            kind,
+            span: name.span,
+            scope,
+            doc,
+        });
+        let index = self.resolution.definitions.len() - 1;
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.push(index);
+        }
+    }
+
+    // Records a use of `name` where a definition of kind `expected` is
+    // needed, and the definition it refers to in one of the open scopes.
+    fn refer(&mut self, name: &Token, expected: DefinitionKind) {
+        let text = name.to_string();
+        let definitions = &self.resolution.definitions;
+        let found = self.scopes.iter().rev().find_map(|scope| {
+            scope.iter().rev().copied().find(|&index| {
+                definitions[index].name == text && expected.accepts(definitions[index].kind)
+            })
+        });
+        match found {
+            Some(definition) => self.resolution.references.push(Reference {
+                span: name.span,
+                definition,
+            }),
+            None => self.resolution.unresolved.push((name.span, expected)),
+        }
+    }
+}
+
+// The source a statement was parsed from, as far as the tree keeps it.
+fn statement_span(statement: &Statement) -> Option<Span> {
+    match statement {
+        Statement::Let {
+            name, initializer, ..
+        } => Some(name.span.to(initializer.span())),
+        Statement::Expression { expr } | Statement::Print { expr } => Some(expr.span()),
+        Statement::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let last = else_branch
+                .as_ref()
+                .unwrap_or(then_branch)
+                .last()
+                .and_then(statement_span);
+            Some(last.map_or(condition.span(), |last| condition.span().to(last)))
+        }
+        Statement::Type {
+            name, definition, ..
+        } => {
+            let last = match definition {
+                TypeDefinition::Alias(target) => Some(type_span(target)),
+                TypeDefinition::Sum(variants) => variants.last().map(|variant| {
+                    variant.fields.last().map_or(variant.name.span, |field| {
+                        variant.name.span.to(type_span(field))
+                    })
+                }),
+                TypeDefinition::Record(fields) => fields
+                    .last()
+                    .map(|field| field.name.span.to(type_span(&field.ty))),
+            };
+            Some(last.map_or(name.span, |last| name.span.to(last)))
+        }
+        Statement::Import { names, path } => Some(
+            names
+                .first()
+                .map_or(path.span, |first| first.span.to(path.span)),
+        ),
+        Statement::Export { statement } => statement_span(statement),
+    }
+}
+
+fn type_span(ty: &TypeExpression) -> Span {
+    match ty {
+        TypeExpression::Named { name, args } => args
+            .last()
+            .map_or(name.span, |last| name.span.to(type_span(last))),
+        TypeExpression::Tuple { paren, elements } => elements
+            .last()
+            .map_or(paren.span, |last| paren.span.to(type_span(last))),
+        TypeExpression::Function { paren, ret, .. } => paren.span.to(type_span(ret)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn resolved(source: &str) -> Resolution {
+        let program = Parser::from_lexer(Lexer::new(source.as_bytes()))
+            .parse_program()
+            .unwrap();
+        resolve(&program)
+    }
+
+    // Each reference as the text of the use and where its definition starts.
+    fn uses(source: &str) -> Vec<(&str, usize)> {
+        let resolution = resolved(source);
+        resolution
+            .references()
+            .iter()
+            .map(|reference| {
+                let span = reference.span;
+                let definition = &resolution.definitions()[reference.definition];
+                (&source[span.start..span.end], definition.span.start)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn resolves_names_to_the_closest_definition() {
+        let source = "let x = 1; let f = x => x; let g = f(x); let x = x + 1; x";
+        assert_eq!(
+            uses(source),
+            vec![("x", 19), ("f", 15), ("x", 4), ("x", 4), ("x", 45)]
+        );
+    }
+
+    #[test]
+    fn resolves_functions_types_and_constructors() {
+        let source = "type List<a> = Nil | Cons(a, List<a>);
+let len = l => match l { Nil => 0, Cons(_, rest) => 1 + len(rest) };";
+        let found = uses(source);
+        assert_eq!(
+            found,
+            vec![
+                ("a", 10),
+                ("List", 5),
+                ("a", 10),
+                ("l", 49),
+                ("Nil", 15),
+                ("Cons", 21),
+                ("len", 43),
+                ("rest", 82),
+            ]
+        );
+    }
+
+    #[test]
+    fn lists_the_names_visible_at_an_offset() {
+        let source = "let a = 1; let f = b => { a: b }; let a = 2;";
+        let resolution = resolved(source);
+        let names = |offset| -> Vec<(String, usize)> {
+            resolution
+                .visible_at(offset)
+                .iter()
+                .map(|definition| (definition.name.to_owned(), definition.span.start))
+                .collect()
+        };
+        assert_eq!(
+            names(28),
+            vec![
+                ("a".to_owned(), 4),
+                ("f".to_owned(), 15),
+                ("b".to_owned(), 19)
+            ]
+        );
+        assert_eq!(names(44), vec![("a".to_owned(), 38), ("f".to_owned(), 15)]);
+    }
+}
@@ -0,0 +1,127 @@
+use super::{FileId, Span};
+
+/// A named source text with the offsets its lines start at.
+#[derive(Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub source: String,
+    line_starts: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new(name: &str, source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile {
+            name: name.to_owned(),
+            source: source.to_owned(),
+            line_starts,
+        }
+    }
+
+    /// The one based line and column of the byte at `offset`. Columns count
+    /// characters, not bytes.
+    pub fn line_col(&self, offset: usize) -> (u32, u32) {
+        let offset = offset.min(self.source.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let before = self
+            .source
+            .get(self.line_starts[line]..offset)
+            .map_or(0, |text| text.chars().count());
+        (line as u32 + 1, before as u32 + 1)
+    }
+
+    /// The byte offset the one based `line` starts at.
+    pub fn line_start(&self, line: u32) -> Option<usize> {
+        let index = (line as usize).checked_sub(1)?;
+        self.line_starts.get(index).copied()
+    }
+
+    /// The text of the one based `line`, without its line break.
+    pub fn line(&self, line: u32) -> Option<&str> {
+        let index = (line as usize).checked_sub(1)?;
+        let start = *self.line_starts.get(index)?;
+        let end = self
+            .line_starts
+            .get(index + 1)
+            .copied()
+            .unwrap_or(self.source.len());
+        Some(self.source[start..end].trim_end_matches(['\n', '\r']))
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+}
+
+/// Every file read in a session, so spans can be turned into positions.
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    pub fn add(&mut self, name: &str, source: &str) -> FileId {
+        self.files.push(SourceFile::new(name, source));
+        FileId(self.files.len() as u32 - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0 as usize)
+    }
+
+    /// The one based line and column where `span` starts.
+    pub fn start(&self, span: Span) -> Option<(u32, u32)> {
+        self.file(span.file_id)
+            .map(|file| file.line_col(span.start))
+    }
+
+    /// The one based line and column just past the end of `span`.
+    pub fn end(&self, span: Span) -> Option<(u32, u32)> {
+        self.file(span.file_id).map(|file| file.line_col(span.end))
+    }
+
+    /// The source text `span` covers.
+    pub fn snippet(&self, span: Span) -> Option<&str> {
+        self.file(span.file_id)
+            .and_then(|file| file.source.get(span.start..span.end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_spans_to_lines_and_columns() {
+        let mut map = SourceMap::new();
+        map.add("other.ls", "");
+        let id = map.add("main.ls", "let a = 1;\nlet bc = a;\r\n\nbc");
+        let file = map.file(id).unwrap();
+
+        assert_eq!(file.line_col(0), (1, 1));
+        assert_eq!(file.line_col(10), (1, 11));
+        assert_eq!(file.line_col(11), (2, 1));
+        assert_eq!(file.line_col(15), (2, 5));
+        assert_eq!(file.line_col(100), (4, 3));
+        assert_eq!(file.line(2), Some("let bc = a;"));
+        assert_eq!(file.line(3), Some(""));
+        assert_eq!(file.line(5), None);
+
+        let span = Span::new(id, 15, 17);
+        assert_eq!(map.start(span), Some((2, 5)));
+        assert_eq!(map.end(span), Some((2, 7)));
+        assert_eq!(map.snippet(span), Some("bc"));
+        assert_eq!(map.start(Span::new(FileId(7), 0, 0)), None);
+
+        let file = SourceFile::new("unicode.ls", "x;\nlet π = \"日本\";");
+        assert_eq!(file.line_col(13), (2, 10));
+        assert_eq!(file.line_col(19), (2, 12));
+        assert_eq!(file.line_start(2), Some(3));
+    }
+}
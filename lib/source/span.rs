@@ -0,0 +1,38 @@
+/// Identifies a file registered in a `SourceMap`.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct FileId(pub u32);
+
+/// A range of bytes in one source file, from `start` up to but excluding `end`.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, Default)]
+pub struct Span {
+    pub file_id: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file_id: FileId, start: usize, end: usize) -> Self {
+        Span {
+            file_id,
+            start,
+            end,
+        }
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            file_id: self.file_id,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
@@ -0,0 +1,139 @@
+use super::SyntaxKind;
+use std::rc::Rc;
+
+/// A token and its text, without a position so it can be shared.
+#[derive(Debug, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxKind,
+    text: String,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxKind, text: &str) -> Self {
+        GreenToken {
+            kind,
+            text: text.to_owned(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// An immutable node that knows its length but not its position, so equal
+/// subtrees can be shared between versions of a tree.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct GreenNode {
+    kind: SyntaxKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: SyntaxKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::text_len).sum();
+        GreenNode {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
+    /// The length of the node's text in bytes.
+    pub fn text_len(&self) -> usize {
+        self.len
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GreenElement {
+    Node(Rc<GreenNode>),
+    Token(Rc<GreenToken>),
+}
+
+impl GreenElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            GreenElement::Node(node) => node.kind(),
+            GreenElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn text_len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.text_len(),
+            GreenElement::Token(token) => token.text().len(),
+        }
+    }
+}
+
+/// A position among the children of the node being built, so that a node
+/// can be started there after its first children are known.
+#[derive(Debug, Copy, Clone)]
+pub struct Checkpoint(usize);
+
+/// Builds a green tree from a preorder walk of starts, tokens and finishes.
+#[derive(Debug, Default)]
+pub struct GreenNodeBuilder {
+    // Each open node with the index of its first child in `children`.
+    parents: Vec<(SyntaxKind, usize)>,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNodeBuilder {
+    pub fn new() -> Self {
+        GreenNodeBuilder::default()
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.parents.push((kind, self.children.len()));
+    }
+
+    pub fn token(&mut self, kind: SyntaxKind, text: &str) {
+        self.children
+            .push(GreenElement::Token(Rc::new(GreenToken::new(kind, text))));
+    }
+
+    pub fn finish_node(&mut self) {
+        let (kind, first) = self.parents.pop().expect("No node to finish");
+        let children = self.children.split_off(first);
+        self.children
+            .push(GreenElement::Node(Rc::new(GreenNode::new(kind, children))));
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Starts a node that will hold everything added since `checkpoint`.
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        let Checkpoint(first) = checkpoint;
+        assert!(
+            self.parents.last().is_none_or(|(_, start)| first >= *start),
+            "Checkpoint is outside the open node"
+        );
+        self.parents.push((kind, first));
+    }
+
+    /// The finished root, which must be the only node left.
+    pub fn finish(mut self) -> GreenNode {
+        assert!(self.parents.is_empty(), "Unfinished nodes");
+        match self.children.pop() {
+            Some(GreenElement::Node(node)) if self.children.is_empty() => Rc::unwrap_or_clone(node),
+            _ => panic!("The tree must have a single root node"),
+        }
+    }
+}
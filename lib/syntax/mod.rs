@@ -0,0 +1,10 @@
+mod green;
+mod red;
+mod syntax;
+mod syntax_kind;
+
+pub use green::{Checkpoint, GreenElement, GreenNode, GreenNodeBuilder, GreenToken};
+pub use red::{SyntaxElement, SyntaxNode, SyntaxToken};
+pub(crate) use syntax::TreeSink;
+pub use syntax::{parse, Parse};
+pub use syntax_kind::SyntaxKind;
@@ -0,0 +1,272 @@
+use super::{GreenElement, GreenNode, GreenToken, SyntaxKind};
+use crate::source::{FileId, Span};
+use std::fmt;
+use std::rc::Rc;
+
+/// A view of a green node that knows its parent and where it starts in the
+/// source, built on demand while walking down from the root.
+#[derive(Clone)]
+pub struct SyntaxNode(Rc<NodeData>);
+
+struct NodeData {
+    green: Rc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    file_id: FileId,
+    offset: usize,
+}
+
+/// A token within a `SyntaxNode`, with its position in the source.
+#[derive(Clone)]
+pub struct SyntaxToken {
+    parent: SyntaxNode,
+    green: Rc<GreenToken>,
+    offset: usize,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: GreenNode, file_id: FileId) -> Self {
+        SyntaxNode(Rc::new(NodeData {
+            green: Rc::new(green),
+            parent: None,
+            file_id,
+            offset: 0,
+        }))
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &GreenNode {
+        &self.0.green
+    }
+
+    /// The source the node covers, trivia inside it included.
+    pub fn span(&self) -> Span {
+        Span::new(
+            self.0.file_id,
+            self.0.offset,
+            self.0.offset + self.0.green.text_len(),
+        )
+    }
+
+    pub fn parent(&self) -> Option<SyntaxNode> {
+        self.0.parent.clone()
+    }
+
+    /// The node and then each of its ancestors up to the root.
+    pub fn ancestors(&self) -> impl Iterator<Item = SyntaxNode> {
+        std::iter::successors(Some(self.clone()), SyntaxNode::parent)
+    }
+
+    pub fn children_with_tokens(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.0.offset;
+        self.0
+            .green
+            .children()
+            .iter()
+            .map(|child| {
+                let element = match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Rc::new(NodeData {
+                            green: Rc::clone(green),
+                            parent: Some(self.clone()),
+                            file_id: self.0.file_id,
+                            offset,
+                        })))
+                    }
+                    GreenElement::Token(green) => SyntaxElement::Token(SyntaxToken {
+                        parent: self.clone(),
+                        green: Rc::clone(green),
+                        offset,
+                    }),
+                };
+                offset += child.text_len();
+                element
+            })
+            .collect()
+    }
+
+    pub fn children(&self) -> Vec<SyntaxNode> {
+        self.children_with_tokens()
+            .into_iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect()
+    }
+
+    /// The node and every node below it, in source order.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = vec![self.clone()];
+        for child in self.children() {
+            nodes.extend(child.descendants());
+        }
+        nodes
+    }
+
+    /// Every token below the node, in source order.
+    pub fn tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = vec![];
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+        tokens
+    }
+
+    /// The token covering the byte at `offset`. At the boundary between two
+    /// tokens this is the one that starts there.
+    pub fn token_at_offset(&self, offset: usize) -> Option<SyntaxToken> {
+        for element in self.children_with_tokens() {
+            match element {
+                SyntaxElement::Node(node) => {
+                    let span = node.span();
+                    if span.start <= offset && offset < span.end {
+                        return node.token_at_offset(offset);
+                    }
+                }
+                SyntaxElement::Token(token) => {
+                    let span = token.span();
+                    if span.start <= offset && offset < span.end {
+                        return Some(token);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// The node's source text.
+    pub fn text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl SyntaxToken {
+    pub fn kind(&self) -> SyntaxKind {
+        self.green.kind()
+    }
+
+    pub fn text(&self) -> &str {
+        self.green.text()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(
+            self.parent.0.file_id,
+            self.offset,
+            self.offset + self.green.text().len(),
+        )
+    }
+
+    pub fn parent(&self) -> SyntaxNode {
+        self.parent.clone()
+    }
+}
+
+impl SyntaxElement {
+    pub fn kind(&self) -> SyntaxKind {
+        match self {
+            SyntaxElement::Node(node) => node.kind(),
+            SyntaxElement::Token(token) => token.kind(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span(),
+            SyntaxElement::Token(token) => token.span(),
+        }
+    }
+}
+
+// Two views are the same if they are of the same green element at the same
+// place.
+impl PartialEq for SyntaxNode {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0.green, &other.0.green) && self.span() == other.span()
+    }
+}
+
+impl Eq for SyntaxNode {}
+
+impl PartialEq for SyntaxToken {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.green, &other.green) && self.offset == other.offset
+    }
+}
+
+impl Eq for SyntaxToken {}
+
+fn write_green(f: &mut fmt::Formatter, green: &GreenNode) -> fmt::Result {
+    for child in green.children() {
+        match child {
+            GreenElement::Node(node) => write_green(f, node)?,
+            GreenElement::Token(token) => write!(f, "{}", token.text())?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints the source text exactly as it was parsed.
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_green(f, &self.0.green)
+    }
+}
+
+/// Prints the tree with one element per line, its kind and its byte range.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn write_node(f: &mut fmt::Formatter, node: &SyntaxNode, depth: usize) -> fmt::Result {
+            let span = node.span();
+            writeln!(
+                f,
+                "{0:1$}{2:?}@{3}..{4}",
+                "",
+                depth * 2,
+                node.kind(),
+                span.start,
+                span.end
+            )?;
+            for element in node.children_with_tokens() {
+                match element {
+                    SyntaxElement::Node(child) => write_node(f, &child, depth + 1)?,
+                    SyntaxElement::Token(token) => {
+                        writeln!(f, "{0:1$}{2:?}", "", depth * 2 + 2, token)?
+                    }
+                }
+            }
+            Ok(())
+        }
+        write_node(f, self, 0)
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let span = self.span();
+        let kind = match self.kind() {
+            SyntaxKind::Token(kind) => format!("{:?}", kind),
+            kind => format!("{:?}", kind),
+        };
+        write!(
+            f,
+            "{0}@{1}..{2} {3:?}",
+            kind,
+            span.start,
+            span.end,
+            self.text()
+        )
+    }
+}
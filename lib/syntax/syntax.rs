@@ -0,0 +1,279 @@
+use super::{Checkpoint, GreenNode, GreenNodeBuilder, SyntaxKind, SyntaxNode};
+use crate::diagnostics::{Diagnostic, ToDiagnostics};
+use crate::lexer::{Lexer, Token};
+use crate::parser::Parser;
+use crate::source::FileId;
+
+/// A concrete syntax tree and the errors found while building it. The tree
+/// holds every byte of the source, even where it has errors.
+pub struct Parse {
+    root: SyntaxNode,
+    errors: Vec<Diagnostic>,
+}
+
+impl Parse {
+    pub fn syntax(&self) -> SyntaxNode {
+        self.root.clone()
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+}
+
+/// Parses `source` into a lossless syntax tree. Like the parser that builds
+/// it, this reports the errors the lexer finds instead of any in the grammar.
+pub fn parse(source: &str, file_id: FileId) -> Parse {
+    let mut parser = Parser::lossless(Lexer::with_file(source.as_bytes(), file_id), source);
+    let errors = match parser.parse_program() {
+        Ok(_) => vec![],
+        Err(err) => err.to_diagnostics(),
+    };
+    let green = parser.into_tree().expect("A lossless parser builds a tree");
+
+    Parse {
+        root: SyntaxNode::new_root(green, file_id),
+        errors,
+    }
+}
+
+/// Receives the tokens and trivia of the source in order as the parser reads
+/// them, along with the nodes they make up.
+pub(crate) struct TreeSink<'a> {
+    source: &'a str,
+    builder: GreenNodeBuilder,
+    // Where the last token added ends, and how many nodes are open.
+    end: usize,
+    open: usize,
+}
+
+impl<'a> TreeSink<'a> {
+    pub fn new(source: &'a str) -> Self {
+        let mut builder = GreenNodeBuilder::new();
+        builder.start_node(SyntaxKind::Root);
+        TreeSink {
+            source,
+            builder,
+            end: 0,
+            open: 0,
+        }
+    }
+
+    pub fn token(&mut self, token: &Token) {
+        self.skipped(token.span.start);
+        let text = &self.source[token.span.start..token.span.end];
+        self.builder.token(token.kind.into(), text);
+        self.end = token.span.end;
+    }
+
+    // Text the lexer skipped over with an error is kept as it is.
+    fn skipped(&mut self, until: usize) {
+        if until > self.end {
+            self.builder.start_node(SyntaxKind::Error);
+            self.builder
+                .token(SyntaxKind::Unknown, &self.source[self.end..until]);
+            self.builder.finish_node();
+            self.end = until;
+        }
+    }
+
+    pub fn start_node(&mut self, kind: SyntaxKind) {
+        self.builder.start_node(kind);
+        self.open += 1;
+    }
+
+    pub fn start_node_at(&mut self, checkpoint: Checkpoint, kind: SyntaxKind) {
+        self.builder.start_node_at(checkpoint, kind);
+        self.open += 1;
+    }
+
+    pub fn finish_node(&mut self) {
+        self.builder.finish_node();
+        self.open -= 1;
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        self.builder.checkpoint()
+    }
+
+    /// How many nodes are open below the root.
+    pub fn open_nodes(&self) -> usize {
+        self.open
+    }
+
+    pub fn finish(mut self) -> GreenNode {
+        self.skipped(self.source.len());
+        for _ in 0..self.open {
+            self.builder.finish_node();
+        }
+        self.builder.finish_node();
+        self.builder.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::ErrorCode;
+
+    const PROGRAM: &str = "import { map } from \"./list\"; // helpers
+/// A shape.
+type Size = { w: Num, h: Num };
+export type Shape<a> = | Circle(Num) | Rect(Size, (a, a) => a);
+let area = (s) => match s {
+    Circle(r) => 3.14 * r * r,
+    Rect({ w, h: height }, _) => w * height, /* both */
+};
+let n = -0x1F + 2e3;
+if !(n >= 1) { print `n is ${ { v: n }.v }`; } else if n == 0 { print 'zero'; } else { n; }
+f(1, (2, 3),)";
+
+    fn round_trip(source: &str) {
+        let root = parse(source, FileId(1)).syntax();
+        assert_eq!(root.to_string(), source);
+        for token in root.tokens() {
+            let span = token.span();
+            assert_eq!(&source[span.start..span.end], token.text());
+        }
+    }
+
+    #[test]
+    fn round_trips_programs_with_and_without_errors() {
+        round_trip(PROGRAM);
+        for (index, _) in PROGRAM.char_indices() {
+            round_trip(&PROGRAM[..index]);
+        }
+        for source in [
+            "",
+            "  // only trivia\n",
+            "let = 1;\nlet y = (2;\nif y { let z = ; print z; }",
+            "let a = 1 # 2; let é = \"unterminated",
+            "}}) ] let",
+            "`a${ b `c${",
+            "/* open",
+            "let x = \"bad \\q\"; match x { 1 => , }",
+            "type = |; export 1; import { from;",
+        ] {
+            round_trip(source);
+        }
+    }
+
+    #[test]
+    fn builds_the_program_and_its_tree_in_one_pass() {
+        for source in [
+            PROGRAM,
+            include_str!("../../tests/corpus/data.ls"),
+            include_str!("../../tests/corpus/patterns.ls"),
+            include_str!("../../tests/corpus/closures.ls"),
+            include_str!("../../tests/corpus/modules.ls"),
+            include_str!("../../tests/corpus/strings.ls"),
+        ] {
+            let mut parser = Parser::lossless(Lexer::new(source.as_bytes()), source);
+            let program = parser.parse_program().unwrap();
+            let root = SyntaxNode::new_root(parser.into_tree().unwrap(), FileId(0));
+            assert_eq!(root.to_string(), source);
+            assert_eq!(root.children().len(), program.statements.len());
+        }
+    }
+
+    #[test]
+    fn builds_nodes_around_tokens_and_trivia() {
+        let tree = parse("/// Doc.\nlet a = 1 + f(x); // c\n", FileId(0)).syntax();
+        assert_eq!(
+            format!("{:?}", tree),
+            r#"Root@0..32
+  LetStatement@0..26
+    DocComment@0..8 "/// Doc."
+    Whitespace@8..9 "\n"
+    Let@9..12 "let"
+    Whitespace@12..13 " "
+    Identifier@13..14 "a"
+    Whitespace@14..15 " "
+    Equal@15..16 "="
+    Whitespace@16..17 " "
+    BinaryExpr@17..25
+      LiteralExpr@17..18
+        Num@17..18 "1"
+      Whitespace@18..19 " "
+      Plus@19..20 "+"
+      Whitespace@20..21 " "
+      CallExpr@21..25
+        NameExpr@21..22
+          Identifier@21..22 "f"
+        ArgList@22..25
+          LeftParen@22..23 "("
+          NameExpr@23..24
+            Identifier@23..24 "x"
+          RightParen@24..25 ")"
+    Semicolon@25..26 ";"
+  Whitespace@26..27 " "
+  Comment@27..31 "// c"
+  Whitespace@31..32 "\n"
+"#
+        );
+    }
+
+    #[test]
+    fn reports_errors_and_navigates_the_tree() {
+        let parse = parse("let = 1;\nlet b = (2;\nprint b;", FileId(0));
+        let errors: Vec<(Option<ErrorCode>, &str)> = parse
+            .errors()
+            .iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    Some(ErrorCode::ExpectedName),
+                    "Expect variable name after 'let'"
+                ),
+                (
+                    Some(ErrorCode::ExpectedToken),
+                    "Expect ')' after expression"
+                ),
+            ]
+        );
+
+        let root = parse.syntax();
+        let token = root.token_at_offset(18).unwrap();
+        assert_eq!(token.text(), "2");
+        let kinds: Vec<SyntaxKind> = token.parent().ancestors().map(|node| node.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::LiteralExpr,
+                SyntaxKind::LetStatement,
+                SyntaxKind::Root,
+            ]
+        );
+        // The tokens skipped to recover stay in the statement.
+        let token = root.token_at_offset(19).unwrap();
+        assert_eq!(token.text(), ";");
+        let kinds: Vec<SyntaxKind> = token.parent().ancestors().map(|node| node.kind()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                SyntaxKind::Error,
+                SyntaxKind::LetStatement,
+                SyntaxKind::Root
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_what_the_lexer_cannot_read() {
+        let parse = parse("print b # 3;", FileId(0));
+        let errors: Vec<&str> = parse
+            .errors()
+            .iter()
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect();
+        assert_eq!(errors, ["Error parsing unknown character #"]);
+        assert!(parse
+            .syntax()
+            .descendants()
+            .iter()
+            .any(|node| node.kind() == SyntaxKind::Error && node.text() == "#"));
+    }
+}
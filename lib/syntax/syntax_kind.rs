@@ -0,0 +1,80 @@
+use crate::lexer::TokenKind;
+
+/// The kind of a node or token in the concrete syntax tree.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum SyntaxKind {
+    /// A token the lexer produced.
+    Token(TokenKind),
+    /// Source text the lexer could not read as a token.
+    Unknown,
+
+    Root,
+    /// Tokens that do not fit the grammar where they appear.
+    Error,
+
+    // Statements.
+    LetStatement,
+    TypeStatement,
+    ImportStatement,
+    ExportStatement,
+    PrintStatement,
+    IfStatement,
+    ExpressionStatement,
+    Block,
+
+    // Type declarations and expressions.
+    TypeParams,
+    SumType,
+    Variant,
+    RecordType,
+    Field,
+    NamedType,
+    TypeArgs,
+    TupleType,
+    ParenType,
+    FunctionType,
+
+    // Expressions.
+    LambdaExpr,
+    ParamList,
+    BinaryExpr,
+    UnaryExpr,
+    CallExpr,
+    ArgList,
+    GetExpr,
+    LiteralExpr,
+    TemplateExpr,
+    NameExpr,
+    ConstructExpr,
+    RecordExpr,
+    RecordField,
+    ParenExpr,
+    TupleExpr,
+    MatchExpr,
+    MatchArm,
+
+    // Patterns.
+    LiteralPattern,
+    BindingPattern,
+    ConstructorPattern,
+    RecordPattern,
+    RecordPatternField,
+    TuplePattern,
+    ParenPattern,
+}
+
+impl SyntaxKind {
+    /// Whitespace and comments, including doc comments.
+    pub fn is_trivia(self) -> bool {
+        match self {
+            SyntaxKind::Token(kind) => kind.is_trivia() || kind == TokenKind::DocComment,
+            _ => false,
+        }
+    }
+}
+
+impl From<TokenKind> for SyntaxKind {
+    fn from(kind: TokenKind) -> Self {
+        SyntaxKind::Token(kind)
+    }
+}
@@ -0,0 +1,44 @@
+use super::{Type, TypeVar};
+
+/// What a declared type name stands for.
+#[derive(Debug, Clone)]
+pub enum TypeKind {
+    Builtin,
+    Alias(Type),
+    Sum(Vec<String>),
+    Record(Vec<(String, Type)>),
+}
+
+#[derive(Debug, Clone)]
+pub struct TypeDeclaration {
+    pub name: String,
+    pub params: Vec<TypeVar>,
+    pub kind: TypeKind,
+}
+
+impl TypeDeclaration {
+    pub fn builtin(name: &str) -> Self {
+        TypeDeclaration {
+            name: name.to_owned(),
+            params: vec![],
+            kind: TypeKind::Builtin,
+        }
+    }
+
+    /// The declared type applied to its own parameters, e.g. `Option<a>`.
+    pub fn ty(&self) -> Type {
+        Type::Con {
+            name: self.name.to_owned(),
+            args: self.params.iter().map(|var| Type::Var(*var)).collect(),
+        }
+    }
+}
+
+/// A constructor of a sum type, e.g. `Some` in `type Option<a> = Some(a) | None`.
+#[derive(Debug, Clone)]
+pub struct Constructor {
+    pub name: String,
+    pub type_name: String,
+    pub params: Vec<TypeVar>,
+    pub fields: Vec<Type>,
+}
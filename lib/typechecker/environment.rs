@@ -0,0 +1,51 @@
+use super::{Scheme, TypeVar};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Clone)]
+pub struct TypeEnvironment {
+    scopes: Vec<HashMap<String, Scheme>>,
+}
+
+impl Default for TypeEnvironment {
+    fn default() -> Self {
+        TypeEnvironment {
+            scopes: vec![HashMap::new()],
+        }
+    }
+}
+
+impl TypeEnvironment {
+    pub fn define(&mut self, name: &str, scheme: Scheme) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), scheme);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Scheme> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    pub fn schemes(&self) -> impl Iterator<Item = &Scheme> {
+        self.scopes.iter().flat_map(|scope| scope.values())
+    }
+
+    pub fn schemes_mut(&mut self) -> impl Iterator<Item = &mut Scheme> {
+        self.scopes.iter_mut().flat_map(|scope| scope.values_mut())
+    }
+
+    pub fn free_type_vars(&self) -> BTreeSet<TypeVar> {
+        self.schemes()
+            .flat_map(|scheme| scheme.free_type_vars())
+            .collect()
+    }
+}
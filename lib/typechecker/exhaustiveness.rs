@@ -0,0 +1,235 @@
+use super::{Constructor, Type, TypeDeclaration, TypeKind, TUPLE};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Identifies the constructor a pattern matches on.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CtorId {
+    Variant(String),
+    Record(String, Vec<String>),
+    Tuple(usize),
+    Bool(bool),
+    Literal(String),
+}
+
+/// A pattern reduced to what matters for exhaustiveness: bindings and
+/// wildcards both match anything.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Pat {
+    Wild,
+    Ctor(CtorId, Vec<Pat>),
+}
+
+fn join(pats: &[Pat]) -> String {
+    pats.iter()
+        .map(|pat| pat.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+impl fmt::Display for Pat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pat::Wild => write!(f, "_"),
+            Pat::Ctor(CtorId::Variant(name), args) if args.is_empty() => write!(f, "{}", name),
+            Pat::Ctor(CtorId::Variant(name), args) => write!(f, "{0}({1})", name, join(args)),
+            Pat::Ctor(CtorId::Record(_, names), args) => {
+                let fields: Vec<String> = names
+                    .iter()
+                    .zip(args)
+                    .map(|(name, arg)| format!("{0}: {1}", name, arg))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+            Pat::Ctor(CtorId::Tuple(_), args) => write!(f, "({})", join(args)),
+            Pat::Ctor(CtorId::Bool(b), _) => write!(f, "{}", b),
+            Pat::Ctor(CtorId::Literal(lit), _) => write!(f, "{}", lit),
+        }
+    }
+}
+
+/// Decides whether match arms are useful, following Maranget's
+/// "Warnings for pattern matching".
+pub struct Exhaustiveness<'a> {
+    types: &'a HashMap<String, TypeDeclaration>,
+    constructors: &'a HashMap<String, Constructor>,
+}
+
+impl<'a> Exhaustiveness<'a> {
+    pub fn new(
+        types: &'a HashMap<String, TypeDeclaration>,
+        constructors: &'a HashMap<String, Constructor>,
+    ) -> Self {
+        Exhaustiveness {
+            types,
+            constructors,
+        }
+    }
+
+    /// Returns a value matched by `v` but by none of `rows`, if there is one.
+    pub fn useful(&self, rows: &[Vec<Pat>], v: &[Pat], tys: &[Type]) -> Option<Vec<Pat>> {
+        if v.is_empty() {
+            return if rows.is_empty() { Some(vec![]) } else { None };
+        }
+
+        match &v[0] {
+            Pat::Ctor(ctor, args) => {
+                let fields = self.field_types(ctor, &tys[0], args.len());
+                self.useful_specialized(rows, ctor, args.to_owned(), fields, v, tys)
+            }
+            Pat::Wild => {
+                let heads: Vec<&CtorId> = rows
+                    .iter()
+                    .filter_map(|row| match &row[0] {
+                        Pat::Ctor(ctor, _) => Some(ctor),
+                        Pat::Wild => None,
+                    })
+                    .collect();
+                let signature = self.signature(&tys[0]);
+
+                if let Some(signature) = &signature {
+                    if signature.iter().all(|(ctor, _)| heads.contains(&ctor)) {
+                        return signature.iter().find_map(|(ctor, fields)| {
+                            let args = vec![Pat::Wild; fields.len()];
+                            self.useful_specialized(rows, ctor, args, fields.to_owned(), v, tys)
+                        });
+                    }
+                }
+
+                let default: Vec<Vec<Pat>> = rows
+                    .iter()
+                    .filter(|row| row[0] == Pat::Wild)
+                    .map(|row| row[1..].to_vec())
+                    .collect();
+                let witness = self.useful(&default, &v[1..], &tys[1..])?;
+
+                let missing = signature.and_then(|signature| {
+                    signature
+                        .into_iter()
+                        .find(|(ctor, _)| !heads.contains(&ctor))
+                        .map(|(ctor, fields)| Pat::Ctor(ctor, vec![Pat::Wild; fields.len()]))
+                });
+                let mut result = vec![missing.unwrap_or(Pat::Wild)];
+                result.extend(witness);
+                Some(result)
+            }
+        }
+    }
+
+    fn useful_specialized(
+        &self,
+        rows: &[Vec<Pat>],
+        ctor: &CtorId,
+        args: Vec<Pat>,
+        fields: Vec<Type>,
+        v: &[Pat],
+        tys: &[Type],
+    ) -> Option<Vec<Pat>> {
+        let arity = args.len();
+        let rows = specialize(rows, ctor, arity);
+
+        let mut v = [args, v[1..].to_vec()].concat();
+        let tys = [fields, tys[1..].to_vec()].concat();
+        let witness = self.useful(&rows, &v, &tys)?;
+
+        v = witness;
+        let rest = v.split_off(arity);
+        let mut result = vec![Pat::Ctor(ctor.to_owned(), v)];
+        result.extend(rest);
+        Some(result)
+    }
+
+    // Every constructor of a type with its field types, or None when the type
+    // has too many values to list, like numbers and strings.
+    fn signature(&self, ty: &Type) -> Option<Vec<(CtorId, Vec<Type>)>> {
+        let (name, args) = match ty {
+            Type::Con { name, args } => (name, args),
+            _ => return None,
+        };
+
+        if name == "Bool" {
+            return Some(vec![
+                (CtorId::Bool(true), vec![]),
+                (CtorId::Bool(false), vec![]),
+            ]);
+        }
+        if name == TUPLE {
+            return Some(vec![(CtorId::Tuple(args.len()), args.to_owned())]);
+        }
+
+        match &self.types.get(name)?.kind {
+            TypeKind::Sum(variants) => Some(
+                variants
+                    .iter()
+                    .map(|variant| {
+                        let ctor = CtorId::Variant(variant.to_owned());
+                        let fields = self.field_types(&ctor, ty, 0);
+                        (ctor, fields)
+                    })
+                    .collect(),
+            ),
+            TypeKind::Record(fields) => {
+                let ctor = CtorId::Record(
+                    name.to_owned(),
+                    fields.iter().map(|(field, _)| field.to_owned()).collect(),
+                );
+                let fields = self.field_types(&ctor, ty, 0);
+                Some(vec![(ctor, fields)])
+            }
+            _ => None,
+        }
+    }
+
+    fn field_types(&self, ctor: &CtorId, ty: &Type, arity: usize) -> Vec<Type> {
+        let args = match ty {
+            Type::Con { args, .. } => args.to_owned(),
+            _ => vec![],
+        };
+
+        let fields = match ctor {
+            CtorId::Variant(name) => self.constructors.get(name).map(|constructor| {
+                let mapping = constructor.params.iter().copied().zip(args).collect();
+                constructor
+                    .fields
+                    .iter()
+                    .map(|field| field.substitute(&mapping))
+                    .collect()
+            }),
+            CtorId::Record(name, _) => {
+                self.types
+                    .get(name)
+                    .and_then(|declaration| match &declaration.kind {
+                        TypeKind::Record(fields) => {
+                            let mapping = declaration.params.iter().copied().zip(args).collect();
+                            Some(
+                                fields
+                                    .iter()
+                                    .map(|(_, field)| field.substitute(&mapping))
+                                    .collect(),
+                            )
+                        }
+                        _ => None,
+                    })
+            }
+            CtorId::Tuple(_) if !args.is_empty() => Some(args),
+            _ => None,
+        };
+
+        // Unknown field types only have to be placeholders, as nothing can be
+        // listed about them.
+        fields.unwrap_or_else(|| vec![Type::Var(0); arity])
+    }
+}
+
+fn specialize(rows: &[Vec<Pat>], ctor: &CtorId, arity: usize) -> Vec<Vec<Pat>> {
+    rows.iter()
+        .filter_map(|row| {
+            let head = match &row[0] {
+                Pat::Ctor(other, args) if other == ctor => args.to_owned(),
+                Pat::Ctor(_, _) => return None,
+                Pat::Wild => vec![Pat::Wild; arity],
+            };
+            Some([head, row[1..].to_vec()].concat())
+        })
+        .collect()
+}
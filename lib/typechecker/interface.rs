@@ -0,0 +1,9 @@
+use super::{Constructor, Scheme, TypeDeclaration};
+use std::collections::HashMap;
+
+/// The types of everything a module exports.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleInterface {
+    pub values: HashMap<String, Scheme>,
+    pub types: HashMap<String, (TypeDeclaration, Vec<Constructor>)>,
+}
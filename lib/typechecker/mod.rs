@@ -0,0 +1,14 @@
+mod declarations;
+mod environment;
+mod exhaustiveness;
+mod interface;
+mod type_error;
+mod typechecker;
+mod types;
+
+pub use declarations::{Constructor, TypeDeclaration, TypeKind};
+pub use environment::TypeEnvironment;
+pub use interface::ModuleInterface;
+pub use type_error::{Details, TypeCheckerError, TypeError};
+pub use typechecker::{Checkpoint, TypeChecker};
+pub use types::{Scheme, Type, TypeVar, TUPLE};
@@ -0,0 +1,140 @@
+use crate::diagnostics::{Diagnostic, ErrorCode, Label, ToDiagnostics};
+use crate::lexer::Token;
+use crate::source::Span;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+#[derive(Debug, PartialEq)]
+pub struct TypeError {
+    pub code: ErrorCode,
+    pub token: Token,
+    pub text: String,
+    pub details: Box<Details>,
+}
+
+/// Where exactly a type error is, with related code, notes and help that
+/// explain it.
+#[derive(Debug, PartialEq, Default)]
+pub struct Details {
+    /// The code at fault, the token's own span unless narrowed or widened.
+    pub span: Span,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub help: Vec<String>,
+}
+
+impl TypeError {
+    pub fn new(code: ErrorCode, token: &Token, text: String) -> Self {
+        TypeError {
+            code,
+            token: token.to_owned(),
+            text,
+            details: Box::new(Details {
+                span: token.span,
+                ..Details::default()
+            }),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.details.span = span;
+        self
+    }
+
+    /// Points at related code that explains the error.
+    pub fn with_label(mut self, span: Span, message: String) -> Self {
+        self.details.labels.push(Label::secondary(span, message));
+        self
+    }
+
+    pub fn with_note(mut self, note: String) -> Self {
+        self.details.notes.push(note);
+        self
+    }
+
+    pub fn with_help(mut self, help: String) -> Self {
+        self.details.help.push(help);
+        self
+    }
+
+    pub fn line(&self) -> u32 {
+        self.token.line
+    }
+
+    pub fn col(&self) -> u32 {
+        self.token.col
+    }
+}
+
+impl Display for TypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Type error at \"{0}\", line {1}, column {2}: {3}",
+            self.token,
+            self.line(),
+            self.col(),
+            self.text
+        )
+    }
+}
+
+impl Error for TypeError {}
+
+impl ToDiagnostics for TypeError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostic = Diagnostic::error(&self.text)
+            .with_code(self.code)
+            .with_label(Label::primary(self.details.span, ""))
+            .with_labels(self.details.labels.to_owned());
+        diagnostic.notes = self.details.notes.to_owned();
+        diagnostic.help = self.details.help.to_owned();
+        vec![diagnostic]
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TypeCheckerError {
+    errors: Vec<TypeError>,
+}
+
+impl TypeCheckerError {
+    pub fn new() -> Self {
+        TypeCheckerError { errors: vec![] }
+    }
+
+    pub fn add(&mut self, err: TypeError) {
+        self.errors.push(err);
+    }
+
+    pub fn errors(&self) -> &[TypeError] {
+        &self.errors
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl Display for TypeCheckerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut err_strings: Vec<String> = vec![];
+        for err in &self.errors {
+            err_strings.push(format!("{}", err));
+        }
+        write!(f, "Error checking types: \n{}", err_strings.join("\n"))
+    }
+}
+
+impl Error for TypeCheckerError {}
+
+impl ToDiagnostics for TypeCheckerError {
+    fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.errors
+            .iter()
+            .flat_map(|err| err.to_diagnostics())
+            .collect()
+    }
+}
@@ -0,0 +1,1743 @@
+use super::exhaustiveness::{CtorId, Exhaustiveness, Pat};
+use super::{
+    Constructor, ModuleInterface, Scheme, Type, TypeCheckerError, TypeDeclaration, TypeEnvironment,
+    TypeError, TypeKind, TypeVar,
+};
+use crate::diagnostics::ErrorCode;
+use crate::lexer::{Token, TokenKind};
+use crate::parser::{
+    Expression, MatchArm, Pattern, Program, Statement, TypeDefinition, TypeExpression,
+};
+use crate::source::Span;
+use std::collections::{HashMap, HashSet};
+
+enum UnifyError {
+    Mismatch,
+    Infinite(TypeVar, Type),
+}
+
+/// Infers types with Algorithm W, keeping the substitution found so far
+/// instead of threading it through every call.
+pub struct TypeChecker {
+    environment: TypeEnvironment,
+    substitution: HashMap<TypeVar, Type>,
+    next_var: TypeVar,
+    types: HashMap<String, TypeDeclaration>,
+    constructors: HashMap<String, Constructor>,
+    field_owners: HashMap<String, String>,
+    modules: HashMap<String, ModuleInterface>,
+    // The types and constructors declared or imported by the program being
+    // checked. Declaring one again is an error, while one left by an earlier
+    // program, as in the REPL, is shadowed.
+    declared_types: HashSet<String>,
+    declared_constructors: HashSet<String>,
+    // The type found for each expression and binding, by its source, when
+    // `recording`.
+    recording: bool,
+    recorded: Vec<(Span, Type)>,
+}
+
+/// What a `TypeChecker` knows about the programs checked so far.
+pub struct Checkpoint {
+    environment: TypeEnvironment,
+    types: HashMap<String, TypeDeclaration>,
+    constructors: HashMap<String, Constructor>,
+    field_owners: HashMap<String, String>,
+    modules: HashMap<String, ModuleInterface>,
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        let types = ["Num", "Str", "Bool"]
+            .iter()
+            .map(|name| (name.to_string(), TypeDeclaration::builtin(name)))
+            .collect();
+
+        TypeChecker {
+            environment: TypeEnvironment::default(),
+            substitution: HashMap::new(),
+            next_var: 0,
+            types,
+            constructors: HashMap::new(),
+            field_owners: HashMap::new(),
+            modules: HashMap::new(),
+            declared_types: HashSet::new(),
+            declared_constructors: HashSet::new(),
+            recording: false,
+            recorded: vec![],
+        }
+    }
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        TypeChecker::default()
+    }
+
+    /// A checker that records the type of every expression and binding.
+    pub fn recording() -> Self {
+        TypeChecker {
+            recording: true,
+            ..TypeChecker::default()
+        }
+    }
+
+    /// Saves the names and types declared so far, to `restore` them when a
+    /// later program fails.
+    pub fn checkpoint(&mut self) -> Checkpoint {
+        // The substitution is what a single program found, so settle the
+        // environment and start the next program without it.
+        self.map_environment(|ty| ty.to_owned());
+        self.recorded = self.recorded_types();
+        self.substitution.clear();
+        Checkpoint {
+            environment: self.environment.to_owned(),
+            types: self.types.to_owned(),
+            constructors: self.constructors.to_owned(),
+            field_owners: self.field_owners.to_owned(),
+            modules: self.modules.to_owned(),
+        }
+    }
+
+    /// Forgets what was declared since `checkpoint`.
+    pub fn restore(&mut self, checkpoint: Checkpoint) {
+        self.environment = checkpoint.environment;
+        self.types = checkpoint.types;
+        self.constructors = checkpoint.constructors;
+        self.field_owners = checkpoint.field_owners;
+        self.modules = checkpoint.modules;
+        self.substitution.clear();
+    }
+
+    /// Checks every statement, reporting all type errors found.
+    pub fn check_program(&mut self, program: &Program) -> Result<(), TypeCheckerError> {
+        let mut errors = TypeCheckerError::new();
+        self.declared_types.clear();
+        self.declared_constructors.clear();
+        self.check_all(&program.statements, &mut errors);
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(())
+    }
+
+    /// Infers the type of an expression in the current environment.
+    pub fn infer(&mut self, expr: &Expression) -> Result<Type, TypeError> {
+        let ty = self.infer_expression(expr)?;
+        Ok(self.apply(&ty))
+    }
+
+    /// Looks up the type scheme bound to `name`, if any.
+    pub fn lookup(&self, name: &str) -> Option<Scheme> {
+        self.environment.get(name).map(|scheme| Scheme {
+            vars: scheme.vars.to_owned(),
+            ty: self.apply(&scheme.ty),
+        })
+    }
+
+    /// The type inferred for every expression and binding checked so far,
+    /// by the span of its source, if the checker is `recording`.
+    pub fn recorded_types(&self) -> Vec<(Span, Type)> {
+        self.recorded
+            .iter()
+            .map(|(span, ty)| (*span, self.apply(ty)))
+            .collect()
+    }
+
+    /// Makes a module's interface available to `import` statements naming `path`.
+    pub fn provide_module(&mut self, path: &str, interface: ModuleInterface) {
+        self.modules.insert(path.to_owned(), interface);
+    }
+
+    /// Collects the types of the bindings exported by an already checked program.
+    pub fn exports(&self, program: &Program) -> ModuleInterface {
+        let mut interface = ModuleInterface::default();
+
+        for statement in &program.statements {
+            let statement = match statement {
+                Statement::Export { statement } => statement.as_ref(),
+                _ => continue,
+            };
+
+            match statement {
+                Statement::Let { name, .. } => {
+                    if let Some(scheme) = self.lookup(&name.to_string()) {
+                        interface.values.insert(name.to_string(), scheme);
+                    }
+                }
+                Statement::Type { name, .. } => {
+                    if let Some(declaration) = self.types.get(&name.to_string()) {
+                        let constructors = self
+                            .constructors
+                            .values()
+                            .filter(|constructor| constructor.type_name == declaration.name)
+                            .cloned()
+                            .collect();
+                        interface
+                            .types
+                            .insert(name.to_string(), (declaration.to_owned(), constructors));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        interface
+    }
+
+    fn check_all(&mut self, statements: &[Statement], errors: &mut TypeCheckerError) {
+        for statement in statements {
+            if let Err(err) = self.check_statement(statement, errors) {
+                errors.add(err);
+            }
+        }
+    }
+
+    fn check_block(&mut self, statements: &[Statement], errors: &mut TypeCheckerError) {
+        self.environment.push_scope();
+        self.check_all(statements, errors);
+        self.environment.pop_scope();
+    }
+
+    fn check_statement(
+        &mut self,
+        statement: &Statement,
+        errors: &mut TypeCheckerError,
+    ) -> Result<(), TypeError> {
+        match statement {
+            Statement::Let {
+                name, initializer, ..
+            } => {
+                let inferred = match initializer.as_ref() {
+                    Expression::Lambda { .. } => self.infer_recursive(name, initializer),
+                    _ => self.infer_expression(initializer),
+                };
+                let scheme = match inferred {
+                    Ok(ty) => {
+                        self.record(name.span, ty.to_owned());
+                        self.generalize(&ty)
+                    }
+                    Err(err) => {
+                        // Keep checking later uses of the name without cascading errors.
+                        let fresh = self.fresh();
+                        self.environment
+                            .define(&name.to_string(), Scheme::monomorphic(fresh));
+                        return Err(err);
+                    }
+                };
+                self.environment.define(&name.to_string(), scheme);
+            }
+            Statement::Expression { expr } | Statement::Print { expr } => {
+                self.infer_expression(expr)?;
+            }
+            Statement::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let result = self
+                    .infer_expression(condition)
+                    .and_then(|ty| self.unify_expression(&Type::bool(), &ty, condition));
+                if let Err(err) = result {
+                    errors.add(err);
+                }
+                self.check_block(then_branch, errors);
+                if let Some(else_branch) = else_branch {
+                    self.check_block(else_branch, errors);
+                }
+            }
+            Statement::Type {
+                name,
+                params,
+                definition,
+                ..
+            } => self.declare_type(name, params, definition)?,
+            Statement::Import { names, path } => self.import(names, path)?,
+            Statement::Export { statement } => self.check_statement(statement, errors)?,
+        }
+
+        Ok(())
+    }
+
+    fn import(&mut self, names: &[Token], path: &Token) -> Result<(), TypeError> {
+        let interface = match self.modules.get(&path.to_string()) {
+            Some(interface) => interface.to_owned(),
+            None => {
+                return Err(TypeError::new(
+                    ErrorCode::UnresolvedImport,
+                    path,
+                    format!("Unresolved module {}", path),
+                ))
+            }
+        };
+
+        for name in names {
+            if let Some(scheme) = interface.values.get(&name.to_string()) {
+                let scheme = self.import_scheme(scheme);
+                self.environment.define(&name.to_string(), scheme);
+            } else if let Some((declaration, constructors)) = interface.types.get(&name.to_string())
+            {
+                self.import_type(name, declaration, constructors)?;
+            } else {
+                return Err(TypeError::new(
+                    ErrorCode::UnresolvedImport,
+                    name,
+                    format!("Module {0} does not export {1}", path, name),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Type variables of other modules were numbered by another checker, so
+    // they are renamed to avoid clashing with ours.
+    fn rename_vars(&mut self, vars: impl IntoIterator<Item = TypeVar>) -> HashMap<TypeVar, Type> {
+        vars.into_iter().map(|var| (var, self.fresh())).collect()
+    }
+
+    fn renamed(mapping: &HashMap<TypeVar, Type>, vars: &[TypeVar]) -> Vec<TypeVar> {
+        vars.iter()
+            .filter_map(|var| match mapping.get(var) {
+                Some(Type::Var(renamed)) => Some(*renamed),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn import_scheme(&mut self, scheme: &Scheme) -> Scheme {
+        let mapping = self.rename_vars(scheme.ty.free_type_vars());
+        Scheme {
+            vars: TypeChecker::renamed(&mapping, &scheme.vars),
+            ty: scheme.ty.substitute(&mapping),
+        }
+    }
+
+    fn import_type(
+        &mut self,
+        name: &Token,
+        declaration: &TypeDeclaration,
+        constructors: &[Constructor],
+    ) -> Result<(), TypeError> {
+        self.claim_type(name)?;
+        for constructor in constructors {
+            self.claim_constructor(name, &constructor.name)?;
+        }
+        let mapping = self.rename_vars(declaration.params.to_owned());
+        let params = TypeChecker::renamed(&mapping, &declaration.params);
+
+        let kind = match &declaration.kind {
+            TypeKind::Alias(target) => TypeKind::Alias(target.substitute(&mapping)),
+            TypeKind::Record(fields) => {
+                for (field, _) in fields {
+                    self.claim_field(name, field, &declaration.name)?;
+                }
+                TypeKind::Record(
+                    fields
+                        .iter()
+                        .map(|(field, ty)| (field.to_owned(), ty.substitute(&mapping)))
+                        .collect(),
+                )
+            }
+            kind => kind.to_owned(),
+        };
+
+        for constructor in constructors {
+            self.constructors.insert(
+                constructor.name.to_owned(),
+                Constructor {
+                    name: constructor.name.to_owned(),
+                    type_name: constructor.type_name.to_owned(),
+                    params: params.to_owned(),
+                    fields: constructor
+                        .fields
+                        .iter()
+                        .map(|field| field.substitute(&mapping))
+                        .collect(),
+                },
+            );
+        }
+
+        self.types.insert(
+            declaration.name.to_owned(),
+            TypeDeclaration {
+                name: declaration.name.to_owned(),
+                params,
+                kind,
+            },
+        );
+        Ok(())
+    }
+
+    // Makes way for a new type called `name`, which must not be builtin or
+    // already declared by this program.
+    fn claim_type(&mut self, name: &Token) -> Result<(), TypeError> {
+        let type_name = name.to_string();
+        let message = match self
+            .types
+            .get(&type_name)
+            .map(|declaration| &declaration.kind)
+        {
+            Some(TypeKind::Builtin) => format!("Cannot redeclare the builtin type {}", name),
+            _ if self.declared_types.contains(&type_name) => {
+                format!("Type {} is already declared", name)
+            }
+            Some(_) => {
+                self.shadow_type(&type_name);
+                self.declared_types.insert(type_name);
+                return Ok(());
+            }
+            None => {
+                self.declared_types.insert(type_name);
+                return Ok(());
+            }
+        };
+        Err(TypeError::new(ErrorCode::DuplicateType, name, message))
+    }
+
+    fn claim_constructor(&mut self, token: &Token, name: &str) -> Result<(), TypeError> {
+        if !self.declared_constructors.insert(name.to_owned()) {
+            return Err(TypeError::new(
+                ErrorCode::DuplicateConstructor,
+                token,
+                format!("Constructor {} is already declared", name),
+            ));
+        }
+        Ok(())
+    }
+
+    // Makes `field` a field of the record type `owner`, unless another record
+    // type of this program has it.
+    fn claim_field(&mut self, token: &Token, field: &str, owner: &str) -> Result<(), TypeError> {
+        if let Some(other) = self.field_owners.get(field) {
+            if other != owner && self.declared_types.contains(other) {
+                return Err(TypeError::new(
+                    ErrorCode::AmbiguousField,
+                    token,
+                    format!("Field {0} already belongs to record {1}", field, other),
+                ));
+            }
+        }
+        self.field_owners.insert(field.to_owned(), owner.to_owned());
+        Ok(())
+    }
+
+    // Replaces the type of each binding with `f` of it, applying the
+    // substitution first so that the environment no longer depends on it.
+    fn map_environment(&mut self, f: impl Fn(&Type) -> Type) {
+        let mut environment = std::mem::take(&mut self.environment);
+        for scheme in environment.schemes_mut() {
+            scheme.ty = f(&self.apply(&scheme.ty));
+        }
+        self.environment = environment;
+    }
+
+    // Renames the type `name` that an earlier program declared to `name/1`,
+    // `name/2`... wherever it is used, so that its values no longer unify
+    // with those of the new type. Aliases need no renaming, since no value
+    // has them as its type.
+    fn shadow_type(&mut self, name: &str) {
+        if let Some(TypeKind::Alias(_)) = self.types.get(name).map(|declaration| &declaration.kind)
+        {
+            return;
+        }
+        let renamed = (1..)
+            .map(|n| format!("{}/{}", name, n))
+            .find(|renamed| !self.types.contains_key(renamed))
+            .unwrap_or_default();
+        let rename = |ty: &Type| ty.rename(name, &renamed);
+        self.map_environment(rename);
+
+        if let Some(mut declaration) = self.types.remove(name) {
+            declaration.name = renamed.to_owned();
+            self.types.insert(renamed.to_owned(), declaration);
+        }
+        for declaration in self.types.values_mut() {
+            declaration.kind = match &declaration.kind {
+                TypeKind::Alias(target) => TypeKind::Alias(rename(target)),
+                TypeKind::Record(fields) => TypeKind::Record(
+                    fields
+                        .iter()
+                        .map(|(field, ty)| (field.to_owned(), rename(ty)))
+                        .collect(),
+                ),
+                kind => kind.to_owned(),
+            };
+        }
+        for constructor in self.constructors.values_mut() {
+            if constructor.type_name == name {
+                constructor.type_name = renamed.to_owned();
+            }
+            constructor.fields = constructor.fields.iter().map(rename).collect();
+        }
+        for owner in self.field_owners.values_mut() {
+            if owner == name {
+                *owner = renamed.to_owned();
+            }
+        }
+    }
+
+    fn declare_type(
+        &mut self,
+        name: &Token,
+        params: &[Token],
+        definition: &TypeDefinition,
+    ) -> Result<(), TypeError> {
+        self.claim_type(name)?;
+        let type_name = name.to_string();
+        let param_vars: HashMap<String, TypeVar> = params
+            .iter()
+            .map(|param| (param.to_string(), self.fresh_var()))
+            .collect();
+        let mut declaration = TypeDeclaration {
+            name: type_name.to_owned(),
+            params: params
+                .iter()
+                .map(|param| param_vars[&param.to_string()])
+                .collect(),
+            kind: TypeKind::Sum(vec![]),
+        };
+
+        if let TypeDefinition::Alias(target) = definition {
+            declaration.kind = TypeKind::Alias(self.convert(target, &param_vars)?);
+            self.types.insert(type_name, declaration);
+            return Ok(());
+        }
+
+        // Sum and record types may refer to themselves, so declare them first.
+        self.types
+            .insert(type_name.to_owned(), declaration.to_owned());
+
+        match definition {
+            TypeDefinition::Sum(variants) => {
+                let mut names = vec![];
+                for variant in variants {
+                    self.claim_constructor(&variant.name, &variant.name.to_string())?;
+                    let mut fields = vec![];
+                    for field in &variant.fields {
+                        fields.push(self.convert(field, &param_vars)?);
+                    }
+                    names.push(variant.name.to_string());
+                    self.constructors.insert(
+                        variant.name.to_string(),
+                        Constructor {
+                            name: variant.name.to_string(),
+                            type_name: type_name.to_owned(),
+                            params: declaration.params.to_owned(),
+                            fields,
+                        },
+                    );
+                }
+                declaration.kind = TypeKind::Sum(names);
+            }
+            TypeDefinition::Record(fields) => {
+                let mut converted = vec![];
+                for field in fields {
+                    let field_name = field.name.to_string();
+                    if converted.iter().any(|(name, _)| *name == field_name) {
+                        return Err(TypeError::new(
+                            ErrorCode::DuplicateField,
+                            &field.name,
+                            format!("Field {} is already declared", field_name),
+                        ));
+                    }
+                    self.claim_field(&field.name, &field_name, &type_name)?;
+                    converted.push((field_name, self.convert(&field.ty, &param_vars)?));
+                }
+                declaration.kind = TypeKind::Record(converted);
+            }
+            TypeDefinition::Alias(_) => {}
+        }
+
+        self.types.insert(type_name, declaration);
+        Ok(())
+    }
+
+    // Turns a written type into a `Type`, expanding aliases.
+    fn convert(
+        &self,
+        ty: &TypeExpression,
+        params: &HashMap<String, TypeVar>,
+    ) -> Result<Type, TypeError> {
+        let (name, args) = match ty {
+            TypeExpression::Named { name, args, .. } => (name, args),
+            TypeExpression::Tuple { elements, .. } => {
+                let mut converted = vec![];
+                for element in elements {
+                    converted.push(self.convert(element, params)?);
+                }
+                return Ok(Type::tuple(converted));
+            }
+            TypeExpression::Function {
+                params: param_types,
+                ret,
+                ..
+            } => {
+                let mut converted = vec![];
+                for param in param_types {
+                    converted.push(self.convert(param, params)?);
+                }
+                return Ok(Type::function(converted, self.convert(ret, params)?));
+            }
+        };
+        let type_name = name.to_string();
+
+        if args.is_empty() {
+            if let Some(var) = params.get(&type_name) {
+                return Ok(Type::Var(*var));
+            }
+        }
+
+        let declaration = match self.types.get(&type_name) {
+            Some(declaration) => declaration,
+            None => {
+                return Err(TypeError::new(
+                    ErrorCode::UnknownType,
+                    name,
+                    format!("Unknown type {}", type_name),
+                ))
+            }
+        };
+        if declaration.params.len() != args.len() {
+            return Err(TypeError::new(
+                ErrorCode::ArgumentCount,
+                name,
+                format!(
+                    "Type {0} expects {1} type arguments, found {2}",
+                    type_name,
+                    declaration.params.len(),
+                    args.len()
+                ),
+            ));
+        }
+
+        let mut converted = vec![];
+        for arg in args {
+            converted.push(self.convert(arg, params)?);
+        }
+
+        match &declaration.kind {
+            TypeKind::Alias(target) => {
+                let mapping = declaration.params.iter().copied().zip(converted).collect();
+                Ok(target.substitute(&mapping))
+            }
+            _ => Ok(Type::Con {
+                name: type_name,
+                args: converted,
+            }),
+        }
+    }
+
+    // Fresh type variables for each parameter of a declared type.
+    fn instantiate_params(&mut self, params: &[TypeVar]) -> HashMap<TypeVar, Type> {
+        params.iter().map(|var| (*var, self.fresh())).collect()
+    }
+
+    fn infer_construct(&mut self, name: &Token, args: &[Expression]) -> Result<Type, TypeError> {
+        let constructor = match self.constructors.get(&name.to_string()) {
+            Some(constructor) => constructor.to_owned(),
+            None => {
+                return Err(TypeError::new(
+                    ErrorCode::UndefinedConstructor,
+                    name,
+                    format!("Undefined constructor {}", name),
+                ))
+            }
+        };
+        if constructor.fields.len() != args.len() {
+            return Err(TypeError::new(
+                ErrorCode::ArgumentCount,
+                name,
+                format!(
+                    "Constructor {0} expects {1} arguments, found {2}",
+                    name,
+                    constructor.fields.len(),
+                    args.len()
+                ),
+            ));
+        }
+
+        let mapping = self.instantiate_params(&constructor.params);
+        for (arg, field) in args.iter().zip(&constructor.fields) {
+            let arg_ty = self.infer_expression(arg)?;
+            self.unify_expression(&field.substitute(&mapping), &arg_ty, arg)?;
+        }
+
+        Ok(Type::Con {
+            name: constructor.type_name,
+            args: constructor
+                .params
+                .iter()
+                .map(|var| mapping[var].to_owned())
+                .collect(),
+        })
+    }
+
+    // Finds the record type declaring `field`, with fresh type arguments.
+    fn field_owner(
+        &mut self,
+        field: &Token,
+    ) -> Result<(TypeDeclaration, HashMap<TypeVar, Type>), TypeError> {
+        let declaration = self
+            .field_owners
+            .get(&field.to_string())
+            .and_then(|owner| self.types.get(owner))
+            .cloned();
+
+        match declaration {
+            Some(declaration) => {
+                let mapping = self.instantiate_params(&declaration.params);
+                Ok((declaration, mapping))
+            }
+            None => Err(TypeError::new(
+                ErrorCode::UnknownField,
+                field,
+                format!("Unknown field {}", field),
+            )),
+        }
+    }
+
+    fn infer_record(
+        &mut self,
+        brace: &Token,
+        fields: &[(Token, Expression)],
+    ) -> Result<Type, TypeError> {
+        let (first, _) = match fields.first() {
+            Some(first) => first,
+            None => {
+                return Err(TypeError::new(
+                    ErrorCode::EmptyRecord,
+                    brace,
+                    "Cannot infer the type of an empty record".to_string(),
+                ))
+            }
+        };
+        let (declaration, mapping) = self.field_owner(first)?;
+        let declared = match &declaration.kind {
+            TypeKind::Record(declared) => declared.to_owned(),
+            _ => vec![],
+        };
+
+        for (index, (name, value)) in fields.iter().enumerate() {
+            if fields[..index]
+                .iter()
+                .any(|(earlier, _)| earlier.to_string() == name.to_string())
+            {
+                return Err(TypeError::new(
+                    ErrorCode::DuplicateField,
+                    name,
+                    format!("Field {} is given twice", name),
+                ));
+            }
+            let field_ty = match declared
+                .iter()
+                .find(|(field, _)| *field == name.to_string())
+            {
+                Some((_, field_ty)) => field_ty.substitute(&mapping),
+                None => {
+                    return Err(TypeError::new(
+                        ErrorCode::UnknownField,
+                        name,
+                        format!("Record {0} has no field {1}", declaration.name, name),
+                    ))
+                }
+            };
+            let value_ty = self.infer_expression(value)?;
+            self.unify_expression(&field_ty, &value_ty, value)?;
+        }
+
+        for (field, _) in &declared {
+            if !fields.iter().any(|(name, _)| name.to_string() == *field) {
+                return Err(TypeError::new(
+                    ErrorCode::MissingField,
+                    brace,
+                    format!("Missing field {0} in record {1}", field, declaration.name),
+                ));
+            }
+        }
+
+        Ok(declaration.ty().substitute(&mapping))
+    }
+
+    fn infer_get(&mut self, object: &Expression, name: &Token) -> Result<Type, TypeError> {
+        let object_ty = self.infer_expression(object)?;
+        let (declaration, mapping) = self.field_owner(name)?;
+        self.unify(
+            &declaration.ty().substitute(&mapping),
+            &object_ty,
+            object.token(),
+        )?;
+
+        match &declaration.kind {
+            TypeKind::Record(fields) => fields
+                .iter()
+                .find(|(field, _)| *field == name.to_string())
+                .map(|(_, ty)| ty.substitute(&mapping))
+                .ok_or_else(|| {
+                    TypeError::new(
+                        ErrorCode::UnknownField,
+                        name,
+                        format!("Unknown field {}", name),
+                    )
+                }),
+            _ => Err(TypeError::new(
+                ErrorCode::UnknownField,
+                name,
+                format!("Unknown field {}", name),
+            )),
+        }
+    }
+
+    fn infer_match(
+        &mut self,
+        keyword: &Token,
+        subject: &Expression,
+        arms: &[MatchArm],
+    ) -> Result<Type, TypeError> {
+        let subject_ty = self.infer_expression(subject)?;
+        let result = self.fresh();
+
+        for arm in arms {
+            let mut bindings = vec![];
+            self.check_pattern(&arm.pattern, &subject_ty, &mut bindings)?;
+
+            self.environment.push_scope();
+            for (name, ty) in bindings {
+                self.record(name.span, ty.to_owned());
+                self.environment
+                    .define(&name.to_string(), Scheme::monomorphic(ty));
+            }
+            let body_ty = self.infer_expression(&arm.body);
+            self.environment.pop_scope();
+
+            self.unify_expression(&result, &body_ty?, &arm.body)?;
+        }
+
+        self.check_exhaustive(keyword, &subject_ty, arms)?;
+        Ok(result)
+    }
+
+    // Collects the variables bound by a pattern matched against `expected`.
+    fn check_pattern(
+        &mut self,
+        pattern: &Pattern,
+        expected: &Type,
+        bindings: &mut Vec<(Token, Type)>,
+    ) -> Result<(), TypeError> {
+        match pattern {
+            Pattern::Wildcard { .. } => Ok(()),
+            Pattern::Binding { name } => {
+                if bindings
+                    .iter()
+                    .any(|(bound, _)| bound.literal == name.literal)
+                {
+                    return Err(TypeError::new(
+                        ErrorCode::DuplicateBinding,
+                        name,
+                        format!("Variable {} is bound more than once in this pattern", name),
+                    ));
+                }
+                bindings.push((name.to_owned(), expected.to_owned()));
+                Ok(())
+            }
+            Pattern::Literal { lit } => {
+                let lit_ty = self.infer_expression(&Expression::new_literal(lit.to_owned()))?;
+                self.unify(expected, &lit_ty, lit)
+            }
+            Pattern::Constructor { name, args } => {
+                let constructor = match self.constructors.get(&name.to_string()) {
+                    Some(constructor) => constructor.to_owned(),
+                    None => {
+                        return Err(TypeError::new(
+                            ErrorCode::UndefinedConstructor,
+                            name,
+                            format!("Undefined constructor {}", name),
+                        ))
+                    }
+                };
+                if constructor.fields.len() != args.len() {
+                    return Err(TypeError::new(
+                        ErrorCode::ArgumentCount,
+                        name,
+                        format!(
+                            "Constructor {0} expects {1} arguments, found {2}",
+                            name,
+                            constructor.fields.len(),
+                            args.len()
+                        ),
+                    ));
+                }
+
+                let mapping = self.instantiate_params(&constructor.params);
+                let ty = Type::Con {
+                    name: constructor.type_name.to_owned(),
+                    args: constructor
+                        .params
+                        .iter()
+                        .map(|var| mapping[var].to_owned())
+                        .collect(),
+                };
+                self.unify(expected, &ty, name)?;
+
+                for (arg, field) in args.iter().zip(&constructor.fields) {
+                    self.check_pattern(arg, &field.substitute(&mapping), bindings)?;
+                }
+                Ok(())
+            }
+            Pattern::Record { brace, fields } => {
+                let (first, _) = match fields.first() {
+                    Some(first) => first,
+                    None => {
+                        return Err(TypeError::new(
+                            ErrorCode::EmptyRecord,
+                            brace,
+                            "Cannot infer the type of an empty record pattern".to_string(),
+                        ))
+                    }
+                };
+                let (declaration, mapping) = self.field_owner(first)?;
+                self.unify(expected, &declaration.ty().substitute(&mapping), brace)?;
+
+                let declared = match &declaration.kind {
+                    TypeKind::Record(declared) => declared.to_owned(),
+                    _ => vec![],
+                };
+                for (name, field_pattern) in fields {
+                    match declared
+                        .iter()
+                        .find(|(field, _)| *field == name.to_string())
+                    {
+                        Some((_, field_ty)) => self.check_pattern(
+                            field_pattern,
+                            &field_ty.substitute(&mapping),
+                            bindings,
+                        )?,
+                        None => {
+                            return Err(TypeError::new(
+                                ErrorCode::UnknownField,
+                                name,
+                                format!("Record {0} has no field {1}", declaration.name, name),
+                            ))
+                        }
+                    }
+                }
+                Ok(())
+            }
+            Pattern::Tuple { paren, elements } => {
+                let element_tys: Vec<Type> = elements.iter().map(|_| self.fresh()).collect();
+                self.unify(expected, &Type::tuple(element_tys.to_owned()), paren)?;
+                for (element, element_ty) in elements.iter().zip(&element_tys) {
+                    self.check_pattern(element, element_ty, bindings)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn check_exhaustive(
+        &self,
+        keyword: &Token,
+        subject_ty: &Type,
+        arms: &[MatchArm],
+    ) -> Result<(), TypeError> {
+        let exhaustiveness = Exhaustiveness::new(&self.types, &self.constructors);
+        let tys = vec![self.apply(subject_ty)];
+
+        let mut rows = vec![];
+        for arm in arms {
+            let row = vec![self.lower_pattern(&arm.pattern)];
+            if exhaustiveness.useful(&rows, &row, &tys).is_none() {
+                return Err(TypeError::new(
+                    ErrorCode::UnreachablePattern,
+                    arm.pattern.token(),
+                    format!("Unreachable pattern {}", arm.pattern),
+                )
+                .with_note("every value it matches is handled by an earlier arm".to_string()));
+            }
+            rows.push(row);
+        }
+
+        match exhaustiveness.useful(&rows, &[Pat::Wild], &tys) {
+            Some(witness) => Err(TypeError::new(
+                ErrorCode::NonExhaustiveMatch,
+                keyword,
+                format!("Non-exhaustive match, missing case {}", witness[0]),
+            )
+            .with_help(format!(
+                "add an arm for {}, or a _ arm to match anything else",
+                witness[0]
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn lower_pattern(&self, pattern: &Pattern) -> Pat {
+        match pattern {
+            Pattern::Wildcard { .. } | Pattern::Binding { .. } => Pat::Wild,
+            Pattern::Literal { lit } => match lit.kind {
+                TokenKind::True => Pat::Ctor(CtorId::Bool(true), vec![]),
+                TokenKind::False => Pat::Ctor(CtorId::Bool(false), vec![]),
+                TokenKind::Num => {
+                    let literal = lit.literal.to_owned().unwrap_or_default();
+                    let normalized = literal
+                        .parse::<f64>()
+                        .map(|n| n.to_string())
+                        .unwrap_or(literal);
+                    Pat::Ctor(CtorId::Literal(normalized), vec![])
+                }
+                _ => Pat::Ctor(CtorId::Literal(format!("\"{}\"", lit)), vec![]),
+            },
+            Pattern::Constructor { name, args } => Pat::Ctor(
+                CtorId::Variant(name.to_string()),
+                args.iter().map(|arg| self.lower_pattern(arg)).collect(),
+            ),
+            Pattern::Record { fields, .. } => {
+                let declaration = fields
+                    .first()
+                    .and_then(|(first, _)| self.field_owners.get(&first.to_string()))
+                    .and_then(|owner| self.types.get(owner));
+                let declared = match declaration.map(|declaration| &declaration.kind) {
+                    Some(TypeKind::Record(declared)) => declared,
+                    _ => return Pat::Wild,
+                };
+
+                let names: Vec<String> = declared.iter().map(|(name, _)| name.to_owned()).collect();
+                let args = names
+                    .iter()
+                    .map(|name| {
+                        fields
+                            .iter()
+                            .find(|(field, _)| field.to_string() == *name)
+                            .map_or(Pat::Wild, |(_, pattern)| self.lower_pattern(pattern))
+                    })
+                    .collect();
+                let type_name = declaration.map(|d| d.name.to_owned()).unwrap_or_default();
+                Pat::Ctor(CtorId::Record(type_name, names), args)
+            }
+            Pattern::Tuple { elements, .. } => Pat::Ctor(
+                CtorId::Tuple(elements.len()),
+                elements
+                    .iter()
+                    .map(|element| self.lower_pattern(element))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn record(&mut self, span: Span, ty: Type) {
+        if self.recording {
+            self.recorded.push((span, ty));
+        }
+    }
+
+    fn infer_expression(&mut self, expr: &Expression) -> Result<Type, TypeError> {
+        let ty = self.infer_kind(expr)?;
+        self.record(expr.span(), ty.to_owned());
+        Ok(ty)
+    }
+
+    fn infer_kind(&mut self, expr: &Expression) -> Result<Type, TypeError> {
+        match expr {
+            Expression::Literal { lit, .. } => match lit.kind {
+                TokenKind::Num => Ok(Type::num()),
+                TokenKind::Str => Ok(Type::str()),
+                TokenKind::True | TokenKind::False => Ok(Type::bool()),
+                _ => Err(TypeError::new(
+                    ErrorCode::InvalidLiteral,
+                    lit,
+                    format!("Unexpected literal {}", lit),
+                )),
+            },
+            Expression::Variable { name, .. } => match self.environment.get(&name.to_string()) {
+                Some(scheme) => {
+                    let scheme = scheme.to_owned();
+                    Ok(self.instantiate(&scheme))
+                }
+                None => Err(TypeError::new(
+                    ErrorCode::UndefinedVariable,
+                    name,
+                    format!("Undefined variable {}", name),
+                )),
+            },
+            Expression::Grouping { expr, .. } => self.infer_expression(expr),
+            Expression::Construct { name, args, .. } => self.infer_construct(name, args),
+            Expression::Record { brace, fields, .. } => self.infer_record(brace, fields),
+            Expression::Get { object, name, .. } => self.infer_get(object, name),
+            Expression::Tuple { elements, .. } => {
+                let mut types = vec![];
+                for element in elements {
+                    types.push(self.infer_expression(element)?);
+                }
+                Ok(Type::tuple(types))
+            }
+            Expression::Match {
+                keyword,
+                subject,
+                arms,
+                ..
+            } => self.infer_match(keyword, subject, arms),
+            Expression::Lambda { params, body, .. } => self.infer_lambda(params, body),
+            Expression::Call {
+                callee,
+                paren,
+                args,
+                span,
+            } => self.infer_call(callee, paren, args, *span),
+            Expression::Unary { op, right, .. } => {
+                let right_ty = self.infer_expression(right)?;
+                let operand = match op.kind {
+                    TokenKind::Bang => Type::bool(),
+                    _ => Type::num(),
+                };
+                self.unify_expression(&operand, &right_ty, right)?;
+                Ok(operand)
+            }
+            Expression::Binary {
+                left, op, right, ..
+            } => {
+                let left_ty = self.infer_expression(left)?;
+                let right_ty = self.infer_expression(right)?;
+                self.binary(left, &left_ty, op, right, &right_ty)
+            }
+        }
+    }
+
+    // A function bound by `let` may call itself, monomorphically, before its
+    // type is generalized.
+    fn infer_recursive(
+        &mut self,
+        name: &Token,
+        initializer: &Expression,
+    ) -> Result<Type, TypeError> {
+        let ty = self.fresh();
+        self.environment.push_scope();
+        self.environment
+            .define(&name.to_string(), Scheme::monomorphic(ty.to_owned()));
+        let result = self
+            .infer_expression(initializer)
+            .and_then(|inferred| self.unify_expression(&ty, &inferred, initializer));
+        self.environment.pop_scope();
+        result.map(|_| ty)
+    }
+
+    fn infer_lambda(&mut self, params: &[Token], body: &Expression) -> Result<Type, TypeError> {
+        let param_types: Vec<Type> = params.iter().map(|_| self.fresh()).collect();
+
+        self.environment.push_scope();
+        for (param, ty) in params.iter().zip(&param_types) {
+            self.record(param.span, ty.to_owned());
+            self.environment
+                .define(&param.to_string(), Scheme::monomorphic(ty.to_owned()));
+        }
+        let ret = self.infer_expression(body);
+        self.environment.pop_scope();
+
+        Ok(Type::function(param_types, ret?))
+    }
+
+    fn infer_call(
+        &mut self,
+        callee: &Expression,
+        paren: &Token,
+        args: &[Expression],
+        span: Span,
+    ) -> Result<Type, TypeError> {
+        let callee_ty = self.infer_expression(callee)?;
+        let mut arg_types = vec![];
+        for arg in args {
+            arg_types.push(self.infer_expression(arg)?);
+        }
+
+        match self.apply(&callee_ty) {
+            Type::Function { params, ret } => {
+                if params.len() != args.len() {
+                    return Err(TypeError::new(
+                        ErrorCode::ArgumentCount,
+                        paren,
+                        format!(
+                            "Function expects {0} arguments, found {1}",
+                            params.len(),
+                            args.len()
+                        ),
+                    )
+                    .with_span(Span::new(span.file_id, paren.span.start, span.end))
+                    .with_label(
+                        callee.span(),
+                        format!("this function takes {} arguments", params.len()),
+                    ));
+                }
+                for ((param, arg_ty), arg) in params.iter().zip(&arg_types).zip(args) {
+                    self.unify_expression(param, arg_ty, arg)?;
+                }
+                Ok(*ret)
+            }
+            Type::Var(_) => {
+                let ret = self.fresh();
+                self.unify(
+                    &callee_ty,
+                    &Type::function(arg_types, ret.to_owned()),
+                    callee.token(),
+                )?;
+                Ok(ret)
+            }
+            other => Err(TypeError::new(
+                ErrorCode::NotAFunction,
+                paren,
+                format!("Expected a function, found {}", other),
+            )),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        left: &Expression,
+        left_ty: &Type,
+        op: &Token,
+        right: &Expression,
+        right_ty: &Type,
+    ) -> Result<Type, TypeError> {
+        match op.kind {
+            TokenKind::EqualEqual | TokenKind::BangEqual => {
+                self.unify_operands(left, left_ty, right, right_ty)?;
+                Ok(Type::bool())
+            }
+            // `+` adds numbers and concatenates strings, defaulting to numbers
+            // when nothing else is known about its operands.
+            TokenKind::Plus => {
+                self.unify_operands(left, left_ty, right, right_ty)?;
+                match self.apply(left_ty) {
+                    Type::Con { ref name, .. } if name == "Str" => Ok(Type::str()),
+                    _ => {
+                        self.unify_expression(&Type::num(), left_ty, left)?;
+                        Ok(Type::num())
+                    }
+                }
+            }
+            TokenKind::Greater
+            | TokenKind::GreaterEqual
+            | TokenKind::Less
+            | TokenKind::LessEqual => {
+                self.unify_expression(&Type::num(), left_ty, left)?;
+                self.unify_expression(&Type::num(), right_ty, right)?;
+                Ok(Type::bool())
+            }
+            _ => {
+                self.unify_expression(&Type::num(), left_ty, left)?;
+                self.unify_expression(&Type::num(), right_ty, right)?;
+                Ok(Type::num())
+            }
+        }
+    }
+
+    // Reports a mismatch at the whole of `expr`.
+    fn unify_expression(
+        &mut self,
+        expected: &Type,
+        found: &Type,
+        expr: &Expression,
+    ) -> Result<(), TypeError> {
+        self.unify(expected, found, expr.token())
+            .map_err(|err| err.with_span(expr.span()))
+    }
+
+    // Both operands must have the same type; a mismatch is reported at the
+    // right one and labels the type of the left.
+    fn unify_operands(
+        &mut self,
+        left: &Expression,
+        left_ty: &Type,
+        right: &Expression,
+        right_ty: &Type,
+    ) -> Result<(), TypeError> {
+        self.unify_expression(left_ty, right_ty, right)
+            .map_err(|err| err.with_label(left.span(), format!("this is {}", self.apply(left_ty))))
+    }
+
+    fn fresh_var(&mut self) -> TypeVar {
+        self.next_var += 1;
+        self.next_var
+    }
+
+    fn fresh(&mut self) -> Type {
+        Type::Var(self.fresh_var())
+    }
+
+    // Resolves every type variable bound by the current substitution.
+    fn apply(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match self.substitution.get(var) {
+                Some(bound) => self.apply(bound),
+                None => ty.to_owned(),
+            },
+            Type::Con { name, args } => Type::Con {
+                name: name.to_owned(),
+                args: args.iter().map(|arg| self.apply(arg)).collect(),
+            },
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|param| self.apply(param)).collect(),
+                ret: Box::new(self.apply(ret)),
+            },
+        }
+    }
+
+    fn generalize(&self, ty: &Type) -> Scheme {
+        let ty = self.apply(ty);
+        let env_vars: Vec<TypeVar> = self
+            .environment
+            .free_type_vars()
+            .into_iter()
+            .flat_map(|var| self.apply(&Type::Var(var)).free_type_vars())
+            .collect();
+        let vars = ty
+            .free_type_vars()
+            .into_iter()
+            .filter(|var| !env_vars.contains(var))
+            .collect();
+
+        Scheme { vars, ty }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let mapping: HashMap<TypeVar, Type> =
+            scheme.vars.iter().map(|var| (*var, self.fresh())).collect();
+        self.apply(&scheme.ty).substitute(&mapping)
+    }
+
+    fn unify(&mut self, expected: &Type, found: &Type, token: &Token) -> Result<(), TypeError> {
+        match self.unify_types(expected, found) {
+            Ok(()) => Ok(()),
+            Err(UnifyError::Mismatch) => Err(TypeError::new(
+                ErrorCode::Mismatch,
+                token,
+                format!(
+                    "Expected {}, found {}",
+                    self.apply(expected),
+                    self.apply(found)
+                ),
+            )),
+            Err(UnifyError::Infinite(var, ty)) => Err(TypeError::new(
+                ErrorCode::InfiniteType,
+                token,
+                format!(
+                    "Cannot construct the infinite type {} = {}",
+                    Type::Var(var),
+                    self.apply(&ty)
+                ),
+            )),
+        }
+    }
+
+    fn unify_types(&mut self, a: &Type, b: &Type) -> Result<(), UnifyError> {
+        let a = self.resolve(a);
+        let b = self.resolve(b);
+
+        match (&a, &b) {
+            (Type::Var(x), Type::Var(y)) if x == y => Ok(()),
+            (Type::Var(var), ty) | (ty, Type::Var(var)) => {
+                if self.apply(ty).free_type_vars().contains(var) {
+                    return Err(UnifyError::Infinite(*var, ty.to_owned()));
+                }
+                self.substitution.insert(*var, ty.to_owned());
+                Ok(())
+            }
+            (
+                Type::Con { name, args },
+                Type::Con {
+                    name: other_name,
+                    args: other_args,
+                },
+            ) if name == other_name && args.len() == other_args.len() => {
+                for (arg, other_arg) in args.iter().zip(other_args) {
+                    self.unify_types(arg, other_arg)?;
+                }
+                Ok(())
+            }
+            (
+                Type::Function { params, ret },
+                Type::Function {
+                    params: other_params,
+                    ret: other_ret,
+                },
+            ) if params.len() == other_params.len() => {
+                for (param, other_param) in params.iter().zip(other_params) {
+                    self.unify_types(param, other_param)?;
+                }
+                self.unify_types(ret, other_ret)
+            }
+            _ => Err(UnifyError::Mismatch),
+        }
+    }
+
+    // Follows variable bindings until reaching an unbound variable or a constructor.
+    fn resolve(&self, ty: &Type) -> Type {
+        match ty {
+            Type::Var(var) => match self.substitution.get(var) {
+                Some(bound) => self.resolve(bound),
+                None => ty.to_owned(),
+            },
+            _ => ty.to_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::{ErrorCode, Label, ToDiagnostics};
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(source: &str) -> (TypeChecker, Result<(), TypeCheckerError>) {
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let mut checker = TypeChecker::new();
+        let result = checker.check_program(&program);
+        (checker, result)
+    }
+
+    #[test]
+    fn infers_literals_and_operators() {
+        let (checker, result) = check(
+            "let n = 1 + 2 * 3; let s = \"a\" + \"b\"; let b = n > 2 == !false; let c = (n);",
+        );
+        assert!(result.is_ok());
+        assert_eq!(checker.lookup("n").unwrap().to_string(), "Num");
+        assert_eq!(checker.lookup("s").unwrap().to_string(), "Str");
+        assert_eq!(checker.lookup("b").unwrap().to_string(), "Bool");
+        assert_eq!(checker.lookup("c").unwrap().to_string(), "Num");
+    }
+
+    #[test]
+    fn records_the_types_of_expressions_and_bindings() {
+        let source = "let id = x => x; let n = id(2);";
+        let tokens = Lexer::new(source.as_bytes()).tokenize().unwrap();
+        let program = Parser::new(tokens).parse_program().unwrap();
+        let mut checker = TypeChecker::recording();
+        assert!(checker.check_program(&program).is_ok());
+        let types: Vec<(&str, String)> = checker
+            .recorded_types()
+            .iter()
+            .map(|(span, ty)| (&source[span.start..span.end], ty.to_string()))
+            .collect();
+        for expected in [
+            ("x", "a"),
+            ("x => x", "(a) => a"),
+            ("id", "(a) => a"),
+            ("id(2)", "Num"),
+            ("n", "Num"),
+        ] {
+            assert!(
+                types.contains(&(expected.0, expected.1.to_owned())),
+                "{:?}",
+                types
+            );
+        }
+    }
+
+    #[test]
+    fn reports_every_mismatch_at_the_offending_token() {
+        let (_, result) = check("let a = 1 + true; let b = a; if 1 { print -\"x\"; }");
+        let errors = result.unwrap_err();
+        let reported: Vec<(String, TokenKind)> = errors
+            .errors()
+            .iter()
+            .map(|err| (err.text.to_owned(), err.token.kind))
+            .collect();
+
+        assert_eq!(
+            reported,
+            vec![
+                ("Expected Num, found Bool".to_string(), TokenKind::True),
+                ("Expected Bool, found Num".to_string(), TokenKind::Num),
+                ("Expected Num, found Str".to_string(), TokenKind::Str),
+            ]
+        );
+    }
+
+    #[test]
+    fn infers_constructors_and_records() {
+        let (checker, result) = check(
+            "type Option<a> = Some(a) | None;
+            type List<a> = Cons(a, List<a>) | Nil;
+            type Pair<a, b> = { first: a, second: b };
+            type Names = List<Str>;
+            let none = None;
+            let names = Cons(\"a\", Nil);
+            let pair = { first: 1, second: Some(true) };
+            let second = pair.second;",
+        );
+        assert!(result.is_ok());
+        assert_eq!(checker.lookup("none").unwrap().to_string(), "Option<a>");
+        assert_eq!(checker.lookup("names").unwrap().to_string(), "List<Str>");
+        assert_eq!(
+            checker.lookup("pair").unwrap().to_string(),
+            "Pair<Num, Option<Bool>>"
+        );
+        assert_eq!(
+            checker.lookup("second").unwrap().to_string(),
+            "Option<Bool>"
+        );
+    }
+
+    #[test]
+    fn reports_misused_constructors_and_fields() {
+        let (_, result) = check(
+            "type Option<a> = Some(a) | None;
+            type Point = { x: Num, y: Num };
+            let a = Cons(1, Some(2, 3));
+            let b = Some(1) == Some(\"one\");
+            let c = { x: 1 };
+            let d = { x: 1, y: 2, z: 3 };
+            let e = Some(1).x;
+            type Bad = Option<Num, Str>;",
+        );
+        let errors: Vec<String> = result
+            .unwrap_err()
+            .errors()
+            .iter()
+            .map(|err| err.text.to_owned())
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                "Undefined constructor Cons",
+                "Expected Option<Num>, found Option<Str>",
+                "Missing field y in record Point",
+                "Record Point has no field z",
+                "Expected Point, found Option<Num>",
+                "Type Option expects 1 type arguments, found 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_redeclared_types() {
+        let (_, result) = check(
+            "type Num = X(Str);
+            let f = (n) => match n { X(s) => s + \"!\" };
+            print f(1);",
+        );
+        assert_eq!(
+            result.unwrap_err().errors()[0].text,
+            "Cannot redeclare the builtin type Num"
+        );
+
+        let (_, result) = check(
+            "type T = A(Num);
+            let a = A(1);
+            type T = A(Str);
+            let f = t => match t { A(s) => s + \"x\" };
+            print f(a);",
+        );
+        let errors: Vec<String> = result
+            .unwrap_err()
+            .errors()
+            .iter()
+            .map(|err| err.text.to_owned())
+            .collect();
+        assert_eq!(errors[0], "Type T is already declared");
+    }
+
+    #[test]
+    fn reports_duplicate_constructors_and_fields() {
+        let (_, result) = check(
+            "type T = A(Num) | A(Str);
+            type P = { a: Num, a: Num };
+            type A = { x: Num };
+            type B = { x: Str, y: Num };
+            type C = { z: Num };
+            let c = { z: 1, z: 2 };",
+        );
+        let errors: Vec<String> = result
+            .unwrap_err()
+            .errors()
+            .iter()
+            .map(|err| err.text.to_owned())
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                "Constructor A is already declared",
+                "Field a is already declared",
+                "Field x already belongs to record A",
+                "Field z is given twice",
+            ]
+        );
+    }
+
+    #[test]
+    fn checks_match_expressions() {
+        let (checker, result) = check(
+            "type Option<a> = Some(a) | None;
+            type Point = { x: Num, y: Num };
+            let describe = match (Some(1), { x: 0, y: 2 }, true) {
+                (Some(0), _, _) => \"zero\",
+                (Some(n), { x: 0, y }, true) => \"on axis\",
+                (None, _, false) => \"none\",
+                (_, _, _) => \"other\",
+            };",
+        );
+        assert!(result.is_ok());
+        assert_eq!(checker.lookup("describe").unwrap().to_string(), "Str");
+    }
+
+    #[test]
+    fn reports_missing_cases_and_unreachable_arms() {
+        let (_, result) = check(
+            "type Option<a> = Some(a) | None;
+            type Point = { x: Num, y: Num };
+            let a = match Some(true) { Some(true) => 1, None => 0 };
+            let b = match (1, false) { (_, true) => 1, (0, _) => 2 };
+            let c = match { x: 1, y: 2 } { { x } => x, { x: 0 } => 0 };
+            let d = match Some(1) { Some(n) => n, None => \"none\" };
+            let e = match Some(1) { Some(n, m) => n };",
+        );
+        let errors: Vec<(String, TokenKind)> = result
+            .unwrap_err()
+            .errors()
+            .iter()
+            .map(|err| (err.text.to_owned(), err.token.kind))
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    "Non-exhaustive match, missing case Some(false)".to_string(),
+                    TokenKind::Match
+                ),
+                (
+                    "Non-exhaustive match, missing case (_, false)".to_string(),
+                    TokenKind::Match
+                ),
+                (
+                    "Unreachable pattern { x: 0 }".to_string(),
+                    TokenKind::LeftBrace
+                ),
+                ("Expected Num, found Str".to_string(), TokenKind::Str),
+                (
+                    "Constructor Some expects 1 arguments, found 2".to_string(),
+                    TokenKind::Identifier
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn generalizes_unconstrained_let_bindings() {
+        let mut checker = TypeChecker::new();
+        let var = checker.fresh();
+        let scheme = checker.generalize(&Type::function(vec![var.to_owned()], var));
+        assert_eq!(scheme.vars.len(), 1);
+
+        let first = checker.instantiate(&scheme);
+        let second = checker.instantiate(&scheme);
+        assert_ne!(first, second);
+        assert_eq!(first.to_string(), "(a) => a");
+    }
+
+    #[test]
+    fn infers_polymorphic_and_recursive_functions() {
+        let (checker, result) = check(
+            "type List<a> = Cons(a, List<a>) | Nil;
+            let id = x => x;
+            let pair = (id(1), id(\"one\"));
+            let compose = (f, g) => x => f(g(x));
+            let length = list => match list { Nil => 0, Cons(_, rest) => 1 + length(rest) };",
+        );
+        assert!(result.is_ok());
+        assert_eq!(checker.lookup("id").unwrap().to_string(), "(a) => a");
+        assert_eq!(checker.lookup("pair").unwrap().to_string(), "(Num, Str)");
+        assert_eq!(
+            checker.lookup("compose").unwrap().to_string(),
+            "((a) => b, (c) => a) => (c) => b"
+        );
+        assert_eq!(
+            checker.lookup("length").unwrap().to_string(),
+            "(List<a>) => Num"
+        );
+    }
+
+    #[test]
+    fn reports_bad_calls() {
+        let (_, result) = check("let f = (a, b) => a - b; f(1); f(1, \"x\"); 1(2);");
+        let messages: Vec<String> = result
+            .unwrap_err()
+            .errors()
+            .iter()
+            .map(|err| err.text.to_owned())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Function expects 2 arguments, found 1",
+                "Expected Num, found Str",
+                "Expected a function, found Num",
+            ]
+        );
+    }
+
+    #[test]
+    fn points_diagnostics_at_whole_expressions() {
+        let source = "let n = (1 + 2) * \"a\";
+            let s = \"a\" + (1, 2);
+            type T = A | B;
+            let t = match A { A => 1 };";
+        let (_, result) = check(source);
+        let diagnostics = result.unwrap_err().to_diagnostics();
+        let snippet = |label: &Label| &source[label.span.start..label.span.end];
+
+        let labels: Vec<Vec<(&str, &str)>> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                diagnostic
+                    .labels
+                    .iter()
+                    .map(|label| (snippet(label), label.message.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                vec![("\"a\"", "")],
+                vec![("(1, 2)", ""), ("\"a\"", "this is Str")],
+                vec![("match", "")],
+            ]
+        );
+        let codes: Vec<Option<ErrorCode>> = diagnostics.iter().map(|d| d.code).collect();
+        assert_eq!(
+            codes,
+            vec![
+                Some(ErrorCode::Mismatch),
+                Some(ErrorCode::Mismatch),
+                Some(ErrorCode::NonExhaustiveMatch)
+            ]
+        );
+        assert_eq!(
+            diagnostics[2].help,
+            vec!["add an arm for B, or a _ arm to match anything else"]
+        );
+    }
+
+    #[test]
+    fn checks_templates_as_string_concatenation() {
+        let (checker, result) = check("let s = `${\"a\"}b`; let n = 1; let t = `n is ${n}`;");
+        assert_eq!(checker.lookup("s").unwrap().to_string(), "Str");
+
+        let errors = result.unwrap_err();
+        let reported: Vec<(String, TokenKind)> = errors
+            .errors()
+            .iter()
+            .map(|err| (err.text.to_owned(), err.token.kind))
+            .collect();
+        assert_eq!(
+            reported,
+            vec![("Expected Str, found Num".to_string(), TokenKind::Identifier)]
+        );
+    }
+}
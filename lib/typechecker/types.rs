@@ -0,0 +1,217 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+pub type TypeVar = u32;
+
+/// Tuples are constructors with one argument per element.
+pub const TUPLE: &str = "Tuple";
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Type {
+    Var(TypeVar),
+    Con { name: String, args: Vec<Type> },
+    Function { params: Vec<Type>, ret: Box<Type> },
+}
+
+impl Type {
+    pub fn con(name: &str) -> Type {
+        Type::Con {
+            name: name.to_owned(),
+            args: vec![],
+        }
+    }
+
+    pub fn num() -> Type {
+        Type::con("Num")
+    }
+
+    pub fn str() -> Type {
+        Type::con("Str")
+    }
+
+    pub fn bool() -> Type {
+        Type::con("Bool")
+    }
+
+    pub fn tuple(elements: Vec<Type>) -> Type {
+        Type::Con {
+            name: TUPLE.to_owned(),
+            args: elements,
+        }
+    }
+
+    pub fn function(params: Vec<Type>, ret: Type) -> Type {
+        Type::Function {
+            params,
+            ret: Box::new(ret),
+        }
+    }
+
+    pub fn free_type_vars(&self) -> BTreeSet<TypeVar> {
+        let mut vars = BTreeSet::new();
+        self.collect_type_vars(&mut vars);
+        vars
+    }
+
+    fn collect_type_vars(&self, vars: &mut BTreeSet<TypeVar>) {
+        match self {
+            Type::Var(var) => {
+                vars.insert(*var);
+            }
+            Type::Con { args, .. } => args.iter().for_each(|arg| arg.collect_type_vars(vars)),
+            Type::Function { params, ret } => {
+                params
+                    .iter()
+                    .for_each(|param| param.collect_type_vars(vars));
+                ret.collect_type_vars(vars);
+            }
+        }
+    }
+
+    /// Replaces type variables according to `mapping`, leaving the others as they are.
+    pub fn substitute(&self, mapping: &HashMap<TypeVar, Type>) -> Type {
+        match self {
+            Type::Var(var) => match mapping.get(var) {
+                Some(ty) => ty.to_owned(),
+                None => self.to_owned(),
+            },
+            Type::Con { name, args } => Type::Con {
+                name: name.to_owned(),
+                args: args.iter().map(|arg| arg.substitute(mapping)).collect(),
+            },
+            Type::Function { params, ret } => Type::Function {
+                params: params
+                    .iter()
+                    .map(|param| param.substitute(mapping))
+                    .collect(),
+                ret: Box::new(ret.substitute(mapping)),
+            },
+        }
+    }
+
+    /// Renames the type constructor `from` to `to`.
+    pub fn rename(&self, from: &str, to: &str) -> Type {
+        match self {
+            Type::Var(_) => self.to_owned(),
+            Type::Con { name, args } => Type::Con {
+                name: if name == from { to } else { name }.to_owned(),
+                args: args.iter().map(|arg| arg.rename(from, to)).collect(),
+            },
+            Type::Function { params, ret } => Type::Function {
+                params: params.iter().map(|param| param.rename(from, to)).collect(),
+                ret: Box::new(ret.rename(from, to)),
+            },
+        }
+    }
+
+    // Renames type variables to a, b, c... in order of appearance.
+    fn display_names(&self) -> HashMap<TypeVar, String> {
+        let mut order = vec![];
+        self.collect_ordered(&mut order);
+        order
+            .into_iter()
+            .enumerate()
+            .map(|(i, var)| (var, var_name(i)))
+            .collect()
+    }
+
+    fn collect_ordered(&self, order: &mut Vec<TypeVar>) {
+        match self {
+            Type::Var(var) => {
+                if !order.contains(var) {
+                    order.push(*var);
+                }
+            }
+            Type::Con { args, .. } => args.iter().for_each(|arg| arg.collect_ordered(order)),
+            Type::Function { params, ret } => {
+                params.iter().for_each(|param| param.collect_ordered(order));
+                ret.collect_ordered(order);
+            }
+        }
+    }
+
+    fn write(&self, f: &mut fmt::Formatter, names: &HashMap<TypeVar, String>) -> fmt::Result {
+        match self {
+            Type::Var(var) => match names.get(var) {
+                Some(name) => write!(f, "{}", name),
+                None => write!(f, "t{}", var),
+            },
+            Type::Con { name, args } if name == TUPLE => {
+                write!(f, "(")?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    arg.write(f, names)?;
+                }
+                write!(f, ")")
+            }
+            Type::Con { name, args } => {
+                write!(f, "{}", name)?;
+                if !args.is_empty() {
+                    write!(f, "<")?;
+                    for (i, arg) in args.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        arg.write(f, names)?;
+                    }
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Type::Function { params, ret } => {
+                write!(f, "(")?;
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    param.write(f, names)?;
+                }
+                write!(f, ") => ")?;
+                ret.write(f, names)
+            }
+        }
+    }
+}
+
+fn var_name(index: usize) -> String {
+    let letter = (b'a' + (index % 26) as u8) as char;
+    match index / 26 {
+        0 => letter.to_string(),
+        n => format!("{}{}", letter, n),
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, &self.display_names())
+    }
+}
+
+/// A type generalized over some of its variables, as bound by `let`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Scheme {
+    pub vars: Vec<TypeVar>,
+    pub ty: Type,
+}
+
+impl Scheme {
+    pub fn monomorphic(ty: Type) -> Scheme {
+        Scheme { vars: vec![], ty }
+    }
+
+    pub fn free_type_vars(&self) -> BTreeSet<TypeVar> {
+        let mut vars = self.ty.free_type_vars();
+        for var in &self.vars {
+            vars.remove(var);
+        }
+        vars
+    }
+}
+
+impl fmt::Display for Scheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.ty)
+    }
+}
@@ -0,0 +1,35 @@
+use crate::compiler::Function;
+use crate::interpreter::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+pub(crate) type Globals = Rc<RefCell<HashMap<String, Value>>>;
+
+/// A compiled function with the values it captured and the globals of the
+/// module it was created in.
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub(crate) captures: Vec<Value>,
+    pub(crate) globals: Globals,
+}
+
+impl fmt::Debug for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Closure({})", self)
+    }
+}
+
+impl fmt::Display for Closure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.function)
+    }
+}
+
+// Functions are only equal to themselves.
+impl PartialEq for Closure {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,5 @@
+mod closure;
+mod vm;
+
+pub use closure::Closure;
+pub use vm::{eval, Vm};
@@ -0,0 +1,483 @@
+use super::closure::{Closure, Globals};
+use crate::compiler::{Capture, Compiler, Function, OpCode};
+use crate::diagnostics::ErrorCode;
+use crate::interpreter::{binary, unary, InterpreterError, Value, MAX_CALL_DEPTH};
+use crate::lexer::{Lexer, Token, TokenKind};
+use crate::loader::{ModuleLoader, INPUT};
+use crate::parser::{Parser, Program, Statement};
+use crate::typechecker::TypeChecker;
+use crate::BoxError;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+
+/// Runs source text on the VM, resolving imports against the current directory.
+pub fn eval(input: String) -> Result<Option<Value>, BoxError> {
+    let mut loader = ModuleLoader::new();
+    let file_id = loader.add_source(INPUT, &input);
+    let program =
+        Parser::from_lexer(Lexer::with_file(input.as_bytes(), file_id)).parse_program()?;
+
+    let mut checker = TypeChecker::new();
+    let mut vm = Vm::new();
+    loader.check_imports(&program, Path::new("."), &mut checker)?;
+    checker.check_program(&program)?;
+    loader.run_imports(&program, Path::new("."), &mut vm)?;
+
+    vm.run(&program)
+}
+
+struct Frame {
+    closure: Rc<Closure>,
+    ip: usize,
+    // The stack index of the frame's first local.
+    base: usize,
+}
+
+/// A stack machine running the bytecode produced by the compiler.
+#[derive(Default)]
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<Frame>,
+    globals: Globals,
+    modules: HashMap<String, HashMap<String, Value>>,
+    result: Option<Value>,
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm::default()
+    }
+
+    /// Compiles and runs a program, returning the value of its last
+    /// statement if it was an expression.
+    pub fn run(&mut self, program: &Program) -> Result<Option<Value>, BoxError> {
+        let function = Compiler::new().compile(program)?;
+        Ok(self.run_function(function)?)
+    }
+
+    /// Runs a compiled top level, defining its globals in this VM.
+    pub fn run_function(&mut self, function: Function) -> Result<Option<Value>, InterpreterError> {
+        let closure = Closure {
+            function: Rc::new(function),
+            captures: vec![],
+            globals: Rc::clone(&self.globals),
+        };
+        self.stack.clear();
+        self.frames.clear();
+        self.result = None;
+
+        self.execute(Frame {
+            closure: Rc::new(closure),
+            ip: 0,
+            base: 0,
+        })
+    }
+
+    /// Makes a module's exported values available to `import` statements naming `path`.
+    pub fn provide_module(&mut self, path: &str, values: HashMap<String, Value>) {
+        self.modules.insert(path.to_owned(), values);
+    }
+
+    /// Collects the values exported by an already run program.
+    pub fn exports(&self, program: &Program) -> HashMap<String, Value> {
+        let globals = self.globals.borrow();
+        let mut values = HashMap::new();
+
+        for statement in &program.statements {
+            if let Statement::Export { statement } = statement {
+                if let Statement::Let { name, .. } = statement.as_ref() {
+                    if let Some(value) = globals.get(&name.to_string()) {
+                        values.insert(name.to_string(), value.to_owned());
+                    }
+                }
+            }
+        }
+
+        values
+    }
+
+    fn execute(&mut self, mut frame: Frame) -> Result<Option<Value>, InterpreterError> {
+        loop {
+            let start = frame.ip;
+            let chunk = &frame.closure.function.chunk;
+            let code = match OpCode::from_byte(chunk.code[start]) {
+                Some(code) => code,
+                None => {
+                    return Err(error(
+                        &frame,
+                        start,
+                        ErrorCode::InvalidProgram,
+                        String::from("Invalid bytecode"),
+                    ))
+                }
+            };
+
+            let mut operands = [0; 2];
+            let mut offset = start + 1;
+            for (operand, width) in operands.iter_mut().zip(code.operands()) {
+                *operand = match width {
+                    1 => chunk.read_u8(offset),
+                    _ => chunk.read_u16(offset),
+                };
+                offset += width;
+            }
+            frame.ip = offset;
+            let [a, b] = operands;
+
+            match code {
+                OpCode::Constant => {
+                    let value = frame.closure.function.chunk.constants[a].to_owned();
+                    self.stack.push(value);
+                }
+                OpCode::True => self.stack.push(Value::Bool(true)),
+                OpCode::False => self.stack.push(Value::Bool(false)),
+                OpCode::Pop => {
+                    self.pop();
+                }
+                OpCode::Slide => {
+                    let top = self.pop();
+                    self.stack.truncate(self.stack.len() - a);
+                    self.stack.push(top);
+                }
+                OpCode::GetLocal => {
+                    let value = self.stack[frame.base + a].to_owned();
+                    self.stack.push(value);
+                }
+                OpCode::GetCapture => {
+                    let value = frame.closure.captures[a].to_owned();
+                    self.stack.push(value);
+                }
+                OpCode::GetCurrent => {
+                    self.stack.push(Value::Compiled(Rc::clone(&frame.closure)));
+                }
+                OpCode::GetGlobal => {
+                    let name = constant_name(&frame, a);
+                    let value = frame.closure.globals.borrow().get(&name).cloned();
+                    match value {
+                        Some(value) => self.stack.push(value),
+                        None => {
+                            return Err(error(
+                                &frame,
+                                start,
+                                ErrorCode::InvalidProgram,
+                                format!("Undefined variable {}", name),
+                            ))
+                        }
+                    }
+                }
+                OpCode::DefineGlobal => {
+                    let value = self.pop();
+                    frame
+                        .closure
+                        .globals
+                        .borrow_mut()
+                        .insert(constant_name(&frame, a), value);
+                }
+                OpCode::GetImport => {
+                    let (path, name) = (constant_name(&frame, a), constant_name(&frame, b));
+                    let values = match self.modules.get(&path) {
+                        Some(values) => values,
+                        None => {
+                            return Err(error(
+                                &frame,
+                                start,
+                                ErrorCode::InvalidProgram,
+                                format!("Unresolved module {}", path),
+                            ))
+                        }
+                    };
+                    match values.get(&name) {
+                        Some(value) => {
+                            let value = value.to_owned();
+                            self.stack.push(value);
+                        }
+                        None => {
+                            return Err(error(
+                                &frame,
+                                start,
+                                ErrorCode::InvalidProgram,
+                                format!("Module {0} does not export {1}", path, name),
+                            ))
+                        }
+                    }
+                }
+                OpCode::Construct => {
+                    let args = self.stack.split_off(self.stack.len() - b);
+                    self.stack.push(Value::Constructor {
+                        name: constant_name(&frame, a),
+                        args,
+                    });
+                }
+                OpCode::Record => {
+                    let items = self.stack.split_off(self.stack.len() - 2 * a);
+                    let mut fields = vec![];
+                    let mut items = items.into_iter();
+                    while let (Some(name), Some(value)) = (items.next(), items.next()) {
+                        if let Value::Str(name) = name {
+                            fields.push((name, value));
+                        }
+                    }
+                    self.stack.push(Value::Record { fields });
+                }
+                OpCode::Tuple => {
+                    let elements = self.stack.split_off(self.stack.len() - a);
+                    self.stack.push(Value::Tuple(elements));
+                }
+                OpCode::GetField => {
+                    let name = constant_name(&frame, a);
+                    let value = match self.pop() {
+                        Value::Record { fields } => fields
+                            .into_iter()
+                            .find(|(field, _)| *field == name)
+                            .map(|(_, value)| value)
+                            .ok_or_else(|| format!("Undefined field {}", name)),
+                        other => Err(format!(
+                            "Cannot read field {0} of a {1}",
+                            name,
+                            other.type_name()
+                        )),
+                    };
+                    match value {
+                        Ok(value) => self.stack.push(value),
+                        Err(text) => {
+                            return Err(error(&frame, start, ErrorCode::InvalidOperand, text))
+                        }
+                    }
+                }
+                OpCode::Index => match self.pop() {
+                    Value::Constructor { mut args, .. } | Value::Tuple(mut args)
+                        if a < args.len() =>
+                    {
+                        self.stack.push(args.swap_remove(a));
+                    }
+                    other => {
+                        return Err(error(
+                            &frame,
+                            start,
+                            ErrorCode::InvalidOperand,
+                            format!("Cannot destructure a {}", other.type_name()),
+                        ))
+                    }
+                },
+                OpCode::IsVariant => {
+                    let matches = match self.pop() {
+                        Value::Constructor { name, args } => {
+                            name == constant_name(&frame, a) && args.len() == b
+                        }
+                        _ => false,
+                    };
+                    self.stack.push(Value::Bool(matches));
+                }
+                OpCode::IsEqual => {
+                    let value = self.pop();
+                    let matches = value == frame.closure.function.chunk.constants[a];
+                    self.stack.push(Value::Bool(matches));
+                }
+                OpCode::Closure => {
+                    let function = Rc::clone(&frame.closure.function.chunk.functions[a]);
+                    let captures = function
+                        .captures
+                        .iter()
+                        .map(|capture| match capture {
+                            Capture::Local(slot) => self.stack[frame.base + slot].to_owned(),
+                            Capture::Capture(index) => frame.closure.captures[*index].to_owned(),
+                            Capture::Current => Value::Compiled(Rc::clone(&frame.closure)),
+                        })
+                        .collect();
+                    let closure = Closure {
+                        function,
+                        captures,
+                        globals: Rc::clone(&frame.closure.globals),
+                    };
+                    self.stack.push(Value::Compiled(Rc::new(closure)));
+                }
+                OpCode::Call => {
+                    let base = self.stack.len() - a;
+                    let closure = match &self.stack[base - 1] {
+                        Value::Compiled(closure) => Rc::clone(closure),
+                        other => {
+                            let text = format!("Cannot call a {}", other.type_name());
+                            return Err(error(&frame, start, ErrorCode::InvalidOperand, text));
+                        }
+                    };
+                    if closure.function.arity != a {
+                        let text = format!(
+                            "Function expects {0} arguments, found {1}",
+                            closure.function.arity, a
+                        );
+                        return Err(error(&frame, start, ErrorCode::InvalidOperand, text));
+                    }
+                    if self.frames.len() == MAX_CALL_DEPTH {
+                        return Err(error(
+                            &frame,
+                            start,
+                            ErrorCode::StackOverflow,
+                            String::from("Stack overflow"),
+                        ));
+                    }
+
+                    let callee = Frame {
+                        closure,
+                        ip: 0,
+                        base,
+                    };
+                    self.frames.push(std::mem::replace(&mut frame, callee));
+                }
+                OpCode::Return => {
+                    let result = self.pop();
+                    self.stack.truncate(frame.base - 1);
+                    self.stack.push(result);
+                    frame = match self.frames.pop() {
+                        Some(caller) => caller,
+                        None => {
+                            return Err(error(
+                                &frame,
+                                start,
+                                ErrorCode::InvalidProgram,
+                                String::from("Invalid bytecode"),
+                            ))
+                        }
+                    };
+                }
+                OpCode::Negate | OpCode::Not => {
+                    let kind = match code {
+                        OpCode::Not => TokenKind::Bang,
+                        _ => TokenKind::Minus,
+                    };
+                    let right = self.pop();
+                    let value = unary(&operator(&frame, start, kind), right)?;
+                    self.stack.push(value);
+                }
+                OpCode::Add
+                | OpCode::Subtract
+                | OpCode::Multiply
+                | OpCode::Divide
+                | OpCode::Equal
+                | OpCode::NotEqual
+                | OpCode::Greater
+                | OpCode::GreaterEqual
+                | OpCode::Less
+                | OpCode::LessEqual => {
+                    let kind = match code {
+                        OpCode::Add => TokenKind::Plus,
+                        OpCode::Subtract => TokenKind::Minus,
+                        OpCode::Multiply => TokenKind::Star,
+                        OpCode::Divide => TokenKind::Slash,
+                        OpCode::Equal => TokenKind::EqualEqual,
+                        OpCode::NotEqual => TokenKind::BangEqual,
+                        OpCode::Greater => TokenKind::Greater,
+                        OpCode::GreaterEqual => TokenKind::GreaterEqual,
+                        OpCode::Less => TokenKind::Less,
+                        _ => TokenKind::LessEqual,
+                    };
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = binary(left, &operator(&frame, start, kind), right)?;
+                    self.stack.push(value);
+                }
+                OpCode::Jump => frame.ip += a,
+                OpCode::JumpIfFalse => match self.pop() {
+                    Value::Bool(true) => {}
+                    Value::Bool(false) => frame.ip += a,
+                    other => {
+                        return Err(error(
+                            &frame,
+                            start,
+                            ErrorCode::InvalidOperand,
+                            format!("Expect a boolean condition, found a {}", other.type_name()),
+                        ))
+                    }
+                },
+                OpCode::NoMatch => {
+                    let subject = self.pop();
+                    return Err(error(
+                        &frame,
+                        start,
+                        ErrorCode::NoMatch,
+                        format!("No pattern matched {}", subject),
+                    ));
+                }
+                OpCode::Print => println!("{}", self.pop()),
+                OpCode::SetResult => self.result = Some(self.pop()),
+                OpCode::ClearResult => self.result = None,
+                OpCode::Halt => return Ok(self.result.take()),
+            }
+        }
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().expect("The compiler balances the stack")
+    }
+}
+
+fn constant_name(frame: &Frame, index: usize) -> String {
+    match &frame.closure.function.chunk.constants[index] {
+        Value::Str(name) => name.to_owned(),
+        other => other.to_string(),
+    }
+}
+
+fn error(frame: &Frame, offset: usize, code: ErrorCode, text: String) -> InterpreterError {
+    let position = frame.closure.function.chunk.position(offset);
+    InterpreterError::at(code, position.line, position.col, position.span, text)
+}
+
+// Rebuilds the operator token an instruction came from, so operators fail
+// with the same errors as in the interpreter.
+fn operator(frame: &Frame, offset: usize, kind: TokenKind) -> Token {
+    let position = frame.closure.function.chunk.position(offset);
+    Token {
+        kind,
+        literal: None,
+        line: position.line,
+        col: position.col,
+        span: position.span,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval_value(input: &str) -> Value {
+        eval(input.to_string()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn runs_statements_and_matches() {
+        let source = "type Size = { w: Num, h: Num };
+            type Shape = Circle(Num) | Rect(Size);
+            let area = shape => match shape {
+                Circle(r) => 3 * r * r,
+                Rect({ w, h: 2 }) => w + w,
+                Rect(r) => r.w * r.h,
+            };
+            let x = 2;
+            if x > 1 { let y = area(Rect({ w: 3, h: 2 })); (y, area(Circle(1))) } else { (0, 0) }";
+        assert_eq!(
+            eval_value(source),
+            Value::Tuple(vec![Value::Num(6.0), Value::Num(3.0)])
+        );
+        assert_eq!(eval("if false { 1 }".to_string()).unwrap(), None);
+    }
+
+    #[test]
+    fn calls_closures_and_local_recursive_functions() {
+        let source = "let adder = n => x => x + n;
+            if true {
+                let fact = n => match n { 0 => 1, _ => n * fact(n - 1) };
+                let add = adder(fact(3));
+                add(1)
+            }";
+        assert_eq!(eval_value(source), Value::Num(7.0));
+    }
+
+    #[test]
+    fn reports_runtime_errors_with_positions() {
+        let source = "let loop = n => loop(n + 1);\nloop(0)";
+        let err = eval(source.to_string()).unwrap_err().to_string();
+        assert!(err.contains("Stack overflow"), "{}", err);
+        assert!(err.contains("line 1"), "{}", err);
+    }
+}
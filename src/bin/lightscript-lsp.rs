@@ -0,0 +1,21 @@
+use lightscript_lib::{interpreter::STACK_SIZE, lsp, BoxError};
+use lsp_server::Connection;
+use std::{process, thread};
+
+// Analysis runs on a thread with room for deeply nested documents.
+fn main() -> Result<(), BoxError> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap_or_else(|_| process::exit(101))
+}
+
+// Speaks the Language Server Protocol over standard input and output.
+fn run() -> Result<(), BoxError> {
+    let (connection, io_threads) = Connection::stdio();
+    lsp::run(&connection)?;
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
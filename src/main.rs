@@ -1,7 +1,30 @@
-use clap::{App, Arg};
-use lightscript_lib::{interpreter, repl::REPL, BoxError};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use lightscript_lib::{
+    diagnostics::{self, Diagnostic, ErrorCode, Renderer, ToDiagnostics},
+    formatter::{self, FormatOptions},
+    interpreter::STACK_SIZE,
+    loader::{Backend, ModuleLoader},
+    repl::REPL,
+    source::SourceMap,
+    BoxError,
+};
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+use std::process;
+use std::thread;
 
+// Programs run on a thread with room for deep recursion.
 fn main() -> Result<(), BoxError> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)?
+        .join()
+        .unwrap_or_else(|_| process::exit(101))
+}
+
+fn run() -> Result<(), BoxError> {
     let matches = App::new("LightScript")
         .version("0.0.1")
         .author("Gustavo K. <g.konrad@outlook.com>")
@@ -9,16 +32,150 @@ fn main() -> Result<(), BoxError> {
         .arg(
             Arg::with_name("file")
                 .short("f")
+                .takes_value(true)
                 .help("Sets an optional input file")
                 .required(false),
         )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .takes_value(true)
+                .possible_values(&["interpreter", "vm", "js"])
+                .default_value("interpreter")
+                .help("Sets what runs the input file, js writes JavaScript next to each module"),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .takes_value(true)
+                .possible_values(&["auto", "always", "never"])
+                .default_value("auto")
+                .help("Sets when errors are highlighted"),
+        )
+        .arg(
+            Arg::with_name("error-format")
+                .long("error-format")
+                .takes_value(true)
+                .possible_values(&["human", "json"])
+                .default_value("human")
+                .help("Sets how errors are reported, json writes one object per line"),
+        )
+        .arg(
+            Arg::with_name("explain")
+                .long("explain")
+                .takes_value(true)
+                .value_name("CODE")
+                .help("Explains an error code such as T0004"),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Formats files in place, or standard input to standard output")
+                .arg(
+                    Arg::with_name("check")
+                        .long("check")
+                        .help("Lists the files that are not formatted instead of writing them"),
+                )
+                .arg(
+                    Arg::with_name("line-width")
+                        .long("line-width")
+                        .takes_value(true)
+                        .value_name("COLUMNS")
+                        .default_value("80")
+                        .help("Sets how wide lines may get before they are broken"),
+                )
+                .arg(Arg::with_name("files").multiple(true)),
+        )
         .get_matches();
 
-    if let Some(path) = matches.value_of("file") {
-        // Get the file into a buffer, pass the buffer
-        interpreter::eval(std::fs::read_to_string(path)?)?;
+    let color = match matches.value_of("color") {
+        Some("always") => true,
+        Some("never") => false,
+        _ => io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+    };
+
+    if let Some(code) = matches.value_of("explain") {
+        let code: ErrorCode = code.parse()?;
+        print!("{}", code.explanation());
+        return Ok(());
+    }
+
+    let json = matches.value_of("error-format") == Some("json");
+    if let Some(fmt) = matches.subcommand_matches("fmt") {
+        if !format_files(fmt, color, json)? {
+            process::exit(1);
+        }
+    } else if let Some(path) = matches.value_of("file") {
+        let backend: Backend = matches.value_of("backend").unwrap_or_default().parse()?;
+        let mut loader = ModuleLoader::with_backend(backend);
+        if let Err(err) = loader.run_file(Path::new(path)) {
+            report(&err.to_diagnostics(), loader.source_map(), color, json);
+            process::exit(1);
+        }
     } else {
         REPL::new().run()?;
     }
     Ok(())
 }
+
+fn report(reports: &[Diagnostic], sources: &SourceMap, color: bool, json: bool) {
+    if json {
+        for report in reports {
+            eprintln!("{}", diagnostics::to_json(report, sources));
+        }
+        return;
+    }
+    let renderer = Renderer::with_color(color);
+    eprint!("{}", renderer.render_all(reports, sources));
+    if let Some(code) = reports.iter().find_map(|report| report.code) {
+        eprintln!(
+            "\nFor more information about an error, try `lightscript --explain {}`.",
+            code
+        );
+    }
+}
+
+// Formats each file in place, or only lists the ones that would change with
+// `--check`. Without files it formats standard input. Returns whether every
+// file could be formatted and, when checking, already was.
+fn format_files(matches: &ArgMatches, color: bool, json: bool) -> Result<bool, BoxError> {
+    let options = FormatOptions {
+        line_width: matches.value_of("line-width").unwrap_or_default().parse()?,
+        ..FormatOptions::default()
+    };
+    let check = matches.is_present("check");
+    let mut sources = SourceMap::new();
+    let mut ok = true;
+
+    let paths: Vec<&str> = matches.values_of("files").map_or(vec![], Iterator::collect);
+    if paths.is_empty() {
+        let mut source = String::new();
+        io::stdin().read_to_string(&mut source)?;
+        let file_id = sources.add("<stdin>", &source);
+        match formatter::format(&source, file_id, &options) {
+            Ok(formatted) if check => ok = formatted == source,
+            Ok(formatted) => print!("{}", formatted),
+            Err(err) => {
+                report(&err.to_diagnostics(), &sources, color, json);
+                ok = false;
+            }
+        }
+    }
+
+    for path in paths {
+        let source = fs::read_to_string(path)?;
+        let file_id = sources.add(path, &source);
+        match formatter::format(&source, file_id, &options) {
+            Ok(formatted) if formatted == source => {}
+            Ok(_) if check => {
+                println!("{}", path);
+                ok = false;
+            }
+            Ok(formatted) => fs::write(path, formatted)?,
+            Err(err) => {
+                report(&err.to_diagnostics(), &sources, color, json);
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
@@ -0,0 +1,82 @@
+use lightscript_lib::interpreter::{self, MAX_CALL_DEPTH, STACK_SIZE};
+use lightscript_lib::loader::{Backend, ModuleLoader};
+use lightscript_lib::vm;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// Every `.ls` file in the corpus starts with a comment holding the value it
+// evaluates to, `none`, or a fragment of the error it fails with.
+fn expectation(path: &Path) -> String {
+    let source = fs::read_to_string(path).unwrap();
+    let first = source.lines().next().unwrap_or_default();
+    first
+        .strip_prefix("// expect")
+        .unwrap_or_else(|| panic!("{} has no expectation", path.display()))
+        .to_string()
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ls"))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn run_corpus(backend: Backend) {
+    for path in corpus() {
+        let expected = expectation(&path);
+        let result = ModuleLoader::with_backend(backend).run_file(&path);
+
+        match (expected.strip_prefix(" error: "), result) {
+            (Some(fragment), Err(err)) => {
+                assert!(
+                    err.to_string().contains(fragment),
+                    "{}: {}",
+                    path.display(),
+                    err
+                )
+            }
+            (None, Ok(value)) => {
+                let value = value.map_or("none".to_string(), |value| value.to_string());
+                assert_eq!(format!(": {}", value), expected, "{}", path.display());
+            }
+            (_, result) => panic!("{}: unexpected {:?}", path.display(), result),
+        }
+    }
+}
+
+#[test]
+fn interpreter_passes_the_corpus() {
+    run_corpus(Backend::Interpreter);
+}
+
+#[test]
+fn vm_passes_the_corpus() {
+    run_corpus(Backend::Vm);
+}
+
+#[test]
+fn backends_share_the_call_depth_limit() {
+    let sum = "let sum = (n) => match n { 0 => 0, _ => n + sum(n - 1) };";
+    let deep = format!("{0} sum(5000)", sum);
+    let too_deep = format!("{0} sum({1})", sum, MAX_CALL_DEPTH);
+
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            for eval in [interpreter::eval, vm::eval] {
+                let value = eval(deep.to_owned()).unwrap().unwrap();
+                assert_eq!(value.to_string(), "12502500");
+                let err = eval(too_deep.to_owned()).unwrap_err();
+                assert!(err.to_string().contains("Stack overflow"), "{}", err);
+            }
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+}
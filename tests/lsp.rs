@@ -0,0 +1,250 @@
+use lightscript_lib::lsp;
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidOpenTextDocument, Exit, Initialized, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, GotoDefinition, HoverRequest, Initialize, References, Request as _,
+    SemanticTokensFullRequest, Shutdown,
+};
+use lsp_types::{
+    CompletionItemKind, CompletionResponse, GotoDefinitionResponse, Hover, HoverContents, Location,
+    Position, PublishDiagnosticsParams, Range, SemanticTokensResult, Url,
+};
+use serde_json::{json, Value};
+use std::thread::{self, JoinHandle};
+
+// A client talking to a server running on another thread.
+struct Client {
+    connection: Connection,
+    server: Option<JoinHandle<()>>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let (server, connection) = Connection::memory();
+        let server = thread::spawn(move || lsp::run(&server).unwrap());
+        let mut client = Client {
+            connection,
+            server: Some(server),
+            next_id: 0,
+        };
+        client.request(Initialize::METHOD, json!({ "capabilities": {} }));
+        client.notify(Initialized::METHOD, json!({}));
+        client
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        let request = Request::new(id.clone(), method.to_owned(), params);
+        self.connection.sender.send(request.into()).unwrap();
+        match self.connection.receiver.recv().unwrap() {
+            Message::Response(Response {
+                id: got,
+                result,
+                error,
+            }) => {
+                assert_eq!(got, id);
+                assert!(error.is_none(), "{:?}", error);
+                result.unwrap_or(Value::Null)
+            }
+            message => panic!("expected a response, got {:?}", message),
+        }
+    }
+
+    fn notify(&self, method: &str, params: Value) {
+        let notification = Notification::new(method.to_owned(), params);
+        self.connection.sender.send(notification.into()).unwrap();
+    }
+
+    fn diagnostics(&self) -> PublishDiagnosticsParams {
+        match self.connection.receiver.recv().unwrap() {
+            Message::Notification(notification)
+                if notification.method == PublishDiagnostics::METHOD =>
+            {
+                serde_json::from_value(notification.params).unwrap()
+            }
+            message => panic!("expected diagnostics, got {:?}", message),
+        }
+    }
+
+    fn open(&self, uri: &Url, text: &str) -> PublishDiagnosticsParams {
+        self.notify(
+            DidOpenTextDocument::METHOD,
+            json!({
+                "textDocument": { "uri": uri, "languageId": "lightscript", "version": 1, "text": text }
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn change(&self, uri: &Url, text: &str) -> PublishDiagnosticsParams {
+        self.notify(
+            DidChangeTextDocument::METHOD,
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": text }]
+            }),
+        );
+        self.diagnostics()
+    }
+
+    fn at(uri: &Url, line: u32, character: u32) -> Value {
+        json!({ "textDocument": { "uri": uri }, "position": Position::new(line, character) })
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            return;
+        }
+        self.request(Shutdown::METHOD, Value::Null);
+        self.notify(Exit::METHOD, Value::Null);
+        self.server.take().unwrap().join().unwrap();
+    }
+}
+
+const SOURCE: &str = "\
+/// Adds one.
+let inc = n => n + 1;
+let two = inc(1);
+inc(two)
+";
+
+fn uri() -> Url {
+    Url::parse("untitled:main.ls").unwrap()
+}
+
+fn range(line: u32, start: u32, end: u32) -> Range {
+    Range::new(Position::new(line, start), Position::new(line, end))
+}
+
+#[test]
+fn publishes_diagnostics_as_the_document_changes() {
+    let client = Client::start();
+    let uri = uri();
+
+    let published = client.open(&uri, SOURCE);
+    assert_eq!(published.uri, uri);
+    assert!(
+        published.diagnostics.is_empty(),
+        "{:?}",
+        published.diagnostics
+    );
+
+    let published = client.change(&uri, "let a = 1;\na + \"b\"\n");
+    assert_eq!(published.diagnostics.len(), 1);
+    assert_eq!(published.diagnostics[0].range.start.line, 1);
+
+    let published = client.change(&uri, "let = 1;");
+    assert_eq!(published.diagnostics.len(), 1);
+    assert_eq!(published.diagnostics[0].range.start, Position::new(0, 4));
+}
+
+#[test]
+fn answers_questions_about_names() {
+    let mut client = Client::start();
+    let uri = uri();
+    client.open(&uri, SOURCE);
+
+    let hover: Hover =
+        serde_json::from_value(client.request(HoverRequest::METHOD, Client::at(&uri, 1, 5)))
+            .unwrap();
+    let HoverContents::Markup(content) = hover.contents else {
+        panic!("expected markdown, got {:?}", hover.contents);
+    };
+    assert!(
+        content.value.contains("inc: (Num) => Num"),
+        "{}",
+        content.value
+    );
+    assert!(content.value.contains("Adds one."), "{}", content.value);
+    assert_eq!(hover.range, Some(range(1, 4, 7)));
+
+    let definition: GotoDefinitionResponse =
+        serde_json::from_value(client.request(GotoDefinition::METHOD, Client::at(&uri, 3, 5)))
+            .unwrap();
+    assert_eq!(
+        definition,
+        GotoDefinitionResponse::Scalar(Location::new(uri.clone(), range(2, 4, 7)))
+    );
+
+    let mut params = Client::at(&uri, 1, 4);
+    params["context"] = json!({ "includeDeclaration": true });
+    let references: Vec<Location> =
+        serde_json::from_value(client.request(References::METHOD, params)).unwrap();
+    let ranges: Vec<Range> = references
+        .into_iter()
+        .map(|location| location.range)
+        .collect();
+    assert_eq!(ranges, [range(1, 4, 7), range(2, 10, 13), range(3, 0, 3)]);
+}
+
+#[test]
+fn completes_keywords_and_names_in_scope() {
+    let mut client = Client::start();
+    let uri = uri();
+    client.open(&uri, SOURCE);
+
+    let completion: CompletionResponse =
+        serde_json::from_value(client.request(Completion::METHOD, Client::at(&uri, 1, 15)))
+            .unwrap();
+    let CompletionResponse::Array(items) = completion else {
+        panic!("expected a list of completions");
+    };
+    let label = |label: &str| items.iter().find(|item| item.label == label);
+
+    assert_eq!(
+        label("match").unwrap().kind,
+        Some(CompletionItemKind::KEYWORD)
+    );
+    assert_eq!(label("n").unwrap().detail.as_deref(), Some("Num"));
+    assert!(label("inc").is_some());
+    assert!(label("two").is_none());
+}
+
+#[test]
+fn classifies_tokens_for_highlighting() {
+    let mut client = Client::start();
+    let uri = uri();
+    client.open(&uri, "type T = A | B;\n/* two\nlines */ A");
+
+    let params = json!({ "textDocument": { "uri": uri } });
+    let tokens: SemanticTokensResult =
+        serde_json::from_value(client.request(SemanticTokensFullRequest::METHOD, params)).unwrap();
+    let SemanticTokensResult::Tokens(tokens) = tokens else {
+        panic!("expected every token");
+    };
+    let data: Vec<[u32; 4]> = tokens
+        .data
+        .iter()
+        .map(|token| {
+            [
+                token.delta_line,
+                token.delta_start,
+                token.length,
+                token.token_type,
+            ]
+        })
+        .collect();
+    // A keyword, a type, constructors and operators, then a comment on two
+    // lines before another constructor.
+    assert_eq!(
+        data,
+        [
+            [0, 0, 4, 0],
+            [0, 5, 1, 6],
+            [0, 2, 1, 5],
+            [0, 2, 1, 7],
+            [0, 2, 1, 5],
+            [0, 2, 1, 7],
+            [1, 0, 6, 4],
+            [1, 0, 8, 4],
+            [0, 9, 1, 7],
+        ]
+    );
+}